@@ -0,0 +1,189 @@
+//! Heap accounting and generated corpora shared by the benchmarks.
+#![allow(dead_code)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Heap in use by the value returned from a closure, and the peak heap
+/// used while running it, in bytes.
+pub struct HeapUsage {
+    pub retained: usize,
+    pub peak: usize,
+}
+
+pub fn measure_heap<T>(f: impl FnOnce() -> T) -> (T, HeapUsage) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let value = f();
+    let usage = HeapUsage {
+        retained: ALLOCATED.load(Ordering::Relaxed).saturating_sub(before),
+        peak: PEAK.load(Ordering::Relaxed).saturating_sub(before),
+    };
+    (value, usage)
+}
+
+/// Size of the generated documents in megabytes, from `XMLINFOSET_BENCH_MB`.
+pub fn corpus_megabytes(default: usize) -> usize {
+    std::env::var("XMLINFOSET_BENCH_MB")
+        .ok()
+        .and_then(|mb| mb.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Elements nested `depth` levels deep, repeated until `target_len`.
+pub fn deep_nesting(target_len: usize, depth: usize) -> String {
+    let mut doc = String::with_capacity(target_len + 4096);
+    doc.push_str("<root>");
+    while doc.len() < target_len {
+        for i in 0..depth {
+            doc.push_str(&format!("<level{i}>"));
+        }
+        doc.push_str("leaf");
+        for i in (0..depth).rev() {
+            doc.push_str(&format!("</level{i}>"));
+        }
+    }
+    doc.push_str("</root>");
+    doc
+}
+
+/// Empty elements with `attributes` attributes each.
+pub fn wide_attributes(target_len: usize, attributes: usize) -> String {
+    let mut doc = String::with_capacity(target_len + 4096);
+    doc.push_str("<root>");
+    let mut n = 0;
+    while doc.len() < target_len {
+        doc.push_str("<item");
+        for i in 0..attributes {
+            doc.push_str(&format!(" attr{i}=\"value {n} &amp; {i}\""));
+        }
+        doc.push_str("/>");
+        n += 1;
+    }
+    doc.push_str("</root>");
+    doc
+}
+
+/// Paragraphs of character data with a few references and CDATA sections.
+pub fn text_heavy(target_len: usize) -> String {
+    let mut doc = String::with_capacity(target_len + 4096);
+    doc.push_str("<book>");
+    let mut n = 0;
+    while doc.len() < target_len {
+        doc.push_str(&format!(
+            "<p>Paragraph {n}: Lorem ipsum dolor sit amet, consectetur adipiscing elit, \
+             sed do eiusmod tempor incididunt &lt;ut&gt; labore et dolore magna aliqua. \
+             Ut enim ad minim veniam, quis nostrud exercitation &#169; ullamco laboris.\n\
+             <![CDATA[Duis aute irure dolor <in> reprehenderit.]]></p>\n"
+        ));
+        n += 1;
+    }
+    doc.push_str("</book>");
+    doc
+}
+
+/// A root declaring `namespaces` prefixes, with records that use them and
+/// occasionally redeclare the default namespace.
+pub fn namespace_heavy(target_len: usize, namespaces: usize) -> String {
+    let mut doc = String::with_capacity(target_len + 4096);
+    doc.push_str("<root xmlns=\"urn:default\"");
+    for i in 0..namespaces {
+        doc.push_str(&format!(" xmlns:ns{i}=\"urn:example:namespace:{i}\""));
+    }
+    doc.push('>');
+    let mut n = 0;
+    while doc.len() < target_len {
+        let ns = n % namespaces;
+        if n % 16 == 0 {
+            doc.push_str(&format!("<group xmlns=\"urn:example:group:{n}\">"));
+        }
+        doc.push_str(&format!(
+            "<ns{ns}:record id=\"r{n}\"><ns{ns}:name>item {n}</ns{ns}:name>\
+             <value ns{ns}:unit=\"m\">{n}</value></ns{ns}:record>"
+        ));
+        if n % 16 == 15 {
+            doc.push_str("</group>");
+        }
+        n += 1;
+    }
+    if n % 16 != 0 {
+        doc.push_str("</group>");
+    }
+    doc.push_str("</root>");
+    doc
+}
+
+/// Elements nested `depth` levels deep, each declaring a namespace of its
+/// own, repeated until `target_len`.
+pub fn deep_namespaces(target_len: usize, depth: usize) -> String {
+    let mut doc = String::with_capacity(target_len + 4096);
+    doc.push_str("<root>");
+    while doc.len() < target_len {
+        for i in 0..depth {
+            doc.push_str(&format!(
+                "<p{i}:level xmlns:p{i}=\"urn:example:level:{i}\">"
+            ));
+        }
+        doc.push_str("leaf");
+        for i in (0..depth).rev() {
+            doc.push_str(&format!("</p{i}:level>"));
+        }
+    }
+    doc.push_str("</root>");
+    doc
+}
+
+/// An internal subset with `entities` entities and attribute list
+/// declarations, and content that references and defaults them.
+pub fn dtd_heavy(target_len: usize, entities: usize) -> String {
+    let mut doc = String::with_capacity(target_len + 4096);
+    doc.push_str("<!DOCTYPE catalog [\n");
+    doc.push_str("<!ELEMENT catalog (entry*)>\n");
+    doc.push_str("<!ELEMENT entry (#PCDATA)>\n");
+    doc.push_str(
+        "<!ATTLIST entry id ID #REQUIRED kind (a|b|c) \"a\" \
+         ref IDREF #IMPLIED note CDATA \"none\">\n",
+    );
+    for i in 0..entities {
+        doc.push_str(&format!(
+            "<!ENTITY ent{i} \"replacement text {i} with &#x26;amp; inside\">\n"
+        ));
+    }
+    doc.push_str("]>\n<catalog>");
+    let mut n = 0;
+    while doc.len() < target_len {
+        let e = n % entities;
+        let reference = if n > 0 {
+            format!(" ref=\"e{}\"", n - 1)
+        } else {
+            String::new()
+        };
+        doc.push_str(&format!(
+            "<entry id=\"e{n}\"{reference}>&ent{e}; and more text</entry>\n"
+        ));
+        n += 1;
+    }
+    doc.push_str("</catalog>");
+    doc
+}
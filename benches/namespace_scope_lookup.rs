@@ -0,0 +1,83 @@
+//! Time taken to list the in-scope namespaces of every element, and to look
+//! up the namespace of the prefix of every element, on generated corpora.
+//!
+//! Run with `cargo bench --bench namespace_scope_lookup`. The size of each
+//! corpus in megabytes is taken from `XMLINFOSET_BENCH_MB`, 16 by default.
+
+mod common;
+
+use std::time::{Duration, Instant};
+use xmlinfoset::visit::{self, InfoSetVisitor};
+use xmlinfoset::{ElementInfoItem, InfoSet};
+
+const ROUNDS: usize = 5;
+
+/// The fastest of `ROUNDS` runs.
+fn best_of<T>(mut f: impl FnMut() -> T) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            drop(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+#[derive(Default)]
+struct Elements(Vec<ElementInfoItem>);
+
+impl<'a> InfoSetVisitor<'a> for Elements {
+    fn visit_element(&mut self, infoset: &'a InfoSet<'a>, item: ElementInfoItem) {
+        self.0.push(item);
+        visit::walk_element(self, infoset, item)
+    }
+}
+
+fn bench(name: &str, doc: &str) {
+    let infoset = xmlinfoset::parse::parse(doc).unwrap();
+    let mut elements = Elements::default();
+    visit::visit_infoset(&mut elements, &infoset);
+    let elements = elements.0;
+    let mut namespaces = 0;
+    let in_scope = best_of(|| {
+        namespaces = elements
+            .iter()
+            .map(|element| element.in_scope_namespaces(&infoset).len())
+            .sum::<usize>();
+    });
+    let prefixes: Vec<_> = elements
+        .iter()
+        .map(|element| {
+            element
+                .prefix(&infoset)
+                .map(|prefix| prefix.get(&infoset).unwrap().to_owned())
+        })
+        .collect();
+    let lookup = best_of(|| {
+        elements
+            .iter()
+            .zip(&prefixes)
+            .filter(|(element, prefix)| {
+                element
+                    .namespace_for_prefix(&infoset, prefix.as_deref())
+                    .is_some()
+            })
+            .count()
+    });
+    let per_element = |elapsed: Duration| elapsed.as_nanos() / elements.len().max(1) as u128;
+    println!(
+        "{name:<16} {:>9} elements, {:>5.1} namespaces in scope on average | \
+         in-scope list {:>6} ns/element | prefix lookup {:>6} ns/element",
+        elements.len(),
+        namespaces as f64 / elements.len().max(1) as f64,
+        per_element(in_scope),
+        per_element(lookup),
+    );
+}
+
+fn main() {
+    let len = common::corpus_megabytes(16) << 20;
+    bench("namespace heavy", &common::namespace_heavy(len, 32));
+    bench("deep namespaces", &common::deep_namespaces(len, 64));
+}
@@ -0,0 +1,56 @@
+//! Heap used by the in-scope namespaces of a large, namespace-heavy document.
+//!
+//! Run with `cargo bench --bench namespace_scope_memory`. The size of the
+//! generated document in megabytes is taken from `XMLINFOSET_BENCH_MB`,
+//! 100 by default.
+//!
+//! Besides the heap retained by the info set, the benchmark reports the heap
+//! that a list of the in-scope namespaces per element, as stored before the
+//! scopes were shared, would take for the same document. Running the
+//! benchmark on the commit before the scope chain was introduced gives the
+//! measured figure for that layout.
+
+mod common;
+
+use std::mem::size_of;
+use std::time::Instant;
+use xmlinfoset::visit::{self, InfoSetVisitor};
+use xmlinfoset::{ElementInfoItem, InfoSet, NSInfoItem};
+
+/// Heap taken by a `Vec<NSInfoItem>` of the in-scope namespaces of every
+/// element.
+#[derive(Default)]
+struct PerElementLists {
+    bytes: usize,
+}
+
+impl<'a> InfoSetVisitor<'a> for PerElementLists {
+    fn visit_element(&mut self, infoset: &'a InfoSet<'a>, item: ElementInfoItem) {
+        self.bytes += item.in_scope_namespaces(infoset).len() * size_of::<NSInfoItem>();
+        visit::walk_element(self, infoset, item)
+    }
+}
+
+fn main() {
+    let doc = common::namespace_heavy(common::corpus_megabytes(100) << 20, 32);
+    let start = Instant::now();
+    let ((infoset, statistics), heap) =
+        common::measure_heap(|| xmlinfoset::parse::parse_with_statistics(&doc).unwrap());
+    let elapsed = start.elapsed();
+    let mut lists = PerElementLists::default();
+    visit::visit_infoset(&mut lists, &infoset);
+    println!(
+        "{} MB, {} elements: parsed in {:.2?}, {} MB retained, {} MB peak, {} bytes per element",
+        doc.len() >> 20,
+        statistics.element_count,
+        elapsed,
+        heap.retained >> 20,
+        heap.peak >> 20,
+        heap.retained / statistics.element_count.max(1),
+    );
+    println!(
+        "per-element in-scope namespace lists would take {} MB",
+        lists.bytes >> 20,
+    );
+    drop(infoset);
+}
@@ -0,0 +1,60 @@
+//! Throughput and heap use of `parse::parse` on generated corpora, next to a
+//! bare `xmlparser::Tokenizer` pass over the same input.
+//!
+//! Run with `cargo bench --bench parse_throughput`. The size of each corpus
+//! in megabytes is taken from `XMLINFOSET_BENCH_MB`, 16 by default.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+const ROUNDS: usize = 5;
+
+/// The fastest of `ROUNDS` runs.
+fn best_of<T>(mut f: impl FnMut() -> T) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            drop(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn megabytes_per_second(len: usize, elapsed: Duration) -> f64 {
+    len as f64 / (1 << 20) as f64 / elapsed.as_secs_f64()
+}
+
+fn tokenize(doc: &str) -> usize {
+    xmlparser::Tokenizer::from(doc)
+        .map(|token| token.unwrap())
+        .count()
+}
+
+fn bench(name: &str, doc: &str) {
+    let tokens = tokenize(doc);
+    let tokenizer = best_of(|| tokenize(doc));
+    let parser = best_of(|| xmlinfoset::parse::parse(doc).unwrap());
+    let (infoset, heap) = common::measure_heap(|| xmlinfoset::parse::parse(doc).unwrap());
+    drop(infoset);
+    println!(
+        "{name:<16} {:>6} KB {tokens:>10} tokens | tokenizer {:>8.1} MB/s | \
+         parse {:>8.1} MB/s ({:.1}x) | heap {:>8} KB retained, {:>8} KB peak",
+        doc.len() >> 10,
+        megabytes_per_second(doc.len(), tokenizer),
+        megabytes_per_second(doc.len(), parser),
+        parser.as_secs_f64() / tokenizer.as_secs_f64(),
+        heap.retained >> 10,
+        heap.peak >> 10,
+    );
+}
+
+fn main() {
+    let len = common::corpus_megabytes(16) << 20;
+    bench("deep nesting", &common::deep_nesting(len, 64));
+    bench("wide attributes", &common::wide_attributes(len, 32));
+    bench("text heavy", &common::text_heavy(len));
+    bench("namespace heavy", &common::namespace_heavy(len, 32));
+    bench("dtd heavy", &common::dtd_heavy(len, 256));
+}
@@ -0,0 +1,84 @@
+/// An interned name or namespace name.
+///
+/// Atoms are only meaningful for the info set whose [`AtomTable`] produced
+/// them; within it, equal strings have equal atoms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Atom(u32);
+
+/// A namespace name and local name pair, compared as atoms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExpandedName {
+    pub namespace_name: Option<Atom>,
+    pub local_name: Atom,
+}
+
+/// The distinct names and namespace names of an info set.
+#[derive(Default, Debug)]
+pub struct AtomTable {
+    strings: Vec<String>,
+    /// The atoms in the order of their strings, so that each string is
+    /// stored once.
+    sorted: Vec<Atom>,
+}
+
+impl AtomTable {
+    /// Returns the position of `s` in `sorted`, or where it would be
+    /// inserted.
+    fn search(&self, s: &str) -> Result<usize, usize> {
+        self.sorted
+            .binary_search_by(|&atom| self.resolve(atom).cmp(s))
+    }
+
+    pub fn intern(&mut self, s: &str) -> Atom {
+        match self.search(s) {
+            Ok(position) => self.sorted[position],
+            Err(position) => {
+                let atom = Atom(self.strings.len() as u32);
+                self.strings.push(s.to_owned());
+                self.sorted.insert(position, atom);
+                atom
+            }
+        }
+    }
+
+    /// Returns the atom of `s`, if it has been interned.
+    pub fn get(&self, s: &str) -> Option<Atom> {
+        self.search(s).ok().map(|position| self.sorted[position])
+    }
+
+    pub fn resolve(&self, atom: Atom) -> &str {
+        &self.strings[atom.0 as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    pub(crate) fn expanded_name(
+        &mut self,
+        namespace_name: Option<&str>,
+        local_name: &str,
+    ) -> ExpandedName {
+        ExpandedName {
+            namespace_name: namespace_name.map(|namespace_name| self.intern(namespace_name)),
+            local_name: self.intern(local_name),
+        }
+    }
+
+    /// Returns the expanded name made of the given strings, or `None` if
+    /// either has not been interned, in which case no item has that name.
+    pub fn lookup(&self, namespace_name: Option<&str>, local_name: &str) -> Option<ExpandedName> {
+        let namespace_name = match namespace_name {
+            Some(namespace_name) => Some(self.get(namespace_name)?),
+            None => None,
+        };
+        Some(ExpandedName {
+            namespace_name,
+            local_name: self.get(local_name)?,
+        })
+    }
+}
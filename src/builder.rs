@@ -0,0 +1,37 @@
+use super::*;
+
+/// Builds an info set whose strings are not slices of a parsed document.
+///
+/// Every string handed to [`InfoSetBuilder::span`] is appended to an owned
+/// input buffer, so the items can keep using plain [`Span`]s.
+pub(crate) struct InfoSetBuilder {
+    input: String,
+    pub(crate) data: InfoSetData,
+}
+
+impl InfoSetBuilder {
+    pub(crate) fn new() -> Self {
+        InfoSetBuilder {
+            input: String::new(),
+            data: InfoSetData::default(),
+        }
+    }
+
+    pub(crate) fn span(&mut self, s: &str) -> Span {
+        let start = self.input.len();
+        self.input.push_str(s);
+        Span(start..self.input.len())
+    }
+
+    pub(crate) fn cow_span(&mut self, s: &str) -> CowSpan {
+        CowSpan::Borrowed(self.span(s))
+    }
+
+    pub(crate) fn finish(self) -> InfoSet<'static> {
+        InfoSet {
+            document: edit::DocumentText::new(self.input.len(), Default::default()),
+            input: Cow::Owned(self.input),
+            data: self.data,
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! OASIS XML Catalogs 1.1, mapping external identifiers and URIs to local
+//! resources.
+//!
+//! A [`Catalog`] is an [`EntityResolver`], so it can be handed to the parser
+//! to read DTDs and external entities without network access.
+
+use super::parse::ParseError;
+use super::resolver::{EntityResolver, ResolveError};
+use super::*;
+use std::path::{Path, PathBuf};
+
+pub const CATALOG_NAMESPACE: &str = "urn:oasis:names:tc:entity:xmlns:xml:catalog";
+
+#[derive(Debug, Error)]
+pub enum CatalogError {
+    #[error("failed to read catalog {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("catalog {path:?} is not well-formed: {source}")]
+    Parse { path: PathBuf, source: ParseError },
+    #[error("{0:?} is not a catalog")]
+    NotACatalog(PathBuf),
+}
+
+enum CatalogEntry {
+    Public {
+        public_id: String,
+        uri: String,
+        prefer_public: bool,
+    },
+    System {
+        system_id: String,
+        uri: String,
+    },
+    RewriteSystem {
+        start: String,
+        rewrite_prefix: String,
+    },
+    Uri {
+        name: String,
+        uri: String,
+    },
+    DelegatePublic {
+        start: String,
+        catalog: Catalog,
+        prefer_public: bool,
+    },
+    NextCatalog(Catalog),
+}
+
+/// An XML catalog, together with the catalogs it delegates to.
+#[derive(Default)]
+pub struct Catalog {
+    entries: Vec<CatalogEntry>,
+}
+
+impl Catalog {
+    /// Reads the catalog file at `path`, and the catalogs it refers to.
+    pub fn load(path: impl AsRef<Path>) -> Result<Catalog, CatalogError> {
+        Catalog::load_inner(path.as_ref(), &mut Vec::new())
+    }
+
+    fn load_inner(path: &Path, loaded: &mut Vec<PathBuf>) -> Result<Catalog, CatalogError> {
+        // A catalog that refers back to itself adds nothing.
+        if loaded.iter().any(|loaded| loaded == path) {
+            return Ok(Catalog::default());
+        }
+        loaded.push(path.to_owned());
+        let bytes = std::fs::read(path).map_err(|source| CatalogError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let text = String::from_utf8_lossy(&bytes);
+        let infoset = parse::parse(&text).map_err(|source| CatalogError::Parse {
+            path: path.to_owned(),
+            source,
+        })?;
+        let root = match infoset.doc_info_item {
+            Some(doc_info_item) => doc_info_item.document_element(&infoset),
+            None => return Err(CatalogError::NotACatalog(path.to_owned())),
+        };
+        if !root.has_name(&infoset, Some(CATALOG_NAMESPACE), "catalog") {
+            return Err(CatalogError::NotACatalog(path.to_owned()));
+        }
+        let mut catalog = Catalog::default();
+        let base = path.to_string_lossy().into_owned();
+        catalog.read_entries(&infoset, root, &base, true, loaded)?;
+        Ok(catalog)
+    }
+
+    fn read_entries(
+        &mut self,
+        infoset: &InfoSet<'_>,
+        parent: ElementInfoItem,
+        base: &str,
+        prefer_public: bool,
+        loaded: &mut Vec<PathBuf>,
+    ) -> Result<(), CatalogError> {
+        let base = match parent.attribute_value(infoset, Some(XML_NAMESPACE), "base") {
+            Some(xml_base) => uri::resolve(base, &xml_base),
+            None => base.to_owned(),
+        };
+        let prefer_public = match parent.attribute_value(infoset, None, "prefer").as_deref() {
+            Some("public") => true,
+            Some("system") => false,
+            _ => prefer_public,
+        };
+        let catalog_namespace = infoset.atoms.get(CATALOG_NAMESPACE);
+        for &child in parent.children(infoset) {
+            let element = match child {
+                ElementChildInfoItem::Element(element) => element,
+                _ => continue,
+            };
+            let namespace_name = element.expanded_name(infoset).namespace_name;
+            if catalog_namespace.is_none() || namespace_name != catalog_namespace {
+                // Elements from other namespaces are ignored.
+                continue;
+            }
+            let attr = |name| element.attribute_value(infoset, None, name);
+            let entry_base = match element.attribute_value(infoset, Some(XML_NAMESPACE), "base") {
+                Some(xml_base) => uri::resolve(&base, &xml_base),
+                None => base.clone(),
+            };
+            let entry_uri = |name| attr(name).map(|uri| uri::resolve(&entry_base, &uri));
+            let entry = match &infoset.input[element.local_name(infoset).0] {
+                "group" => {
+                    self.read_entries(infoset, element, &base, prefer_public, loaded)?;
+                    continue;
+                }
+                "public" => match (attr("publicId"), entry_uri("uri")) {
+                    (Some(public_id), Some(uri)) => CatalogEntry::Public {
+                        public_id: normalize_public_id(&public_id),
+                        uri,
+                        prefer_public: match attr("prefer").as_deref() {
+                            Some("public") => true,
+                            Some("system") => false,
+                            _ => prefer_public,
+                        },
+                    },
+                    _ => continue,
+                },
+                "system" => match (attr("systemId"), entry_uri("uri")) {
+                    (Some(system_id), Some(uri)) => CatalogEntry::System {
+                        system_id: normalize_uri(&system_id),
+                        uri,
+                    },
+                    _ => continue,
+                },
+                "rewriteSystem" => {
+                    match (attr("systemIdStartString"), entry_uri("rewritePrefix")) {
+                        (Some(start), Some(rewrite_prefix)) => CatalogEntry::RewriteSystem {
+                            start: normalize_uri(&start),
+                            rewrite_prefix,
+                        },
+                        _ => continue,
+                    }
+                }
+                "uri" => match (attr("name"), entry_uri("uri")) {
+                    (Some(name), Some(uri)) => CatalogEntry::Uri {
+                        name: normalize_uri(&name),
+                        uri,
+                    },
+                    _ => continue,
+                },
+                "delegatePublic" => match (attr("publicIdStartString"), entry_uri("catalog")) {
+                    (Some(start), Some(catalog)) => CatalogEntry::DelegatePublic {
+                        start: normalize_public_id(&start),
+                        catalog: Catalog::load_referenced(&catalog, loaded)?,
+                        prefer_public,
+                    },
+                    _ => continue,
+                },
+                "nextCatalog" => match entry_uri("catalog") {
+                    Some(catalog) => {
+                        CatalogEntry::NextCatalog(Catalog::load_referenced(&catalog, loaded)?)
+                    }
+                    None => continue,
+                },
+                _ => continue,
+            };
+            self.entries.push(entry);
+        }
+        Ok(())
+    }
+
+    fn load_referenced(uri: &str, loaded: &mut Vec<PathBuf>) -> Result<Catalog, CatalogError> {
+        match uri_to_path(uri) {
+            Some(path) => Catalog::load_inner(&path, loaded),
+            // Catalogs that are not local files are skipped.
+            None => Ok(Catalog::default()),
+        }
+    }
+
+    /// Maps an external identifier to a URI, following the resolution rules
+    /// of XML Catalogs 1.1 §7.1.2.
+    pub fn resolve_external_id(
+        &self,
+        public_id: Option<&str>,
+        system_id: Option<&str>,
+    ) -> Option<String> {
+        let public_id = public_id.map(normalize_public_id);
+        let system_id = system_id.map(normalize_uri);
+        self.resolve_external_id_inner(public_id.as_deref(), system_id.as_deref())
+    }
+
+    fn resolve_external_id_inner(
+        &self,
+        public_id: Option<&str>,
+        system_id: Option<&str>,
+    ) -> Option<String> {
+        if let Some(system_id) = system_id {
+            for entry in &self.entries {
+                if let CatalogEntry::System { system_id: s, uri } = entry {
+                    if s == system_id {
+                        return Some(uri.clone());
+                    }
+                }
+            }
+            let rewrite = self
+                .entries
+                .iter()
+                .filter_map(|entry| match entry {
+                    CatalogEntry::RewriteSystem {
+                        start,
+                        rewrite_prefix,
+                    } if system_id.starts_with(start.as_str()) => Some((start, rewrite_prefix)),
+                    _ => None,
+                })
+                .max_by_key(|(start, _)| start.len());
+            if let Some((start, rewrite_prefix)) = rewrite {
+                return Some(format!("{}{}", rewrite_prefix, &system_id[start.len()..]));
+            }
+        }
+        if let Some(public_id) = public_id {
+            let applies = |prefer_public: bool| prefer_public || system_id.is_none();
+            for entry in &self.entries {
+                if let CatalogEntry::Public {
+                    public_id: p,
+                    uri,
+                    prefer_public,
+                } = entry
+                {
+                    if p == public_id && applies(*prefer_public) {
+                        return Some(uri.clone());
+                    }
+                }
+            }
+            let mut delegates = self
+                .entries
+                .iter()
+                .filter_map(|entry| match entry {
+                    CatalogEntry::DelegatePublic {
+                        start,
+                        catalog,
+                        prefer_public,
+                    } if public_id.starts_with(start.as_str()) && applies(*prefer_public) => {
+                        Some((start.len(), catalog))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            if !delegates.is_empty() {
+                // Delegation ends the lookup in this catalog, whether or not
+                // one of the delegates has an answer.
+                delegates.sort_by(|a, b| b.0.cmp(&a.0));
+                return delegates.into_iter().find_map(|(_, catalog)| {
+                    catalog.resolve_external_id_inner(Some(public_id), None)
+                });
+            }
+        }
+        self.entries.iter().find_map(|entry| match entry {
+            CatalogEntry::NextCatalog(catalog) => {
+                catalog.resolve_external_id_inner(public_id, system_id)
+            }
+            _ => None,
+        })
+    }
+
+    /// Maps a URI that is not part of an external identifier, such as an
+    /// XInclude `href`, to another URI.
+    pub fn resolve_uri(&self, uri: &str) -> Option<String> {
+        self.resolve_uri_inner(&normalize_uri(uri))
+    }
+
+    fn resolve_uri_inner(&self, uri: &str) -> Option<String> {
+        for entry in &self.entries {
+            if let CatalogEntry::Uri { name, uri: target } = entry {
+                if name == uri {
+                    return Some(target.clone());
+                }
+            }
+        }
+        self.entries.iter().find_map(|entry| match entry {
+            CatalogEntry::NextCatalog(catalog) => catalog.resolve_uri_inner(uri),
+            _ => None,
+        })
+    }
+}
+
+impl EntityResolver for Catalog {
+    fn resolve_entity(
+        &mut self,
+        public_id: Option<&str>,
+        system_id: &str,
+        base_uri: Option<&str>,
+    ) -> Result<Vec<u8>, ResolveError> {
+        let uri = self
+            .resolve_external_id(public_id, Some(system_id))
+            .or_else(|| self.resolve_uri(system_id))
+            .unwrap_or_else(|| uri::resolve_opt(base_uri, system_id));
+        let path = uri_to_path(&uri).ok_or_else(|| ResolveError::NotFound(system_id.to_owned()))?;
+        std::fs::read(&path).map_err(|e| ResolveError::ReadFailed {
+            system_id: system_id.to_owned(),
+            message: e.to_string(),
+        })
+    }
+}
+
+// Public identifiers are compared after normalizing white space.
+fn normalize_public_id(public_id: &str) -> String {
+    public_id
+        .split(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn has_scheme(reference: &str) -> bool {
+    match reference.split_once(':') {
+        // A single letter is taken as a drive letter rather than a scheme.
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+/// Normalizes a system identifier or URI for comparison, as described in
+/// XML Catalogs 1.1 §6.3: the characters not allowed in URIs are %-escaped
+/// as the bytes of their UTF-8 encoding.
+fn normalize_uri(uri: &str) -> String {
+    let mut normalized = String::with_capacity(uri.len());
+    for c in uri.chars() {
+        let escaped = c <= ' '
+            || c > '~'
+            || matches!(c, '"' | '<' | '>' | '\\' | '^' | '`' | '{' | '|' | '}');
+        if !escaped {
+            normalized.push(c);
+            continue;
+        }
+        let mut bytes = [0; 4];
+        for byte in c.encode_utf8(&mut bytes).bytes() {
+            normalized.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    normalized
+}
+
+/// Returns the local file a URI refers to, if any.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    if let Some(path) = uri.strip_prefix("file://") {
+        Some(PathBuf::from(path))
+    } else if let Some(path) = uri.strip_prefix("file:") {
+        Some(PathBuf::from(path))
+    } else if has_scheme(uri) {
+        None
+    } else {
+        Some(PathBuf::from(uri))
+    }
+}
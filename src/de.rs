@@ -0,0 +1,682 @@
+//! Deserializing Rust values from an [`InfoSet`] with `serde`.
+//!
+//! Struct fields are looked up on an element as follows:
+//!
+//! * `@name` reads the attribute `name`,
+//! * `$value` reads the child elements if there are any, otherwise the text
+//!   content of the element,
+//! * any other field reads the child elements whose local name matches. A
+//!   field named `{namespace}local` only matches elements in that namespace.
+//!
+//! A field that matches several child elements can be deserialized into a
+//! sequence. Enums pick their variant from the name of the first child
+//! element, or from the text content for unit variants. The elements read
+//! through `$value` pick their variant from their own name instead.
+
+use super::visit::ChildItems;
+use super::*;
+use core::fmt;
+use serde::de::value::CowStrDeserializer;
+use serde::de::{self, DeserializeSeed, Deserializer as _, IntoDeserializer, Visitor};
+use serde::Deserialize;
+
+#[derive(Debug, Error)]
+pub enum DeError {
+    #[error("{0}")]
+    Custom(String),
+    #[error(transparent)]
+    Span(#[from] SpanError),
+    #[error("info set has no document element")]
+    MissingDocumentElement,
+    #[error("cannot parse {0:?} as {1}")]
+    InvalidValue(String, &'static str),
+}
+
+impl de::Error for DeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeError::Custom(msg.to_string())
+    }
+}
+
+pub fn from_infoset<'de, T: Deserialize<'de>>(infoset: &'de InfoSet<'de>) -> Result<T, DeError> {
+    let doc_info_item = infoset
+        .doc_info_item
+        .ok_or(DeError::MissingDocumentElement)?;
+    from_element(infoset, doc_info_item.document_element(infoset))
+}
+
+pub fn from_element<'de, T: Deserialize<'de>>(
+    infoset: &'de InfoSet<'de>,
+    element: ElementInfoItem,
+) -> Result<T, DeError> {
+    T::deserialize(ElementDeserializer::new(infoset, element))
+}
+
+fn cow_text<'de>(infoset: &'de InfoSet<'de>, span: CowSpan) -> Result<Cow<'de, str>, DeError> {
+    Ok(match span {
+        CowSpan::Borrowed(span) => Cow::Borrowed(span.get(infoset)?),
+        CowSpan::Owned(str) => Cow::Owned(str),
+        CowSpan::Static(str) => Cow::Borrowed(str),
+    })
+}
+
+fn name_matches(infoset: &InfoSet<'_>, key: &str, expanded_name: ExpandedName) -> bool {
+    if let Some((namespace, local)) = key.strip_prefix('{').and_then(|k| k.split_once('}')) {
+        let namespace = Some(namespace).filter(|namespace| !namespace.is_empty());
+        return infoset.atoms.lookup(namespace, local) == Some(expanded_name);
+    }
+    infoset.atoms.get(key) == Some(expanded_name.local_name)
+}
+
+fn parse_trimmed<T: core::str::FromStr>(text: &str) -> Result<T, DeError> {
+    text.trim()
+        .parse()
+        .map_err(|_| DeError::InvalidValue(text.to_owned(), core::any::type_name::<T>()))
+}
+
+/// Deserializes a value from attribute values and text content.
+pub struct TextDeserializer<'de> {
+    text: Cow<'de, str>,
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                visitor.$visit(parse_trimmed(&self.text)?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for TextDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.text {
+            Cow::Borrowed(text) => visitor.visit_borrowed_str(text),
+            Cow::Owned(text) => visitor.visit_string(text),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.text.trim() {
+            "true" | "1" => visitor.visit_bool(true),
+            "false" | "0" => visitor.visit_bool(false),
+            _ => Err(DeError::InvalidValue(self.text.into_owned(), "bool")),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let mut chars = self.text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(DeError::InvalidValue(self.text.into_owned(), "char")),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Sequences are read as whitespace separated lists, like `xs:list`.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let items: Vec<Cow<'de, str>> = match self.text {
+            Cow::Borrowed(text) => text.split_whitespace().map(Cow::Borrowed).collect(),
+            Cow::Owned(text) => text
+                .split_whitespace()
+                .map(|item| Cow::Owned(item.to_owned()))
+                .collect(),
+        };
+        let items = items.into_iter().map(|text| TextDeserializer { text });
+        de::value::SeqDeserializer::new(items).deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let variant: CowStrDeserializer<'de, DeError> = self.text.into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf map struct identifier
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for TextDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Deserializes a value from a single element.
+pub struct ElementDeserializer<'de> {
+    infoset: &'de InfoSet<'de>,
+    element: ElementInfoItem,
+    variant_by_name: bool,
+}
+
+enum MapValue<'de> {
+    Text(Cow<'de, str>),
+    Elements(Vec<ElementInfoItem>, bool),
+}
+
+impl<'de> ElementDeserializer<'de> {
+    pub fn new(infoset: &'de InfoSet<'de>, element: ElementInfoItem) -> Self {
+        ElementDeserializer {
+            infoset,
+            element,
+            variant_by_name: false,
+        }
+    }
+
+    fn child_elements(&self) -> Vec<ElementInfoItem> {
+        ChildItems::of(self.infoset, self.element).elements
+    }
+
+    fn has_child_elements(&self) -> bool {
+        !self.child_elements().is_empty()
+    }
+
+    fn text_content(&self) -> Result<Cow<'de, str>, DeError> {
+        let mut text: Option<Cow<'de, str>> = None;
+        for char_group in ChildItems::of(self.infoset, self.element).char_groups {
+            let chunk = cow_text(self.infoset, char_group.characters(self.infoset))?;
+            text = Some(match text {
+                None => chunk,
+                Some(prev) => Cow::Owned(prev.into_owned() + &*chunk),
+            });
+        }
+        Ok(text.unwrap_or(Cow::Borrowed("")))
+    }
+
+    fn text(&self) -> Result<TextDeserializer<'de>, DeError> {
+        Ok(TextDeserializer {
+            text: self.text_content()?,
+        })
+    }
+
+    fn value_entry(&self) -> Result<Option<MapValue<'de>>, DeError> {
+        let children = ChildItems::of(self.infoset, self.element);
+        if !children.elements.is_empty() {
+            return Ok(Some(MapValue::Elements(children.elements, true)));
+        }
+        if !children.char_groups.is_empty() {
+            Ok(Some(MapValue::Text(self.text_content()?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn attribute_value(&self, key: &str) -> Result<Option<Cow<'de, str>>, DeError> {
+        for &attribute in self.element.attributes(self.infoset) {
+            if name_matches(self.infoset, key, attribute.expanded_name(self.infoset)) {
+                let value = cow_text(self.infoset, attribute.normalized_value(self.infoset))?;
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    fn struct_entries(
+        &self,
+        fields: &'static [&'static str],
+    ) -> Result<Vec<(Cow<'de, str>, MapValue<'de>)>, DeError> {
+        let mut entries = Vec::new();
+        for &field in fields {
+            let value = if let Some(key) = field.strip_prefix('@') {
+                self.attribute_value(key)?.map(MapValue::Text)
+            } else if field == "$value" {
+                self.value_entry()?
+            } else {
+                let mut elements = Vec::new();
+                for element in self.child_elements() {
+                    if name_matches(self.infoset, field, element.expanded_name(self.infoset)) {
+                        elements.push(element);
+                    }
+                }
+                if elements.is_empty() {
+                    None
+                } else {
+                    Some(MapValue::Elements(elements, false))
+                }
+            };
+            if let Some(value) = value {
+                entries.push((Cow::Borrowed(field), value));
+            }
+        }
+        Ok(entries)
+    }
+
+    fn map_entries(&self) -> Result<Vec<(Cow<'de, str>, MapValue<'de>)>, DeError> {
+        let mut entries = Vec::new();
+        for &attribute in self.element.attributes(self.infoset) {
+            let local_name = attribute.local_name(self.infoset).get(self.infoset)?;
+            let value = cow_text(self.infoset, attribute.normalized_value(self.infoset))?;
+            entries.push((
+                Cow::Owned(format!("@{}", local_name)),
+                MapValue::Text(value),
+            ));
+        }
+        let mut groups: Vec<(&'de str, Vec<ElementInfoItem>)> = Vec::new();
+        for element in self.child_elements() {
+            let local_name = element.local_name(self.infoset).get(self.infoset)?;
+            match groups.iter_mut().find(|(name, _)| *name == local_name) {
+                Some((_, elements)) => elements.push(element),
+                None => groups.push((local_name, vec![element])),
+            }
+        }
+        for (name, elements) in groups {
+            entries.push((Cow::Borrowed(name), MapValue::Elements(elements, false)));
+        }
+        let text = self.text_content()?;
+        if !text.trim().is_empty() {
+            entries.push((Cow::Borrowed("$value"), MapValue::Text(text)));
+        }
+        Ok(entries)
+    }
+
+    fn into_map_access(
+        self,
+        entries: Vec<(Cow<'de, str>, MapValue<'de>)>,
+    ) -> ElementMapAccess<'de> {
+        ElementMapAccess {
+            infoset: self.infoset,
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+macro_rules! deserialize_from_text {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                self.text()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ElementDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if self.element.attributes(self.infoset).is_empty() && !self.has_child_elements() {
+            self.text()?.deserialize_any(visitor)
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    deserialize_from_text! {
+        deserialize_bool
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+        deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf
+        deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// An element with child elements is read as the sequence of its
+    /// children, otherwise its text content is read as a list.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if self.has_child_elements() {
+            ElementsDeserializer {
+                infoset: self.infoset,
+                elements: self.child_elements(),
+                variant_by_name: true,
+            }
+            .deserialize_seq(visitor)
+        } else {
+            self.text()?.deserialize_seq(visitor)
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let entries = self.map_entries()?;
+        visitor.visit_map(self.into_map_access(entries))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let entries = self.struct_entries(fields)?;
+        visitor.visit_map(self.into_map_access(entries))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        if self.variant_by_name {
+            return visitor.visit_enum(self);
+        }
+        match self.child_elements().first().copied() {
+            Some(element) => visitor.visit_enum(ElementDeserializer {
+                infoset: self.infoset,
+                element,
+                variant_by_name: true,
+            }),
+            None => self.text()?.deserialize_enum(name, variants, visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for ElementDeserializer<'de> {
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self), DeError> {
+        let local_name = self.element.local_name(self.infoset).get(self.infoset)?;
+        let variant: CowStrDeserializer<'de, DeError> =
+            Cow::Borrowed(local_name).into_deserializer();
+        let value = seed.deserialize(variant)?;
+        Ok((
+            value,
+            ElementDeserializer {
+                variant_by_name: false,
+                ..self
+            },
+        ))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ElementDeserializer<'de> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, DeError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, DeError> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
+
+struct ElementMapAccess<'de> {
+    infoset: &'de InfoSet<'de>,
+    entries: alloc::vec::IntoIter<(Cow<'de, str>, MapValue<'de>)>,
+    value: Option<MapValue<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for ElementMapAccess<'de> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeError> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                let key: CowStrDeserializer<'de, DeError> = key.into_deserializer();
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, DeError> {
+        match self.value.take() {
+            Some(MapValue::Text(text)) => seed.deserialize(TextDeserializer { text }),
+            Some(MapValue::Elements(elements, variant_by_name)) => {
+                seed.deserialize(ElementsDeserializer {
+                    infoset: self.infoset,
+                    elements,
+                    variant_by_name,
+                })
+            }
+            None => Err(de::Error::custom("value requested before key")),
+        }
+    }
+}
+
+/// Deserializes a value from all the elements matched by a struct field.
+///
+/// Sequences read every element, anything else reads the first one.
+struct ElementsDeserializer<'de> {
+    infoset: &'de InfoSet<'de>,
+    elements: Vec<ElementInfoItem>,
+    variant_by_name: bool,
+}
+
+impl<'de> ElementsDeserializer<'de> {
+    fn first(self) -> ElementDeserializer<'de> {
+        ElementDeserializer {
+            infoset: self.infoset,
+            element: self.elements[0],
+            variant_by_name: self.variant_by_name,
+        }
+    }
+}
+
+macro_rules! deserialize_from_first {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                self.first().$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ElementsDeserializer<'de> {
+    type Error = DeError;
+
+    deserialize_from_first! {
+        deserialize_any deserialize_bool
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+        deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf
+        deserialize_unit deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if self.elements.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.first().deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let infoset = self.infoset;
+        let variant_by_name = self.variant_by_name;
+        let elements = self
+            .elements
+            .into_iter()
+            .map(move |element| ElementDeserializer {
+                infoset,
+                element,
+                variant_by_name,
+            });
+        de::value::SeqDeserializer::new(elements).deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.first().deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.first().deserialize_enum(name, variants, visitor)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for ElementDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
@@ -0,0 +1,504 @@
+use super::visit::{self, InfoSetVisitor};
+use super::*;
+use core::fmt;
+
+/// Renders a single info item with its names and content resolved against
+/// the input of the info set, as returned by [`InfoSet::debug_item`].
+pub struct InfoItemDisplay<'a, 'input> {
+    pub(crate) infoset: &'a InfoSet<'input>,
+    pub(crate) item: InfoItem,
+}
+
+fn resolve<'a>(infoset: &'a InfoSet<'_>, span: &Span) -> &'a str {
+    span.get(infoset).unwrap_or("<invalid span>")
+}
+
+fn resolve_cow<'a>(infoset: &'a InfoSet<'_>, span: &'a CowSpan) -> &'a str {
+    span.get_ref(infoset).unwrap_or("<invalid span>")
+}
+
+fn write_qname(
+    f: &mut fmt::Formatter<'_>,
+    infoset: &InfoSet<'_>,
+    prefix: &Option<Span>,
+    local_name: &Span,
+) -> fmt::Result {
+    if let Some(prefix) = prefix {
+        write!(f, "{}:", resolve(infoset, prefix))?;
+    }
+    f.write_str(resolve(infoset, local_name))
+}
+
+fn write_namespace_name(f: &mut fmt::Formatter<'_>, namespace_name: Option<&str>) -> fmt::Result {
+    if let Some(namespace_name) = namespace_name {
+        write!(f, " {{{}}}", namespace_name)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn attr_type_name(attr_type: &AttrTypeAndReferences) -> &'static str {
+    match attr_type {
+        AttrTypeAndReferences::ID(_) => "ID",
+        AttrTypeAndReferences::IDREF(_) => "IDREF",
+        AttrTypeAndReferences::IDREFS(_) => "IDREFS",
+        AttrTypeAndReferences::ENTITY(_) => "ENTITY",
+        AttrTypeAndReferences::ENTITIES(_) => "ENTITIES",
+        AttrTypeAndReferences::NMTOKEN(_) => "NMTOKEN",
+        AttrTypeAndReferences::NMTOKENS(_) => "NMTOKENS",
+        AttrTypeAndReferences::NOTATION(_) => "NOTATION",
+        AttrTypeAndReferences::CDATA(_) => "CDATA",
+        AttrTypeAndReferences::ENUMERATION(_) => "ENUMERATION",
+    }
+}
+
+impl fmt::Display for InfoItemDisplay<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let infoset = self.infoset;
+        match self.item {
+            InfoItem::Doc(doc) if !doc.is_parsed(infoset) => {
+                f.write_str("Document (not yet parsed)")
+            }
+            InfoItem::Doc(doc) => write!(
+                f,
+                "Document (version {}, {} children)",
+                doc.version(infoset).as_str(),
+                doc.children(infoset).len()
+            ),
+            InfoItem::Element(element) => {
+                f.write_str("Element <")?;
+                write_qname(
+                    f,
+                    infoset,
+                    &element.prefix(infoset),
+                    &element.local_name(infoset),
+                )?;
+                for &attribute in element
+                    .namespace_attributes(infoset)
+                    .iter()
+                    .chain(element.attributes(infoset))
+                {
+                    f.write_str(" ")?;
+                    write_qname(
+                        f,
+                        infoset,
+                        &attribute.prefix(infoset),
+                        &attribute.local_name(infoset),
+                    )?;
+                    write!(
+                        f,
+                        "={:?}",
+                        resolve_cow(infoset, &attribute.normalized_value(infoset))
+                    )?;
+                }
+                f.write_str(">")?;
+                write_namespace_name(f, element.namespace_name(infoset))
+            }
+            InfoItem::Attribute(attribute) => {
+                f.write_str("Attribute ")?;
+                write_qname(
+                    f,
+                    infoset,
+                    &attribute.prefix(infoset),
+                    &attribute.local_name(infoset),
+                )?;
+                write!(
+                    f,
+                    "={:?}",
+                    resolve_cow(infoset, &attribute.normalized_value(infoset))
+                )?;
+                let namespace_name = attribute.namespace_name(infoset);
+                write_namespace_name(
+                    f,
+                    namespace_name
+                        .as_ref()
+                        .map(|namespace_name| resolve_cow(infoset, namespace_name)),
+                )
+            }
+            InfoItem::PI(pi) => {
+                write!(f, "PI {}", resolve(infoset, &pi.target(infoset)))?;
+                if let Some(content) = pi.content(infoset) {
+                    write!(f, " {:?}", resolve_cow(infoset, &content))?;
+                }
+                Ok(())
+            }
+            InfoItem::UER(uer) => write!(
+                f,
+                "Unexpanded entity reference &{};",
+                resolve(infoset, &uer.name(infoset))
+            ),
+            InfoItem::CharGroup(char_group) => write!(
+                f,
+                "Characters {:?}",
+                resolve_cow(infoset, &char_group.characters(infoset))
+            ),
+            InfoItem::Comment(comment) => write!(
+                f,
+                "Comment {:?}",
+                resolve_cow(infoset, &comment.content(infoset))
+            ),
+            InfoItem::DTD(dtd) => {
+                f.write_str("DTD")?;
+                write_external_id(
+                    f,
+                    infoset,
+                    &dtd.system_identifier(infoset),
+                    &dtd.public_identifier(infoset),
+                )
+            }
+            InfoItem::UE(ue) => {
+                write!(
+                    f,
+                    "Unparsed entity {} (system {:?}",
+                    resolve(infoset, &ue.name(infoset)),
+                    resolve(infoset, &ue.system_identifier(infoset))
+                )?;
+                if let Some(public_identifier) = ue.public_identifier(infoset) {
+                    write!(f, ", public {:?}", resolve(infoset, &public_identifier))?;
+                }
+                write!(
+                    f,
+                    ", notation {})",
+                    resolve(infoset, &ue.notation_name(infoset))
+                )
+            }
+            InfoItem::Notation(notation) => {
+                write!(f, "Notation {}", resolve(infoset, &notation.name(infoset)))?;
+                write_external_id(
+                    f,
+                    infoset,
+                    &notation.system_identifier(infoset),
+                    &notation.public_identifier(infoset),
+                )
+            }
+            InfoItem::NS(ns) => {
+                f.write_str("Namespace ")?;
+                match ns.prefix(infoset) {
+                    Some(prefix) => write!(f, "xmlns:{}", resolve_cow(infoset, &prefix))?,
+                    None => f.write_str("xmlns")?,
+                }
+                write!(
+                    f,
+                    "={:?}",
+                    resolve_cow(infoset, &ns.namespace_name(infoset))
+                )
+            }
+        }
+    }
+}
+
+fn write_external_id(
+    f: &mut fmt::Formatter<'_>,
+    infoset: &InfoSet<'_>,
+    system_identifier: &Option<Span>,
+    public_identifier: &Option<Span>,
+) -> fmt::Result {
+    match (system_identifier, public_identifier) {
+        (Some(system), Some(public)) => write!(
+            f,
+            " (system {:?}, public {:?})",
+            resolve(infoset, system),
+            resolve(infoset, public)
+        ),
+        (Some(system), None) => write!(f, " (system {:?})", resolve(infoset, system)),
+        (None, Some(public)) => write!(f, " (public {:?})", resolve(infoset, public)),
+        (None, None) => Ok(()),
+    }
+}
+
+/// Dumps the info set as an indented listing of items and their properties,
+/// following the property names used by the XML Information Set spec.
+impl fmt::Debug for InfoSet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.doc_info_item.is_none() {
+            return f.write_str("Document (empty)\n");
+        }
+        let mut dumper = TreeDumper {
+            f,
+            depth: 0,
+            result: Ok(()),
+        };
+        visit::visit_infoset(&mut dumper, self);
+        dumper.result
+    }
+}
+
+struct TreeDumper<'f, 'g> {
+    f: &'f mut fmt::Formatter<'g>,
+    depth: usize,
+    result: fmt::Result,
+}
+
+impl TreeDumper<'_, '_> {
+    fn line(&mut self, args: fmt::Arguments<'_>) {
+        if self.result.is_err() {
+            return;
+        }
+        self.result = writeln!(self.f, "{:indent$}{}", "", args, indent = self.depth * 2);
+    }
+
+    fn property(&mut self, name: &str, value: fmt::Arguments<'_>) {
+        self.line(format_args!("  [{}] {}", name, value));
+    }
+
+    fn span_property(&mut self, infoset: &InfoSet<'_>, name: &str, span: &Span) {
+        self.property(name, format_args!("{}", resolve(infoset, span)));
+    }
+
+    fn opt_span_property(&mut self, infoset: &InfoSet<'_>, name: &str, span: &Option<Span>) {
+        match span {
+            Some(span) => self.span_property(infoset, name, span),
+            None => self.property(name, format_args!("<no value>")),
+        }
+    }
+
+    fn cow_property(&mut self, infoset: &InfoSet<'_>, name: &str, span: &CowSpan) {
+        self.property(name, format_args!("{}", resolve_cow(infoset, span)));
+    }
+
+    fn opt_cow_property(&mut self, infoset: &InfoSet<'_>, name: &str, span: &Option<CowSpan>) {
+        match span {
+            Some(span) => self.cow_property(infoset, name, span),
+            None => self.property(name, format_args!("<no value>")),
+        }
+    }
+
+    fn uri_property(&mut self, infoset: &InfoSet<'_>, name: &str, uri: &Option<UriSpan>) {
+        match uri {
+            Some(UriSpan(uri)) => self.cow_property(infoset, name, uri),
+            None => self.property(name, format_args!("<no value>")),
+        }
+    }
+
+    fn nested(&mut self, f: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        f(self);
+        self.depth -= 1;
+    }
+}
+
+impl<'a> InfoSetVisitor<'a> for TreeDumper<'_, '_> {
+    fn visit_doc(&mut self, infoset: &'a InfoSet<'a>, item: DocInfoItem) {
+        if !item.is_parsed(infoset) {
+            self.line(format_args!("Document (not yet parsed)"));
+            return;
+        }
+        self.line(format_args!("Document"));
+        self.property(
+            "version",
+            format_args!("{}", item.version(infoset).as_str()),
+        );
+        match item.character_encoding_scheme(infoset) {
+            Some(encoding) => self.property(
+                "character encoding scheme",
+                format_args!("{}", encoding.as_str()),
+            ),
+            None => self.property("character encoding scheme", format_args!("<no value>")),
+        }
+        match item.standalone(infoset) {
+            Some(standalone) => self.property(
+                "standalone",
+                format_args!("{}", if standalone { "yes" } else { "no" }),
+            ),
+            None => self.property("standalone", format_args!("<no value>")),
+        }
+        self.uri_property(infoset, "base URI", &item.base_uri(infoset));
+        self.property(
+            "all declarations processed",
+            format_args!("{}", item.all_declarations_processed(infoset)),
+        );
+        if item.notations(infoset).is_none() {
+            self.property("notations", format_args!("<no value>"));
+        }
+        self.nested(|this| visit::walk_doc(this, infoset, item));
+    }
+
+    fn visit_element(&mut self, infoset: &'a InfoSet<'a>, item: ElementInfoItem) {
+        self.line(format_args!("Element"));
+        match item.namespace_name(infoset) {
+            Some(namespace_name) => {
+                self.property("namespace name", format_args!("{}", namespace_name))
+            }
+            None => self.property("namespace name", format_args!("<no value>")),
+        }
+        self.span_property(infoset, "local name", &item.local_name(infoset));
+        self.opt_span_property(infoset, "prefix", &item.prefix(infoset));
+        self.uri_property(infoset, "base URI", &item.base_uri(infoset));
+        self.nested(|this| {
+            for &attribute in item
+                .namespace_attributes(infoset)
+                .iter()
+                .chain(item.attributes(infoset))
+            {
+                this.visit_attribute(infoset, attribute);
+            }
+            visit::walk_in_scope_namespaces(this, infoset, item);
+            for &child in item.children(infoset) {
+                this.visit_element_child(infoset, child);
+            }
+        });
+    }
+
+    fn visit_attribute(&mut self, infoset: &'a InfoSet<'a>, item: AttributeInfoItem) {
+        self.line(format_args!("Attribute"));
+        self.opt_cow_property(infoset, "namespace name", &item.namespace_name(infoset));
+        self.span_property(infoset, "local name", &item.local_name(infoset));
+        self.opt_span_property(infoset, "prefix", &item.prefix(infoset));
+        self.property(
+            "normalized value",
+            format_args!(
+                "{:?}",
+                resolve_cow(infoset, &item.normalized_value(infoset))
+            ),
+        );
+        self.property(
+            "specified",
+            format_args!(
+                "{}",
+                matches!(item.specified(infoset), AttrSpecified::Specified)
+            ),
+        );
+        match item.attribute_type_and_references(infoset) {
+            None => self.property("attribute type", format_args!("<no value>")),
+            Some(UnknownOr::Unknown) => self.property("attribute type", format_args!("<unknown>")),
+            Some(UnknownOr::Known(attr_type)) => self.property(
+                "attribute type",
+                format_args!("{}", attr_type_name(&attr_type)),
+            ),
+        }
+        visit::walk_attribute(self, infoset, item);
+    }
+
+    fn visit_namespace(&mut self, infoset: &'a InfoSet<'a>, item: NSInfoItem) {
+        self.line(format_args!("Namespace"));
+        self.opt_cow_property(infoset, "prefix", &item.prefix(infoset));
+        self.cow_property(infoset, "namespace name", &item.namespace_name(infoset));
+        visit::walk_namespace(self, infoset, item);
+    }
+
+    fn visit_char_group(&mut self, infoset: &'a InfoSet<'a>, item: CharGroupInfoItem) {
+        self.line(format_args!("Characters"));
+        self.property(
+            "character codes",
+            format_args!("{:?}", resolve_cow(infoset, &item.characters(infoset))),
+        );
+        match item.element_content_whitespace(infoset) {
+            UnknownOr::Unknown => {
+                self.property("element content whitespace", format_args!("<unknown>"))
+            }
+            UnknownOr::Known(None) => {
+                self.property("element content whitespace", format_args!("<no value>"))
+            }
+            UnknownOr::Known(Some(v)) => {
+                self.property("element content whitespace", format_args!("{}", v))
+            }
+        }
+        visit::walk_char_group(self, infoset, item);
+    }
+
+    fn visit_comment(&mut self, infoset: &'a InfoSet<'a>, item: CommentInfoItem) {
+        self.line(format_args!("Comment"));
+        self.property(
+            "content",
+            format_args!("{:?}", resolve_cow(infoset, &item.content(infoset))),
+        );
+        visit::walk_comment(self, infoset, item);
+    }
+
+    fn visit_pi(&mut self, infoset: &'a InfoSet<'a>, item: PIInfoItem) {
+        self.line(format_args!("Processing Instruction"));
+        self.span_property(infoset, "target", &item.target(infoset));
+        match item.content(infoset) {
+            Some(content) => self.property(
+                "content",
+                format_args!("{:?}", resolve_cow(infoset, &content)),
+            ),
+            None => self.property("content", format_args!("\"\"")),
+        }
+        self.uri_property(infoset, "base URI", &item.base_uri(infoset));
+        match item.notation(infoset) {
+            None => self.property("notation", format_args!("<no value>")),
+            Some(UnknownOr::Unknown) => self.property("notation", format_args!("<unknown>")),
+            Some(UnknownOr::Known(notation)) => {
+                self.span_property(infoset, "notation", &notation.name(infoset))
+            }
+        }
+        visit::walk_pi(self, infoset, item);
+    }
+
+    fn visit_uer(&mut self, infoset: &'a InfoSet<'a>, item: UERInfoItem) {
+        self.line(format_args!("Unexpanded Entity Reference"));
+        self.span_property(infoset, "name", &item.name(infoset));
+        for (name, identifier) in [
+            ("system identifier", item.system_identifier(infoset)),
+            ("public identifier", item.public_identifier(infoset)),
+        ] {
+            match identifier {
+                UnknownOr::Unknown => self.property(name, format_args!("<unknown>")),
+                UnknownOr::Known(identifier) => self.opt_span_property(infoset, name, &identifier),
+            }
+        }
+        match item.declaration_base_uri(infoset) {
+            UnknownOr::Unknown => self.property("declaration base URI", format_args!("<unknown>")),
+            UnknownOr::Known(uri) => self.uri_property(infoset, "declaration base URI", &uri),
+        }
+        visit::walk_uer(self, infoset, item);
+    }
+
+    fn visit_dtd(&mut self, infoset: &'a InfoSet<'a>, item: DTDInfoItem) {
+        self.line(format_args!("Document Type Declaration"));
+        self.opt_span_property(
+            infoset,
+            "system identifier",
+            &item.system_identifier(infoset),
+        );
+        self.opt_span_property(
+            infoset,
+            "public identifier",
+            &item.public_identifier(infoset),
+        );
+        self.nested(|this| visit::walk_dtd(this, infoset, item));
+    }
+
+    fn visit_notation(&mut self, infoset: &'a InfoSet<'a>, item: NotationInfoItem) {
+        self.line(format_args!("Notation"));
+        self.span_property(infoset, "name", &item.name(infoset));
+        self.opt_span_property(
+            infoset,
+            "system identifier",
+            &item.system_identifier(infoset),
+        );
+        self.opt_span_property(
+            infoset,
+            "public identifier",
+            &item.public_identifier(infoset),
+        );
+        self.uri_property(
+            infoset,
+            "declaration base URI",
+            &item.declaration_base_uri(infoset),
+        );
+        visit::walk_notation(self, infoset, item);
+    }
+
+    fn visit_unparsed_entity(&mut self, infoset: &'a InfoSet<'a>, item: UEInfoItem) {
+        self.line(format_args!("Unparsed Entity"));
+        self.span_property(infoset, "name", &item.name(infoset));
+        self.span_property(
+            infoset,
+            "system identifier",
+            &item.system_identifier(infoset),
+        );
+        self.opt_span_property(
+            infoset,
+            "public identifier",
+            &item.public_identifier(infoset),
+        );
+        self.uri_property(
+            infoset,
+            "declaration base URI",
+            &item.declaration_base_uri(infoset),
+        );
+        self.span_property(infoset, "notation name", &item.notation_name(infoset));
+        visit::walk_unparsed_entity(self, infoset, item);
+    }
+}
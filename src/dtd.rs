@@ -0,0 +1,1388 @@
+use super::parse::{normalize_line_ends, ParseError, ParseOptions};
+use super::*;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::rc::Rc;
+
+/// Markup declarations collected from the document type declaration.
+#[derive(Default)]
+pub(crate) struct DtdDeclarations {
+    /// The name given in the document type declaration.
+    pub(crate) doctype_name: Option<Span>,
+    pub(crate) general_entities: BTreeMap<String, EntityDecl>,
+    pub(crate) parameter_entities: BTreeMap<String, EntityDecl>,
+    /// Names of the general entities declared in external markup
+    /// declarations.
+    pub(crate) external_general_entities: BTreeSet<String>,
+    pub(crate) element_decls: BTreeMap<String, ElementDecl>,
+    pub(crate) attribute_lists: BTreeMap<String, Vec<AttributeDecl>>,
+    pub(crate) notations: Vec<NotationDecl>,
+    pub(crate) unparsed_entities: Vec<UnparsedEntityDecl>,
+    /// The notation items created for the declared notations, by name, or
+    /// `None` for names declared more than once.
+    pub(crate) notation_items: BTreeMap<String, Option<NotationInfoItem>>,
+    /// Texts read while processing the DTD that are not part of the document
+    /// entity, such as the external subset and parameter entity replacement
+    /// texts, by offset.
+    ///
+    /// Spans into them start past the end of the document entity, and the
+    /// texts are appended to it once the DTD has been processed. The content
+    /// of the document is parsed against the document text with these texts
+    /// appended, so that such spans, like those of attribute defaults
+    /// declared in a parameter entity, can be read there too.
+    pub(crate) external_texts: Vec<(usize, Rc<str>)>,
+    /// Replacement texts of the external parsed general entities read so far.
+    pub(crate) external_replacement_texts: BTreeMap<String, String>,
+    /// Spans of the replacement texts of the general entities that cannot be
+    /// expanded into plain text, stored with the external texts so that they
+    /// can be parsed as content where the entities are referenced.
+    pub(crate) entity_contents: BTreeMap<String, Span>,
+    /// General entities whose replacement text is being parsed as content.
+    pub(crate) open_entities: Vec<String>,
+    /// Texts of the external parameter entities read so far.
+    pub(crate) parameter_entity_texts: BTreeMap<String, String>,
+    /// Parameter entities whose replacement text is being processed.
+    pub(crate) parameter_entity_stack: Vec<String>,
+    /// Target, content and base URI of the processing instructions in the
+    /// DTD.
+    pub(crate) processing_instructions: Vec<(Span, Option<Span>, Option<String>)>,
+    /// Whether the document declared itself standalone.
+    pub(crate) standalone: bool,
+    /// Whether the document is processed with the XML 1.1 rules.
+    pub(crate) xml_1_1: bool,
+    /// Whether some markup declarations were not read.
+    pub(crate) skipped_declarations: bool,
+    /// References in the document entity to externally declared entities,
+    /// which a standalone document must not contain.
+    pub(crate) external_entity_references: Vec<Span>,
+}
+
+pub(crate) enum EntityDecl {
+    Internal {
+        replacement_text: String,
+    },
+    External {
+        public_id: Option<Span>,
+        system_id: Span,
+        notation_name: Option<String>,
+        /// Base URI of the entity containing the declaration.
+        base_uri: Option<String>,
+    },
+}
+
+pub(crate) struct ElementDecl {
+    pub(crate) content_spec: ContentSpec,
+    /// Whether the declaration was read from outside the document entity.
+    pub(crate) external: bool,
+}
+
+pub(crate) enum ContentSpec {
+    Empty,
+    Any,
+    /// Mixed content, with the element types that may appear in it.
+    Mixed(Vec<String>),
+    /// Element content.
+    Children(ContentParticle),
+}
+
+pub(crate) struct ContentParticle {
+    pub(crate) kind: ContentParticleKind,
+    pub(crate) occurrence: Occurrence,
+}
+
+pub(crate) enum ContentParticleKind {
+    Name(String),
+    Choice(Vec<ContentParticle>),
+    Seq(Vec<ContentParticle>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Occurrence {
+    Once,
+    Optional,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+pub(crate) struct NotationDecl {
+    pub(crate) name: String,
+    pub(crate) name_span: Span,
+    pub(crate) system_id: Option<Span>,
+    pub(crate) public_id: Option<Span>,
+    /// Base URI of the entity containing the declaration.
+    pub(crate) base_uri: Option<String>,
+}
+
+pub(crate) struct UnparsedEntityDecl {
+    pub(crate) name: Span,
+    pub(crate) system_id: Span,
+    pub(crate) public_id: Option<Span>,
+    pub(crate) notation_name: String,
+    pub(crate) notation_name_span: Span,
+    /// Base URI of the entity containing the declaration.
+    pub(crate) base_uri: Option<String>,
+}
+
+pub(crate) struct AttributeDecl {
+    pub(crate) name: String,
+    pub(crate) name_span: Span,
+    pub(crate) attr_type: AttributeType,
+    pub(crate) default: DefaultDecl,
+    /// Whether the declaration was read from outside the document entity.
+    pub(crate) external: bool,
+}
+
+pub(crate) enum AttributeType {
+    CData,
+    Id,
+    IdRef,
+    IdRefs,
+    Entity,
+    Entities,
+    NmToken,
+    NmTokens,
+    Notation(Vec<String>),
+    Enumeration(Vec<String>),
+}
+
+pub(crate) enum DefaultDecl {
+    Required,
+    Implied,
+    Fixed(AttributeDefault),
+    Default(AttributeDefault),
+}
+
+pub(crate) struct AttributeDefault {
+    pub(crate) raw: String,
+    pub(crate) span: Span,
+}
+
+impl AttributeType {
+    pub(crate) fn is_tokenized(&self) -> bool {
+        !matches!(self, AttributeType::CData)
+    }
+
+    pub(crate) fn to_attr_type_and_references(&self) -> AttrTypeAndReferences {
+        match self {
+            AttributeType::CData => AttrTypeAndReferences::CDATA(None),
+            AttributeType::Id => AttrTypeAndReferences::ID(None),
+            AttributeType::IdRef => AttrTypeAndReferences::IDREF(None),
+            AttributeType::IdRefs => AttrTypeAndReferences::IDREFS(None),
+            AttributeType::Entity => AttrTypeAndReferences::ENTITY(None),
+            AttributeType::Entities => AttrTypeAndReferences::ENTITIES(None),
+            AttributeType::NmToken => AttrTypeAndReferences::NMTOKEN(None),
+            AttributeType::NmTokens => AttrTypeAndReferences::NMTOKENS(None),
+            AttributeType::Notation(_) => AttrTypeAndReferences::NOTATION(None),
+            AttributeType::Enumeration(_) => AttrTypeAndReferences::ENUMERATION(None),
+        }
+    }
+}
+
+impl DefaultDecl {
+    pub(crate) fn value(&self) -> Option<&AttributeDefault> {
+        match self {
+            DefaultDecl::Fixed(value) | DefaultDecl::Default(value) => Some(value),
+            DefaultDecl::Required | DefaultDecl::Implied => None,
+        }
+    }
+}
+
+impl DtdDeclarations {
+    /// Returns the text at `span`, which may lie past the end of `document` in
+    /// a text read while processing the DTD.
+    pub(crate) fn text<'a>(&'a self, document: &'a str, span: &Span) -> &'a str {
+        if span.0.end <= document.len() {
+            return &document[span.0.clone()];
+        }
+        let (offset, text) = self
+            .external_texts
+            .iter()
+            .rev()
+            .find(|(offset, _)| *offset <= span.0.start)
+            .expect("span out of range");
+        &text[span.0.start - offset..span.0.end - offset]
+    }
+
+    /// The notation named `name`, as the notation property of an item.
+    ///
+    /// It has no value when the notation is not declared exactly once, and
+    /// is unknown when its declaration may be among the ones not read.
+    pub(crate) fn notation_named(&self, name: &str) -> UnknownOr<Option<NotationInfoItem>> {
+        match self.notation_items.get(name) {
+            Some(&notation) => UnknownOr::Known(notation),
+            None if self.skipped_declarations => UnknownOr::Unknown,
+            None => UnknownOr::Known(None),
+        }
+    }
+
+    /// Whether entity and attribute-list declarations are processed.
+    ///
+    /// After a reference to a parameter entity that is not read, they are not,
+    /// unless the document is standalone.
+    fn processes_declarations(&self) -> bool {
+        self.standalone || !self.skipped_declarations
+    }
+
+    /// Stores a text read while processing the DTD, returning the offset at which
+    /// it will start once appended to the document text.
+    pub(crate) fn add_external_text(&mut self, input: &str, text: String) -> (usize, Rc<str>) {
+        let offset = match self.external_texts.last() {
+            Some((offset, text)) => offset + text.len(),
+            None => input.len(),
+        };
+        let text: Rc<str> = text.into();
+        self.external_texts.push((offset, text.clone()));
+        (offset, text)
+    }
+
+    /// Stores the replacement texts of the general entities that contain
+    /// markup or references as texts to be appended to the document text.
+    pub(crate) fn add_entity_contents(&mut self, input: &str) {
+        let mut contents = Vec::new();
+        for (name, decl) in &self.general_entities {
+            let replacement_text = match decl {
+                EntityDecl::Internal { replacement_text } => replacement_text,
+                EntityDecl::External {
+                    notation_name: None,
+                    ..
+                } => match self.external_replacement_texts.get(name) {
+                    Some(replacement_text) => replacement_text,
+                    None => continue,
+                },
+                EntityDecl::External { .. } => continue,
+            };
+            if replacement_text.contains(|c| matches!(c, '<' | '&')) {
+                contents.push((name.clone(), replacement_text.clone()));
+            }
+        }
+        for (name, replacement_text) in contents {
+            let (offset, text) = self.add_external_text(input, replacement_text);
+            self.entity_contents
+                .insert(name, Span(offset..offset + text.len()));
+        }
+    }
+
+    /// Appends the texts of the external entities read to the document text.
+    pub(crate) fn append_external_texts(&self, document: &mut String) {
+        for (_, text) in &self.external_texts {
+            document.push_str(text);
+        }
+    }
+
+    pub(crate) fn attribute_decl(&self, element: &str, attribute: &str) -> Option<&AttributeDecl> {
+        self.attribute_lists
+            .get(element)?
+            .iter()
+            .find(|decl| decl.name == attribute)
+    }
+
+    /// Normalizes an attribute value as described in XML 1.0 §3.3.3.
+    ///
+    /// Returns `None` when `raw` is already in normalized form. Each general
+    /// entity expanded on the way is counted in `expansions`.
+    pub(crate) fn normalize_attribute_value(
+        &self,
+        raw: &str,
+        tokenized: bool,
+        expansions: &mut usize,
+    ) -> Result<Option<String>, ParseError> {
+        let needs_expansion = raw.contains(|c| is_attribute_value_special(c, self.xml_1_1));
+        if !needs_expansion && (!tokenized || is_collapsed(raw)) {
+            return Ok(None);
+        }
+        // A line end is replaced by a single space, even when it is `\r\n`.
+        let raw = normalize_line_ends(raw, self.xml_1_1);
+        let mut value = String::with_capacity(raw.len());
+        self.expand_attribute_value(&raw, &mut value, &mut Vec::new(), expansions)?;
+        if tokenized {
+            value = value
+                .split(' ')
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+        Ok(Some(value))
+    }
+
+    fn expand_attribute_value<'a>(
+        &'a self,
+        raw: &str,
+        value: &mut String,
+        entity_stack: &mut Vec<&'a str>,
+        expansions: &mut usize,
+    ) -> Result<(), ParseError> {
+        let mut rest = raw;
+        while let Some(c) = rest.chars().next() {
+            if c != '&' {
+                value.push(if is_attribute_value_special(c, self.xml_1_1) {
+                    ' '
+                } else {
+                    c
+                });
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            let end = rest.find(';').ok_or(ParseError::MalformedReference)?;
+            let reference = &rest[1..end];
+            rest = &rest[end + 1..];
+            if let Some(c) = parse_char_reference(reference, self.xml_1_1)? {
+                value.push(c);
+                continue;
+            }
+            if let Some(c) = predefined_entity(reference) {
+                value.push(c);
+                continue;
+            }
+            let (name, decl) = self
+                .general_entities
+                .get_key_value(reference)
+                .ok_or(ParseError::UndefinedEntity)?;
+            let replacement_text = match decl {
+                EntityDecl::Internal { replacement_text } => replacement_text,
+                EntityDecl::External { .. } => {
+                    return Err(ParseError::ExternalEntityInAttributeValue)
+                }
+            };
+            if entity_stack.contains(&name.as_str()) {
+                return Err(ParseError::RecursiveEntityReference);
+            }
+            entity_stack.push(name);
+            self.expand_attribute_value(replacement_text, value, entity_stack, expansions)?;
+            entity_stack.pop();
+            *expansions += 1;
+        }
+        Ok(())
+    }
+}
+
+impl DtdDeclarations {
+    /// Appends the replacement text of the general entity `name`, with the
+    /// references in it expanded, to `text`.
+    ///
+    /// Returns `false` if the entity cannot be expanded into plain text, as it
+    /// is external or contains markup; see [`DtdDeclarations::entity_contents`].
+    /// Each general entity expanded on the way is counted in `expansions`.
+    pub(crate) fn expand_entity_text(
+        &self,
+        name: &str,
+        text: &mut String,
+        expansions: &mut usize,
+    ) -> Result<bool, ParseError> {
+        self.expand_entity_text_inner(name, text, &mut Vec::new(), expansions)
+    }
+
+    fn expand_entity_text_inner<'a>(
+        &'a self,
+        name: &str,
+        text: &mut String,
+        entity_stack: &mut Vec<&'a str>,
+        expansions: &mut usize,
+    ) -> Result<bool, ParseError> {
+        let (name, decl) = self
+            .general_entities
+            .get_key_value(name)
+            .ok_or(ParseError::UndefinedEntity)?;
+        let replacement_text = match decl {
+            EntityDecl::Internal { replacement_text } => replacement_text,
+            EntityDecl::External {
+                notation_name: None,
+                ..
+            } => match self.external_replacement_texts.get(name) {
+                Some(replacement_text) => replacement_text,
+                None => return Ok(false),
+            },
+            EntityDecl::External { .. } => return Ok(false),
+        };
+        if replacement_text.contains('<') {
+            return Ok(false);
+        }
+        if entity_stack.contains(&name.as_str()) {
+            return Err(ParseError::RecursiveEntityReference);
+        }
+        entity_stack.push(name);
+        let mut rest = replacement_text.as_str();
+        while let Some(start) = rest.find('&') {
+            text.push_str(&rest[..start]);
+            let end = start
+                + rest[start..]
+                    .find(';')
+                    .ok_or(ParseError::MalformedReference)?;
+            let reference = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+            if let Some(c) = parse_char_reference(reference, self.xml_1_1)? {
+                text.push(c);
+            } else if let Some(c) = predefined_entity(reference) {
+                text.push(c);
+            } else if !self.expand_entity_text_inner(reference, text, entity_stack, expansions)? {
+                return Ok(false);
+            }
+        }
+        text.push_str(rest);
+        entity_stack.pop();
+        *expansions += 1;
+        Ok(true)
+    }
+}
+
+/// Returns whether `c` is not kept as is in an attribute value: `&` starts a
+/// reference, and tabs and line ends are replaced by spaces. XML 1.1 has two
+/// more line end characters.
+fn is_attribute_value_special(c: char, xml_1_1: bool) -> bool {
+    matches!(c, '&' | '\t' | '\n' | '\r') || (xml_1_1 && matches!(c, '\u{85}' | '\u{2028}'))
+}
+
+fn is_collapsed(value: &str) -> bool {
+    !value.starts_with(' ') && !value.ends_with(' ') && !value.contains("  ")
+}
+
+pub(crate) fn predefined_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "lt" => '<',
+        "gt" => '>',
+        "amp" => '&',
+        "apos" => '\'',
+        "quot" => '"',
+        _ => return None,
+    })
+}
+
+/// Parses the part of a character reference between `&` and `;`.
+///
+/// Returns `None` if `reference` is an entity reference instead. XML 1.1
+/// allows references to the C0 control characters.
+pub(crate) fn parse_char_reference(
+    reference: &str,
+    xml_1_1: bool,
+) -> Result<Option<char>, ParseError> {
+    let digits = match reference.strip_prefix('#') {
+        Some(digits) => digits,
+        None => return Ok(None),
+    };
+    let code = match digits.strip_prefix('x') {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => digits.parse::<u32>(),
+    };
+    match code.ok().and_then(char::from_u32) {
+        Some(c) if utils::is_xml_char(c) || (xml_1_1 && utils::is_xml_1_1_char(c)) => Ok(Some(c)),
+        _ => Err(ParseError::InvalidCharacterReference),
+    }
+}
+
+/// Parses the internal subset starting at `start` of `input`, returning the
+/// position after the end of the document type declaration.
+pub(crate) fn parse_internal_subset(
+    input: &str,
+    start: usize,
+    declarations: &mut DtdDeclarations,
+    options: &mut ParseOptions<'_>,
+) -> Result<usize, ParseError> {
+    let base_uri = options.base_uri.clone();
+    let mut scanner = DeclScanner {
+        document: input,
+        input,
+        offset: 0,
+        pos: start,
+        end: input.len(),
+        external: false,
+        in_external_subset: false,
+        base_uri: base_uri.as_deref(),
+    };
+    scanner.parse_markup_decls(declarations, options)?;
+    // doctypedecl ::= '<!DOCTYPE' S Name (S ExternalID)? S? ('[' intSubset ']' S?)? '>'
+    scanner.expect("]")?;
+    scanner.skip_whitespace();
+    scanner.expect(">")?;
+    Ok(scanner.pos)
+}
+
+/// Parses the markup declarations of an external subset whose text will
+/// start at `offset` of the document text.
+pub(crate) fn parse_external_subset(
+    document: &str,
+    text: &str,
+    offset: usize,
+    base_uri: Option<&str>,
+    declarations: &mut DtdDeclarations,
+    options: &mut ParseOptions<'_>,
+) -> Result<(), ParseError> {
+    let mut scanner = DeclScanner {
+        document,
+        input: text,
+        offset,
+        pos: offset,
+        end: offset + text.len(),
+        external: true,
+        in_external_subset: true,
+        base_uri,
+    };
+    scanner.parse_markup_decls(declarations, options)
+}
+
+/// The replacement text of a parameter entity.
+struct ParameterEntityText {
+    text: String,
+    /// The URI of an external entity, against which the identifiers declared
+    /// in it are resolved.
+    uri: Option<String>,
+}
+
+struct DeclScanner<'a> {
+    /// The document entity.
+    document: &'a str,
+    input: &'a str,
+    /// Offset of `input` in the document text.
+    offset: usize,
+    pos: usize,
+    end: usize,
+    /// Whether the declarations are external markup declarations, i.e. not
+    /// read from the internal subset itself.
+    external: bool,
+    /// Whether the text is part of the external subset or of an external
+    /// parameter entity, where parameter entity references may occur within
+    /// markup declarations and conditional sections are allowed.
+    in_external_subset: bool,
+    base_uri: Option<&'a str>,
+}
+
+impl<'a> DeclScanner<'a> {
+    fn parse_markup_decls(
+        &mut self,
+        declarations: &mut DtdDeclarations,
+        options: &mut ParseOptions<'_>,
+    ) -> Result<(), ParseError> {
+        let mut open_sections = 0;
+        loop {
+            self.skip_whitespace();
+            if self.is_at_end() {
+                break;
+            }
+            // The end of the internal subset.
+            if !self.in_external_subset && self.rest().starts_with(']') {
+                break;
+            }
+            if self.in_external_subset
+                && self.rest().starts_with("<!")
+                && !self.rest().starts_with("<!--")
+                && !self.rest().starts_with("<![")
+            {
+                let len = declaration_len(self.rest()).ok_or(ParseError::MalformedDeclaration)?;
+                let decl = &self.rest()[..len];
+                if !parameter_references(decl).is_empty() {
+                    self.pos += len;
+                    self.parse_expanded_decl(decl, declarations, options)?;
+                    continue;
+                }
+            }
+            if self.eat("<!--") {
+                self.skip_past("-->")?;
+            } else if self.eat("<?") {
+                self.parse_processing_instruction(declarations)?;
+            } else if self.eat("<!ENTITY") {
+                self.parse_entity_decl(declarations, options)?;
+            } else if self.eat("<!ATTLIST") {
+                self.parse_attlist_decl(declarations)?;
+            } else if self.eat("<!NOTATION") {
+                self.parse_notation_decl(declarations)?;
+            } else if self.eat("<!ELEMENT") {
+                self.parse_element_decl(declarations)?;
+            } else if self.in_external_subset && self.eat("<![") {
+                if self.parse_conditional_section_start(declarations, options)? {
+                    open_sections += 1;
+                }
+            } else if open_sections > 0 && self.eat("]]>") {
+                open_sections -= 1;
+            } else if self.eat("%") {
+                let name = self.parameter_entity_reference()?;
+                self.include_parameter_entity(name, declarations, options)?;
+            } else {
+                return Err(ParseError::MalformedDeclaration);
+            }
+        }
+        if open_sections > 0 {
+            return Err(ParseError::MalformedDeclaration);
+        }
+        Ok(())
+    }
+
+    // PEReference ::= '%' Name ';'
+    //
+    // The `%` has already been consumed.
+    fn parameter_entity_reference(&mut self) -> Result<&'a str, ParseError> {
+        let (name, _) = self.name()?;
+        self.expect(";")?;
+        Ok(name)
+    }
+
+    /// Returns the replacement text of the parameter entity `name`, or `None`
+    /// if it is not read.
+    fn parameter_entity_text(
+        &self,
+        name: &str,
+        declarations: &mut DtdDeclarations,
+        options: &mut ParseOptions<'_>,
+    ) -> Result<Option<ParameterEntityText>, ParseError> {
+        let (public_id, system_id, base_uri) = match declarations.parameter_entities.get(name) {
+            None => return Ok(None),
+            Some(EntityDecl::Internal { replacement_text }) => {
+                return Ok(Some(ParameterEntityText {
+                    text: replacement_text.clone(),
+                    uri: None,
+                }))
+            }
+            Some(EntityDecl::External {
+                public_id,
+                system_id,
+                base_uri,
+                ..
+            }) => (
+                public_id
+                    .as_ref()
+                    .map(|span| declarations.text(self.document, span).to_owned()),
+                declarations.text(self.document, system_id).to_owned(),
+                base_uri.clone(),
+            ),
+        };
+        let uri = uri::resolve_opt(base_uri.as_deref(), &system_id);
+        if let Some(text) = declarations.parameter_entity_texts.get(name) {
+            return Ok(Some(ParameterEntityText {
+                text: text.clone(),
+                uri: Some(uri),
+            }));
+        }
+        if !options.load_external_subset {
+            return Ok(None);
+        }
+        let text = match options.load_external_text(
+            public_id.as_deref(),
+            &system_id,
+            base_uri.as_deref(),
+            declarations.xml_1_1,
+        )? {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+        declarations
+            .parameter_entity_texts
+            .insert(name.to_owned(), text.clone());
+        Ok(Some(ParameterEntityText {
+            text,
+            uri: Some(uri),
+        }))
+    }
+
+    fn enter_parameter_entity(
+        &self,
+        name: &str,
+        declarations: &mut DtdDeclarations,
+    ) -> Result<(), ParseError> {
+        if declarations
+            .parameter_entity_stack
+            .iter()
+            .any(|entity| entity == name)
+        {
+            return Err(ParseError::RecursiveEntityReference);
+        }
+        declarations.parameter_entity_stack.push(name.to_owned());
+        Ok(())
+    }
+
+    /// Parses the markup declarations in the replacement text of a parameter
+    /// entity referenced between declarations.
+    fn include_parameter_entity(
+        &self,
+        name: &str,
+        declarations: &mut DtdDeclarations,
+        options: &mut ParseOptions<'_>,
+    ) -> Result<(), ParseError> {
+        let entity = match self.parameter_entity_text(name, declarations, options)? {
+            Some(entity) => entity,
+            None => {
+                declarations.skipped_declarations = true;
+                return Ok(());
+            }
+        };
+        self.enter_parameter_entity(name, declarations)?;
+        let (offset, text) = declarations.add_external_text(self.document, entity.text);
+        let mut scanner = DeclScanner {
+            document: self.document,
+            input: &text,
+            offset,
+            pos: offset,
+            end: offset + text.len(),
+            external: true,
+            in_external_subset: self.in_external_subset || entity.uri.is_some(),
+            base_uri: entity.uri.as_deref().or(self.base_uri),
+        };
+        scanner.parse_markup_decls(declarations, options)?;
+        if !scanner.is_at_end() {
+            return Err(ParseError::MalformedDeclaration);
+        }
+        declarations.parameter_entity_stack.pop();
+        Ok(())
+    }
+
+    // PI ::= '<?' PITarget (S (Char* - (Char* '?>' Char*)))? '?>'
+    //
+    // The `<?` has already been consumed.
+    fn parse_processing_instruction(
+        &mut self,
+        declarations: &mut DtdDeclarations,
+    ) -> Result<(), ParseError> {
+        let (target, target_span) = self.name()?;
+        if target.eq_ignore_ascii_case("xml") {
+            return Err(ParseError::MalformedDeclaration);
+        }
+        let content = if self.eat("?>") {
+            None
+        } else {
+            self.expect_whitespace()?;
+            let start = self.pos;
+            self.skip_past("?>")?;
+            let end = self.pos - "?>".len();
+            (start < end).then(|| Span(start..end))
+        };
+        declarations.processing_instructions.push((
+            target_span,
+            content,
+            self.base_uri.map(str::to_owned),
+        ));
+        Ok(())
+    }
+
+    /// Parses a markup declaration with the parameter entity references in it
+    /// replaced by their replacement texts.
+    fn parse_expanded_decl(
+        &self,
+        decl: &str,
+        declarations: &mut DtdDeclarations,
+        options: &mut ParseOptions<'_>,
+    ) -> Result<(), ParseError> {
+        let mut expanded = String::with_capacity(decl.len());
+        if !self.expand_parameter_references(decl, &mut expanded, declarations, options)? {
+            declarations.skipped_declarations = true;
+            return Ok(());
+        }
+        let (offset, text) = declarations.add_external_text(self.document, expanded);
+        let mut scanner = DeclScanner {
+            document: self.document,
+            input: &text,
+            offset,
+            pos: offset,
+            end: offset + text.len(),
+            external: self.external,
+            in_external_subset: true,
+            base_uri: self.base_uri,
+        };
+        scanner.parse_markup_decls(declarations, options)
+    }
+
+    /// Appends `text` to `expanded`, replacing the parameter entity references
+    /// outside literals by their replacement texts padded with spaces.
+    ///
+    /// Returns `false` if one of the entities is not read.
+    fn expand_parameter_references(
+        &self,
+        text: &str,
+        expanded: &mut String,
+        declarations: &mut DtdDeclarations,
+        options: &mut ParseOptions<'_>,
+    ) -> Result<bool, ParseError> {
+        let mut pos = 0;
+        for (range, name) in parameter_references(text) {
+            expanded.push_str(&text[pos..range.start]);
+            pos = range.end;
+            let entity = match self.parameter_entity_text(name, declarations, options)? {
+                Some(entity) => entity,
+                None => return Ok(false),
+            };
+            self.enter_parameter_entity(name, declarations)?;
+            expanded.push(' ');
+            let is_read =
+                self.expand_parameter_references(&entity.text, expanded, declarations, options)?;
+            declarations.parameter_entity_stack.pop();
+            if !is_read {
+                return Ok(false);
+            }
+            expanded.push(' ');
+        }
+        expanded.push_str(&text[pos..]);
+        Ok(true)
+    }
+
+    // conditionalSect ::= includeSect | ignoreSect
+    // includeSect     ::= '<![' S? 'INCLUDE' S? '[' extSubsetDecl ']]>'
+    // ignoreSect      ::= '<![' S? 'IGNORE' S? '[' ignoreSectContents* ']]>'
+    //
+    // The `<![` has already been consumed. Returns `true` for an include
+    // section, whose content follows; an ignore section is skipped entirely.
+    fn parse_conditional_section_start(
+        &mut self,
+        declarations: &mut DtdDeclarations,
+        options: &mut ParseOptions<'_>,
+    ) -> Result<bool, ParseError> {
+        self.skip_whitespace();
+        let keyword = if self.eat("%") {
+            let name = self.parameter_entity_reference()?;
+            match self.parameter_entity_text(name, declarations, options)? {
+                Some(entity) => Some(entity.text.trim().to_owned()),
+                None => None,
+            }
+        } else {
+            Some(self.name()?.0.to_owned())
+        };
+        self.skip_whitespace();
+        self.expect("[")?;
+        match keyword.as_deref() {
+            Some("INCLUDE") => return Ok(true),
+            Some("IGNORE") => {}
+            Some(_) => return Err(ParseError::MalformedDeclaration),
+            // The section cannot be told apart from an ignore section.
+            None => declarations.skipped_declarations = true,
+        }
+        let mut depth = 1;
+        while depth > 0 {
+            let rest = self.rest();
+            let close = rest.find("]]>").ok_or(ParseError::MalformedDeclaration)?;
+            match rest.find("<![") {
+                Some(open) if open < close => {
+                    self.pos += open + "<![".len();
+                    depth += 1;
+                }
+                _ => {
+                    self.pos += close + "]]>".len();
+                    depth -= 1;
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Computes the replacement text of an internal entity from its literal
+    /// entity value.
+    ///
+    /// Returns `None` if a parameter entity referenced in it is not read.
+    fn entity_value(
+        &self,
+        literal: &str,
+        declarations: &mut DtdDeclarations,
+        options: &mut ParseOptions<'_>,
+    ) -> Result<Option<String>, ParseError> {
+        // Literal line ends are normalized, unlike character references to
+        // line end characters.
+        let literal = normalize_line_ends(literal, declarations.xml_1_1);
+        let mut replacement_text = String::with_capacity(literal.len());
+        let mut rest = &*literal;
+        while let Some(start) = rest.find(&['&', '%'][..]) {
+            replacement_text.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(';').ok_or(ParseError::MalformedReference)?;
+            let reference = &rest[1..end];
+            if rest.starts_with('&') {
+                match parse_char_reference(reference, declarations.xml_1_1)? {
+                    Some(c) => replacement_text.push(c),
+                    // General entity references are bypassed.
+                    None => replacement_text.push_str(&rest[..=end]),
+                }
+            } else {
+                // Parameter entity references may not occur within markup
+                // declarations in the internal subset.
+                if !self.in_external_subset {
+                    return Err(ParseError::MalformedDeclaration);
+                }
+                let entity = match self.parameter_entity_text(reference, declarations, options)? {
+                    Some(entity) => entity,
+                    None => return Ok(None),
+                };
+                if entity.uri.is_none() {
+                    replacement_text.push_str(&entity.text);
+                } else {
+                    self.enter_parameter_entity(reference, declarations)?;
+                    let text = self.entity_value(&entity.text, declarations, options)?;
+                    declarations.parameter_entity_stack.pop();
+                    match text {
+                        Some(text) => replacement_text.push_str(&text),
+                        None => return Ok(None),
+                    }
+                }
+            }
+            rest = &rest[end + 1..];
+        }
+        replacement_text.push_str(rest);
+        Ok(Some(replacement_text))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos - self.offset..self.end - self.offset]
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.pos >= self.end
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), ParseError> {
+        if self.eat(s) {
+            Ok(())
+        } else {
+            Err(ParseError::MalformedDeclaration)
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = self.rest();
+        let trimmed = rest.trim_start_matches(|c| matches!(c, ' ' | '\t' | '\n' | '\r'));
+        self.pos += rest.len() - trimmed.len();
+        trimmed.len() != rest.len()
+    }
+
+    fn expect_whitespace(&mut self) -> Result<(), ParseError> {
+        if self.skip_whitespace() {
+            Ok(())
+        } else {
+            Err(ParseError::MalformedDeclaration)
+        }
+    }
+
+    fn skip_past(&mut self, s: &str) -> Result<(), ParseError> {
+        let offset = self
+            .rest()
+            .find(s)
+            .ok_or(ParseError::MalformedDeclaration)?;
+        self.pos += offset + s.len();
+        Ok(())
+    }
+
+    fn name_token(&mut self) -> Result<(&'a str, Span), ParseError> {
+        let rest = self.rest();
+        let len = rest.find(|c| !utils::is_name_char(c)).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(ParseError::MalformedDeclaration);
+        }
+        let span = Span(self.pos..self.pos + len);
+        self.pos += len;
+        Ok((&rest[..len], span))
+    }
+
+    fn name(&mut self) -> Result<(&'a str, Span), ParseError> {
+        let (name, span) = self.name_token()?;
+        if !name.starts_with(utils::is_name_start_char) {
+            return Err(ParseError::MalformedDeclaration);
+        }
+        Ok((name, span))
+    }
+
+    /// Reads a quoted literal, returning its content without the quotes.
+    fn quoted(&mut self) -> Result<(&'a str, Span), ParseError> {
+        let quote = match self.rest().chars().next() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => return Err(ParseError::MalformedDeclaration),
+        };
+        self.pos += 1;
+        let len = self
+            .rest()
+            .find(quote)
+            .ok_or(ParseError::MalformedDeclaration)?;
+        let span = Span(self.pos..self.pos + len);
+        let content = &self.rest()[..len];
+        self.pos += len + 1;
+        Ok((content, span))
+    }
+
+    // ExternalID ::= 'SYSTEM' S SystemLiteral | 'PUBLIC' S PubidLiteral S SystemLiteral
+    fn external_id(&mut self) -> Result<Option<(Option<Span>, Span)>, ParseError> {
+        if self.eat("SYSTEM") {
+            self.expect_whitespace()?;
+            let (_, system_id) = self.quoted()?;
+            Ok(Some((None, system_id)))
+        } else if self.eat("PUBLIC") {
+            self.expect_whitespace()?;
+            let (_, public_id) = self.quoted()?;
+            self.expect_whitespace()?;
+            let (_, system_id) = self.quoted()?;
+            Ok(Some((Some(public_id), system_id)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // NotationDecl ::= '<!NOTATION' S Name S (ExternalID | PublicID) S? '>'
+    // PublicID     ::= 'PUBLIC' S PubidLiteral
+    fn parse_notation_decl(
+        &mut self,
+        declarations: &mut DtdDeclarations,
+    ) -> Result<(), ParseError> {
+        self.expect_whitespace()?;
+        let (name, name_span) = self.name()?;
+        self.expect_whitespace()?;
+        let (system_id, public_id) = if self.eat("SYSTEM") {
+            self.expect_whitespace()?;
+            (Some(self.quoted()?.1), None)
+        } else if self.eat("PUBLIC") {
+            self.expect_whitespace()?;
+            let public_id = self.quoted()?.1;
+            let had_whitespace = self.skip_whitespace();
+            if had_whitespace && !self.rest().starts_with('>') {
+                (Some(self.quoted()?.1), Some(public_id))
+            } else {
+                (None, Some(public_id))
+            }
+        } else {
+            return Err(ParseError::MalformedDeclaration);
+        };
+        self.skip_whitespace();
+        self.expect(">")?;
+        declarations.notations.push(NotationDecl {
+            name: name.to_owned(),
+            name_span,
+            system_id,
+            public_id,
+            base_uri: self.base_uri.map(str::to_owned),
+        });
+        Ok(())
+    }
+
+    // EntityDecl ::= '<!ENTITY' S Name S EntityDef S? '>'
+    //              | '<!ENTITY' S '%' S Name S PEDef S? '>'
+    fn parse_entity_decl(
+        &mut self,
+        declarations: &mut DtdDeclarations,
+        options: &mut ParseOptions<'_>,
+    ) -> Result<(), ParseError> {
+        self.expect_whitespace()?;
+        let is_parameter_entity = self.eat("%");
+        if is_parameter_entity {
+            self.expect_whitespace()?;
+        }
+        let (name, name_span) = self.name()?;
+        self.expect_whitespace()?;
+        let mut unparsed_entity = None;
+        let decl = match self.external_id()? {
+            Some((public_id, system_id)) => {
+                let had_whitespace = self.skip_whitespace();
+                let notation_name = if had_whitespace && self.eat("NDATA") {
+                    if is_parameter_entity {
+                        return Err(ParseError::MalformedDeclaration);
+                    }
+                    self.expect_whitespace()?;
+                    let (notation_name, notation_name_span) = self.name()?;
+                    unparsed_entity = Some(UnparsedEntityDecl {
+                        name: name_span,
+                        system_id: system_id.clone(),
+                        public_id: public_id.clone(),
+                        notation_name: notation_name.to_owned(),
+                        notation_name_span,
+                        base_uri: self.base_uri.map(str::to_owned),
+                    });
+                    Some(notation_name.to_owned())
+                } else {
+                    None
+                };
+                Some(EntityDecl::External {
+                    public_id,
+                    system_id,
+                    notation_name,
+                    base_uri: self.base_uri.map(str::to_owned),
+                })
+            }
+            None => {
+                let (literal, _) = self.quoted()?;
+                self.entity_value(literal, declarations, options)?
+                    .map(|replacement_text| EntityDecl::Internal { replacement_text })
+            }
+        };
+        self.skip_whitespace();
+        self.expect(">")?;
+        let decl = match decl {
+            Some(decl) => decl,
+            // The replacement text depends on a parameter entity that is not
+            // read.
+            None => {
+                declarations.skipped_declarations = true;
+                return Ok(());
+            }
+        };
+        if !declarations.processes_declarations() {
+            return Ok(());
+        }
+        let entities = if is_parameter_entity {
+            &mut declarations.parameter_entities
+        } else {
+            &mut declarations.general_entities
+        };
+        // The first declaration of an entity is binding.
+        if entities.contains_key(name) {
+            return Ok(());
+        }
+        entities.insert(name.to_owned(), decl);
+        if self.external && !is_parameter_entity {
+            declarations
+                .external_general_entities
+                .insert(name.to_owned());
+        }
+        declarations.unparsed_entities.extend(unparsed_entity);
+        Ok(())
+    }
+
+    // elementdecl ::= '<!ELEMENT' S Name S contentspec S? '>'
+    // contentspec ::= 'EMPTY' | 'ANY' | Mixed | children
+    fn parse_element_decl(&mut self, declarations: &mut DtdDeclarations) -> Result<(), ParseError> {
+        self.expect_whitespace()?;
+        let (name, _) = self.name()?;
+        self.expect_whitespace()?;
+        let content_spec = if self.eat("EMPTY") {
+            ContentSpec::Empty
+        } else if self.eat("ANY") {
+            ContentSpec::Any
+        } else {
+            self.expect("(")?;
+            self.skip_whitespace();
+            if self.eat("#PCDATA") {
+                ContentSpec::Mixed(self.mixed()?)
+            } else {
+                let kind = self.group()?;
+                let occurrence = self.occurrence();
+                ContentSpec::Children(ContentParticle { kind, occurrence })
+            }
+        };
+        self.skip_whitespace();
+        self.expect(">")?;
+        // An element type must not be declared more than once; the first
+        // declaration is kept.
+        declarations
+            .element_decls
+            .entry(name.to_owned())
+            .or_insert(ElementDecl {
+                content_spec,
+                external: self.external,
+            });
+        Ok(())
+    }
+
+    // Mixed ::= '(' S? '#PCDATA' (S? '|' S? Name)* S? ')*'
+    //         | '(' S? '#PCDATA' S? ')'
+    fn mixed(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut names = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.eat(")") {
+                break;
+            }
+            self.expect("|")?;
+            self.skip_whitespace();
+            names.push(self.name()?.0.to_owned());
+        }
+        if !self.eat("*") && !names.is_empty() {
+            return Err(ParseError::MalformedDeclaration);
+        }
+        Ok(names)
+    }
+
+    // choice ::= '(' S? cp ( S? '|' S? cp )+ S? ')'
+    // seq    ::= '(' S? cp ( S? ',' S? cp )* S? ')'
+    //
+    // The opening parenthesis has already been consumed.
+    fn group(&mut self) -> Result<ContentParticleKind, ParseError> {
+        let mut particles = vec![self.content_particle()?];
+        let mut separator = None;
+        loop {
+            self.skip_whitespace();
+            if self.eat(")") {
+                break;
+            }
+            let next_separator = if self.eat("|") {
+                '|'
+            } else if self.eat(",") {
+                ','
+            } else {
+                return Err(ParseError::MalformedDeclaration);
+            };
+            if separator.map_or(false, |separator| separator != next_separator) {
+                return Err(ParseError::MalformedDeclaration);
+            }
+            separator = Some(next_separator);
+            particles.push(self.content_particle()?);
+        }
+        Ok(if separator == Some('|') {
+            ContentParticleKind::Choice(particles)
+        } else {
+            ContentParticleKind::Seq(particles)
+        })
+    }
+
+    // cp ::= (Name | choice | seq) ('?' | '*' | '+')?
+    fn content_particle(&mut self) -> Result<ContentParticle, ParseError> {
+        self.skip_whitespace();
+        let kind = if self.eat("(") {
+            self.skip_whitespace();
+            self.group()?
+        } else {
+            ContentParticleKind::Name(self.name()?.0.to_owned())
+        };
+        let occurrence = self.occurrence();
+        Ok(ContentParticle { kind, occurrence })
+    }
+
+    fn occurrence(&mut self) -> Occurrence {
+        if self.eat("?") {
+            Occurrence::Optional
+        } else if self.eat("*") {
+            Occurrence::ZeroOrMore
+        } else if self.eat("+") {
+            Occurrence::OneOrMore
+        } else {
+            Occurrence::Once
+        }
+    }
+
+    // AttlistDecl ::= '<!ATTLIST' S Name AttDef* S? '>'
+    // AttDef      ::= S Name S AttType S DefaultDecl
+    fn parse_attlist_decl(&mut self, declarations: &mut DtdDeclarations) -> Result<(), ParseError> {
+        self.expect_whitespace()?;
+        let (element, _) = self.name()?;
+        let processes_declarations = declarations.processes_declarations();
+        let attribute_list = declarations
+            .attribute_lists
+            .entry(element.to_owned())
+            .or_default();
+        loop {
+            let had_whitespace = self.skip_whitespace();
+            if self.eat(">") {
+                break;
+            }
+            if !had_whitespace {
+                return Err(ParseError::MalformedDeclaration);
+            }
+            let (name, name_span) = self.name()?;
+            self.expect_whitespace()?;
+            let attr_type = self.attribute_type()?;
+            self.expect_whitespace()?;
+            let default = self.default_decl()?;
+            // When more than one definition is provided for the same attribute,
+            // the first one is binding.
+            if processes_declarations && attribute_list.iter().all(|decl| decl.name != name) {
+                attribute_list.push(AttributeDecl {
+                    name: name.to_owned(),
+                    name_span,
+                    attr_type,
+                    default,
+                    external: self.external,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn attribute_type(&mut self) -> Result<AttributeType, ParseError> {
+        if self.rest().starts_with('(') {
+            return Ok(AttributeType::Enumeration(self.enumeration(false)?));
+        }
+        let (keyword, _) = self.name_token()?;
+        Ok(match keyword {
+            "CDATA" => AttributeType::CData,
+            "ID" => AttributeType::Id,
+            "IDREF" => AttributeType::IdRef,
+            "IDREFS" => AttributeType::IdRefs,
+            "ENTITY" => AttributeType::Entity,
+            "ENTITIES" => AttributeType::Entities,
+            "NMTOKEN" => AttributeType::NmToken,
+            "NMTOKENS" => AttributeType::NmTokens,
+            "NOTATION" => {
+                self.expect_whitespace()?;
+                AttributeType::Notation(self.enumeration(true)?)
+            }
+            _ => return Err(ParseError::MalformedDeclaration),
+        })
+    }
+
+    // Enumeration ::= '(' S? Nmtoken (S? '|' S? Nmtoken)* S? ')'
+    fn enumeration(&mut self, names: bool) -> Result<Vec<String>, ParseError> {
+        self.expect("(")?;
+        let mut values = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let (value, _) = if names {
+                self.name()?
+            } else {
+                self.name_token()?
+            };
+            values.push(value.to_owned());
+            self.skip_whitespace();
+            if self.eat(")") {
+                return Ok(values);
+            }
+            self.expect("|")?;
+        }
+    }
+
+    // DefaultDecl ::= '#REQUIRED' | '#IMPLIED' | (('#FIXED' S)? AttValue)
+    fn default_decl(&mut self) -> Result<DefaultDecl, ParseError> {
+        if self.eat("#REQUIRED") {
+            return Ok(DefaultDecl::Required);
+        }
+        if self.eat("#IMPLIED") {
+            return Ok(DefaultDecl::Implied);
+        }
+        let fixed = self.eat("#FIXED");
+        if fixed {
+            self.expect_whitespace()?;
+        }
+        let (raw, span) = self.quoted()?;
+        if raw.contains('<') {
+            return Err(ParseError::MalformedDeclaration);
+        }
+        let value = AttributeDefault {
+            raw: raw.to_owned(),
+            span,
+        };
+        Ok(if fixed {
+            DefaultDecl::Fixed(value)
+        } else {
+            DefaultDecl::Default(value)
+        })
+    }
+}
+
+/// Returns the length of the markup declaration at the start of `rest`, up to
+/// and including the closing `>`.
+fn declaration_len(rest: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in rest.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return Some(i + 1),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Finds the parameter entity references outside literals in `text`.
+fn parameter_references(text: &str) -> Vec<(ops::Range<usize>, &str)> {
+    let mut references = Vec::new();
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '%' => {
+                let rest = &text[i + 1..];
+                let len = rest.find(|c| !utils::is_name_char(c)).unwrap_or(rest.len());
+                if rest.starts_with(utils::is_name_start_char) && rest[len..].starts_with(';') {
+                    references.push((i..i + len + 2, &rest[..len]));
+                }
+            }
+            None => {}
+        }
+    }
+    references
+}
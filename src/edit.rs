@@ -0,0 +1,476 @@
+//! Updating an info set after its input has been edited.
+//!
+//! The text of the items never moves: the new text of a reparsed element is
+//! appended to the input, and the document text is kept as the list of
+//! pieces of the input it is made of.
+
+use super::parse::{
+    parse_element_tree, parse_with_options, ParseError, ParseOptions, TreeContext,
+    UnknownVersionPolicy,
+};
+use super::resolver::{EntityResolver, ResolveError};
+use super::visit::ChildItems;
+use super::*;
+use xmlparser::Tokenizer as XmlTokenizer;
+
+/// An external entity read while parsing a document.
+#[derive(Clone, Debug)]
+struct RecordedEntity {
+    public_id: Option<String>,
+    system_id: String,
+    base_uri: Option<String>,
+    bytes: Vec<u8>,
+}
+
+/// Passes requests on to another resolver, keeping the entities it returns
+/// so that the document can be parsed again without it.
+pub(crate) struct RecordingResolver<'r> {
+    resolver: &'r mut dyn EntityResolver,
+    entities: Vec<RecordedEntity>,
+}
+
+impl<'r> RecordingResolver<'r> {
+    pub(crate) fn new(resolver: &'r mut dyn EntityResolver) -> Self {
+        RecordingResolver {
+            resolver,
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl EntityResolver for RecordingResolver<'_> {
+    fn resolve_entity(
+        &mut self,
+        public_id: Option<&str>,
+        system_id: &str,
+        base_uri: Option<&str>,
+    ) -> Result<Vec<u8>, ResolveError> {
+        let bytes = self
+            .resolver
+            .resolve_entity(public_id, system_id, base_uri)?;
+        self.entities.push(RecordedEntity {
+            public_id: public_id.map(str::to_owned),
+            system_id: system_id.to_owned(),
+            base_uri: base_uri.map(str::to_owned),
+            bytes: bytes.clone(),
+        });
+        Ok(bytes)
+    }
+}
+
+/// Returns the entities read while the document was first parsed.
+struct ReplayResolver<'e> {
+    entities: &'e [RecordedEntity],
+}
+
+impl EntityResolver for ReplayResolver<'_> {
+    fn resolve_entity(
+        &mut self,
+        public_id: Option<&str>,
+        system_id: &str,
+        base_uri: Option<&str>,
+    ) -> Result<Vec<u8>, ResolveError> {
+        self.entities
+            .iter()
+            .find(|entity| {
+                entity.system_id == system_id
+                    && entity.public_id.as_deref() == public_id
+                    && entity.base_uri.as_deref() == base_uri
+            })
+            .map(|entity| entity.bytes.clone())
+            .ok_or_else(|| ResolveError::NotFound(system_id.to_owned()))
+    }
+}
+
+/// The options a document was parsed with, kept to parse it again after an
+/// edit.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StoredOptions {
+    load_external_subset: bool,
+    load_external_entities: bool,
+    base_uri: Option<String>,
+    unknown_version_policy: UnknownVersionPolicy,
+    recover: bool,
+    entities: Vec<RecordedEntity>,
+}
+
+impl StoredOptions {
+    pub(crate) fn new(options: &ParseOptions<'_>) -> Self {
+        StoredOptions {
+            load_external_subset: options.load_external_subset,
+            load_external_entities: options.load_external_entities,
+            base_uri: options.base_uri.clone(),
+            unknown_version_policy: options.unknown_version_policy,
+            recover: options.recover,
+            entities: Vec::new(),
+        }
+    }
+
+    /// Keeps the entities read through `resolver`.
+    pub(crate) fn keep_entities(&mut self, resolver: RecordingResolver<'_>) {
+        self.entities = resolver.entities;
+    }
+
+    fn parse_options<'r>(&self, resolver: &'r mut ReplayResolver<'_>) -> ParseOptions<'r> {
+        ParseOptions {
+            entity_resolver: Some(resolver),
+            load_external_subset: self.load_external_subset,
+            load_external_entities: self.load_external_entities,
+            base_uri: self.base_uri.clone(),
+            unknown_version_policy: self.unknown_version_policy,
+            recover: self.recover,
+        }
+    }
+}
+
+/// Where the text of the document is in the input of an info set, and how
+/// to parse it again.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DocumentText {
+    /// The ranges of the input that make up the document text, in order.
+    pieces: Vec<ops::Range<usize>>,
+    /// Length of the text whose items were replaced or discarded by edits.
+    /// They are left in the repository until the document is parsed again.
+    replaced: usize,
+    options: StoredOptions,
+}
+
+impl DocumentText {
+    /// The document text is the first `len` bytes of the input.
+    pub(crate) fn new(len: usize, options: StoredOptions) -> Self {
+        DocumentText {
+            pieces: vec![0..len],
+            replaced: 0,
+            options,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.pieces.iter().map(ops::Range::len).sum()
+    }
+
+    /// Returns the offset in the input of the document offset `offset`.
+    fn input_offset(&self, offset: usize) -> Option<usize> {
+        let mut start = 0;
+        for piece in &self.pieces {
+            if offset <= start + piece.len() {
+                return Some(piece.start + offset - start);
+            }
+            start += piece.len();
+        }
+        None
+    }
+
+    /// Returns the document offset of the input offset `offset`. An offset
+    /// at the end of a piece is only part of it as the end of a range.
+    fn document_offset(&self, offset: usize, is_end: bool) -> Option<usize> {
+        let mut start = 0;
+        for piece in &self.pieces {
+            let in_piece = if is_end {
+                piece.start < offset && offset <= piece.end
+            } else {
+                piece.start <= offset && offset < piece.end
+            };
+            if in_piece {
+                return Some(start + offset - piece.start);
+            }
+            start += piece.len();
+        }
+        None
+    }
+
+    fn document_range(&self, range: ops::Range<usize>) -> Option<ops::Range<usize>> {
+        if range.is_empty() {
+            let offset = self
+                .document_offset(range.start, false)
+                .or_else(|| self.document_offset(range.start, true))?;
+            return Some(offset..offset);
+        }
+        let start = self.document_offset(range.start, false)?;
+        let end = self.document_offset(range.end, true)?;
+        Some(start..end).filter(|range| range.start < range.end)
+    }
+
+    /// Returns the ranges of the input that make up `range` of the document
+    /// text.
+    fn input_ranges(&self, range: ops::Range<usize>) -> Vec<ops::Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        for piece in &self.pieces {
+            let end = start + piece.len();
+            if start < range.end && range.start < end {
+                ranges.push(
+                    piece.start + range.start.max(start) - start
+                        ..piece.start + range.end.min(end) - start,
+                );
+            }
+            start = end;
+        }
+        ranges
+    }
+
+    fn push_range(&self, input: &str, range: ops::Range<usize>, text: &mut String) {
+        for range in self.input_ranges(range) {
+            text.push_str(&input[range]);
+        }
+    }
+
+    /// Makes `new` of the input the text of `old` of the document.
+    fn replace(&mut self, old: ops::Range<usize>, new: ops::Range<usize>) {
+        let len = self.len();
+        let mut pieces = self.input_ranges(0..old.start);
+        pieces.push(new);
+        pieces.extend(self.input_ranges(old.end..len));
+        self.pieces = pieces;
+        self.replaced += old.len();
+    }
+}
+
+impl<'input> InfoSet<'input> {
+    /// Returns the text of the document, with the edits made to it.
+    pub fn text(&self) -> Cow<'_, str> {
+        match &self.document.pieces[..] {
+            [piece] => Cow::Borrowed(&self.input[piece.clone()]),
+            pieces => Cow::Owned(
+                pieces
+                    .iter()
+                    .map(|piece| &self.input[piece.clone()])
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns the range of [`InfoSet::text`] that `span` is at.
+    ///
+    /// Spans are ranges of the text held by the info set, which stops being
+    /// the document text after an edit. Returns `None` for text that is not
+    /// part of the document, such as that of external entities or the text
+    /// replaced by an edit.
+    pub fn document_range(&self, span: &Span) -> Option<ops::Range<usize>> {
+        self.document.document_range(span.range())
+    }
+
+    /// Replaces the text at `range` of [`InfoSet::text`] with `new_text`, and
+    /// updates the info set to match.
+    ///
+    /// Only the smallest element that contains the edit and whose start and
+    /// end tags are left as they were is parsed again, and it replaces the
+    /// old one; the other items are left as they are. When there is no such
+    /// element, the whole document is parsed again. Both use the options the
+    /// document was parsed with, and the external entities read then.
+    ///
+    /// The replaced items are only dropped when the whole document is parsed
+    /// again, which is also done once they outweigh the document. Items
+    /// obtained before an edit are not valid after that.
+    ///
+    /// Fails if `range` is not a range of the text on character boundaries.
+    /// On error, the info set is left unchanged.
+    pub fn apply_edit(
+        &mut self,
+        range: ops::Range<usize>,
+        new_text: &str,
+    ) -> Result<(), ParseError> {
+        let len = self.document.len();
+        let is_char_boundary = |offset| {
+            self.document
+                .input_offset(offset)
+                .map_or(false, |offset| self.input.is_char_boundary(offset))
+        };
+        if range.start > range.end
+            || range.end > len
+            || !is_char_boundary(range.start)
+            || !is_char_boundary(range.end)
+        {
+            return Err(ParseError::InvalidEditRange);
+        }
+        for (element, extent) in self.enclosing_elements(&range).into_iter().rev() {
+            let new_element = match self.reparse_element(element, extent, &range, new_text) {
+                Some(new_element) => new_element,
+                None => continue,
+            };
+            replace_element(&mut self.data, element, new_element);
+            validate::resolve_replaced_references(self, element, new_element);
+            if self.document.replaced > self.document.len() {
+                // The edit itself is done, so the items are only kept when
+                // the document no longer parses on its own.
+                let _ = self.reparse_document(self.text().into_owned());
+            }
+            return Ok(());
+        }
+        let mut text = String::with_capacity(len - range.len() + new_text.len());
+        self.document
+            .push_range(&self.input, 0..range.start, &mut text);
+        text.push_str(new_text);
+        self.document
+            .push_range(&self.input, range.end..len, &mut text);
+        self.reparse_document(text)
+    }
+
+    /// Parses `text` as the document text.
+    fn reparse_document(&mut self, text: String) -> Result<(), ParseError> {
+        let mut resolver = ReplayResolver {
+            entities: &self.document.options.entities,
+        };
+        let options = self.document.options.parse_options(&mut resolver);
+        let InfoSet {
+            input,
+            data,
+            document,
+        } = parse_with_options(&text, options)?.0;
+        self.input = Cow::Owned(input.into_owned());
+        self.data = data;
+        self.document = document;
+        Ok(())
+    }
+
+    /// Returns the elements whose content contains `range`, outermost first,
+    /// with the range of the document text they are at.
+    fn enclosing_elements(
+        &self,
+        range: &ops::Range<usize>,
+    ) -> Vec<(ElementInfoItem, ops::Range<usize>)> {
+        let repo = &self.data;
+        let enclosing = |element: ElementInfoItem| {
+            let span = element.source_span(repo)?;
+            self.document
+                .document_range(span.range())
+                .filter(|extent| extent.start < range.start && range.end < extent.end)
+                .map(|extent| (element, extent))
+        };
+        let mut elements = Vec::new();
+        let mut next = repo
+            .doc_info_item
+            .and_then(|doc_info_item| enclosing(doc_info_item.document_element(repo)));
+        while let Some((element, extent)) = next {
+            elements.push((element, extent));
+            next = ChildItems::of(self, element)
+                .elements
+                .into_iter()
+                .find_map(enclosing);
+        }
+        elements
+    }
+
+    /// Parses `element`, which is at `extent` of the document text, again
+    /// with the text at `range` replaced by `new_text`. Its new text is
+    /// appended to the input.
+    ///
+    /// Returns `None` if its text no longer forms a single element of the
+    /// same name.
+    fn reparse_element(
+        &mut self,
+        element: ElementInfoItem,
+        extent: ops::Range<usize>,
+        range: &ops::Range<usize>,
+        new_text: &str,
+    ) -> Option<ElementInfoItem> {
+        let mut text = String::with_capacity(extent.len() - range.len() + new_text.len());
+        self.document
+            .push_range(&self.input, extent.start..range.start, &mut text);
+        text.push_str(new_text);
+        self.document
+            .push_range(&self.input, range.end..extent.end, &mut text);
+        let start = self.input.len();
+        let new_span = start..start + text.len();
+        self.input.to_mut().push_str(&text);
+
+        let repo = &mut self.data;
+        let input = &*self.input;
+        let qualified_name = |repo: &InfoSetData, element: ElementInfoItem| {
+            let local_name = element.local_name(repo);
+            let start = element
+                .prefix(repo)
+                .map_or(local_name.0.start, |prefix| prefix.0.start);
+            &input[start..local_name.0.end]
+        };
+        let context = match element.parent(repo) {
+            ElementParentInfoItem::Doc(doc_info_item) => {
+                let base_uri = doc_info_item.base_uri(repo);
+                TreeContext::document(repo, doc_info_item, base_uri)
+            }
+            ElementParentInfoItem::Element(parent) => TreeContext {
+                parent: ElementParentInfoItem::Element(parent),
+                namespace_scope: parent.namespace_scope(repo),
+                base_uri: parent.base_uri(repo),
+            },
+        };
+
+        let reference_count = repo.dtd_declarations.external_entity_references.len();
+        let mut resolver = ReplayResolver {
+            entities: &self.document.options.entities,
+        };
+        let mut tokens = XmlTokenizer::from_fragment(input, new_span.clone()).peekable();
+        let parsed = parse_element_tree(
+            repo,
+            input,
+            &mut tokens,
+            context,
+            &mut self.document.options.parse_options(&mut resolver),
+            &mut InfoSetStatistics::default(),
+        );
+        let new_element = parsed
+            .ok()
+            .filter(|_| tokens.next().is_none())
+            .filter(|&new_element| {
+                let same_extent = new_element
+                    .source_span(repo)
+                    .map_or(false, |span| span.0 == new_span);
+                same_extent && qualified_name(repo, new_element) == qualified_name(repo, element)
+            });
+        let references = &mut repo.dtd_declarations.external_entity_references;
+        let new_references = references.split_off(reference_count);
+        match new_element {
+            Some(new_element) => {
+                // The references to externally declared entities recorded for
+                // the old element are dropped.
+                let document = &self.document;
+                references.retain(|span| {
+                    document
+                        .document_range(span.range())
+                        .map_or(false, |range| !extent.contains(&range.start))
+                });
+                references.extend(new_references);
+                self.document.replace(extent, new_span);
+                Some(new_element)
+            }
+            None => {
+                self.document.replaced += text.len();
+                self.input.to_mut().truncate(start);
+                None
+            }
+        }
+    }
+}
+
+/// Puts `new_element` in the place of `old_element`.
+fn replace_element(
+    repo: &mut InfoSetData,
+    old_element: ElementInfoItem,
+    new_element: ElementInfoItem,
+) {
+    let old_span = old_element.source_span(repo).map(|span| span.0);
+    let is_old = |repo: &InfoSetData, element: ElementInfoItem| {
+        element.source_span(repo).map(|span| span.0) == old_span
+    };
+    match old_element.parent(repo) {
+        ElementParentInfoItem::Doc(doc_info_item) => {
+            if is_old(repo, doc_info_item.document_element(repo)) {
+                doc_info_item.set_document_element(new_element, repo);
+            }
+            let position = doc_info_item.children(repo).iter().position(|child| {
+                matches!(*child, DocChildInfoItem::Element(element) if is_old(repo, element))
+            });
+            if let Some(position) = position {
+                doc_info_item.children_mut(repo)[position] = DocChildInfoItem::Element(new_element);
+            }
+        }
+        ElementParentInfoItem::Element(parent) => {
+            let position = parent.children(repo).iter().position(|child| {
+                matches!(*child, ElementChildInfoItem::Element(element) if is_old(repo, element))
+            });
+            if let Some(position) = position {
+                parent.children_mut(repo)[position] = ElementChildInfoItem::Element(new_element);
+            }
+        }
+    }
+}
@@ -0,0 +1,1036 @@
+//! JSON projections of an [`InfoSet`].
+//!
+//! [`JsonForm::JsonML`] renders the document element as
+//! [JsonML](http://www.jsonml.org/): `["tag", {"attr": "value"}, "text", ...]`.
+//! Comments, processing instructions and the DTD are dropped.
+//!
+//! [`JsonForm::Lossless`] keeps every info item and property, and can be read
+//! back with [`InfoSet::from_json`]. Properties with an unknown value are
+//! written as `{"unknown": true}`, and properties without a value as `null`.
+//! The references of IDREF, ENTITY and NOTATION-typed attributes are not
+//! written, as [`InfoSet::from_json`] recomputes them from the rest of the
+//! document.
+
+use super::builder::InfoSetBuilder;
+use super::display::attr_type_name;
+use super::parse::{parse_encoding_scheme, parse_version};
+use super::visit::{self, InfoSetVisitor};
+use super::*;
+use core::mem;
+use serde_json::{Map, Value};
+
+#[derive(Debug, Error)]
+pub enum JsonError {
+    #[error(transparent)]
+    Span(#[from] SpanError),
+    #[error("missing property {0:?}")]
+    MissingProperty(&'static str),
+    #[error("property {0:?} has an invalid value")]
+    InvalidProperty(&'static str),
+    #[error("unknown info item type {0:?}")]
+    UnknownItemType(String),
+    #[error("reference to undeclared notation {0:?}")]
+    UndeclaredNotation(String),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum JsonForm {
+    JsonML,
+    Lossless,
+}
+
+impl<'input> InfoSet<'input> {
+    pub fn to_json(&self, form: JsonForm) -> Result<Value, JsonError> {
+        let doc_info_item = match self.doc_info_item {
+            Some(doc_info_item) => doc_info_item,
+            None => return Ok(Value::Null),
+        };
+        match form {
+            JsonForm::JsonML => {
+                JsonMLWriter::new(self).element(doc_info_item.document_element(self))
+            }
+            JsonForm::Lossless => JsonWriter::new(self).doc(doc_info_item),
+        }
+    }
+
+    pub fn from_json(value: &Value) -> Result<InfoSet<'static>, JsonError> {
+        let mut reader = JsonReader {
+            builder: InfoSetBuilder::new(),
+            notations: Vec::new(),
+        };
+        let doc_info_item = reader.doc(value)?;
+        reader.builder.data.doc_info_item = Some(doc_info_item);
+        let mut infoset = reader.builder.finish();
+        validate::resolve_references(&mut infoset);
+        Ok(infoset)
+    }
+}
+
+fn unknown() -> Value {
+    let mut object = Map::new();
+    object.insert("unknown".to_owned(), Value::Bool(true));
+    Value::Object(object)
+}
+
+fn is_unknown(value: &Value) -> bool {
+    matches!(value.get("unknown"), Some(Value::Bool(true)))
+}
+
+/// Writes the JSON value of each visited item into `values`, keeping the
+/// first error in `result`.
+struct JsonWriter<'a> {
+    infoset: &'a InfoSet<'a>,
+    values: Vec<Value>,
+    result: Result<(), JsonError>,
+}
+
+impl<'a> JsonWriter<'a> {
+    fn new(infoset: &'a InfoSet<'a>) -> Self {
+        JsonWriter {
+            infoset,
+            values: Vec::new(),
+            result: Ok(()),
+        }
+    }
+
+    fn push(&mut self, value: Result<Value, JsonError>) {
+        match value {
+            Ok(value) => self.values.push(value),
+            Err(error) => {
+                if self.result.is_ok() {
+                    self.result = Err(error);
+                }
+            }
+        }
+    }
+
+    /// Returns the values of the items that `visit` visits.
+    fn values(&mut self, visit: impl FnOnce(&mut Self)) -> Result<Value, JsonError> {
+        let outer = mem::take(&mut self.values);
+        visit(self);
+        let values = mem::replace(&mut self.values, outer);
+        mem::replace(&mut self.result, Ok(()))?;
+        Ok(Value::Array(values))
+    }
+
+    fn text(&self, span: &Span) -> Result<Value, JsonError> {
+        Ok(Value::String(span.get(self.infoset)?.to_owned()))
+    }
+
+    fn cow_text(&self, span: &CowSpan) -> Result<Value, JsonError> {
+        Ok(Value::String(span.get_ref(self.infoset)?.to_owned()))
+    }
+
+    fn opt_text(&self, span: &Option<Span>) -> Result<Value, JsonError> {
+        match span {
+            Some(span) => self.text(span),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn opt_cow_text(&self, span: &Option<CowSpan>) -> Result<Value, JsonError> {
+        match span {
+            Some(span) => self.cow_text(span),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn uri(&self, uri: &Option<UriSpan>) -> Result<Value, JsonError> {
+        match uri {
+            Some(UriSpan(uri)) => self.cow_text(uri),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn doc(&mut self, doc_info_item: DocInfoItem) -> Result<Value, JsonError> {
+        let infoset = self.infoset;
+        let mut object = Map::new();
+        object.insert(
+            "version".to_owned(),
+            Value::String(doc_info_item.version(infoset).as_str().to_owned()),
+        );
+        object.insert(
+            "character_encoding_scheme".to_owned(),
+            match doc_info_item.character_encoding_scheme(infoset) {
+                Some(encoding) => Value::String(encoding.as_str().to_owned()),
+                None => Value::Null,
+            },
+        );
+        object.insert(
+            "standalone".to_owned(),
+            match doc_info_item.standalone(infoset) {
+                Some(standalone) => Value::Bool(standalone),
+                None => Value::Null,
+            },
+        );
+        object.insert(
+            "base_uri".to_owned(),
+            self.uri(&doc_info_item.base_uri(infoset))?,
+        );
+        object.insert(
+            "all_declarations_processed".to_owned(),
+            Value::Bool(doc_info_item.all_declarations_processed(infoset)),
+        );
+        let children = self.values(|this| {
+            for &child in doc_info_item.children(infoset) {
+                this.visit_doc_child(infoset, child);
+            }
+        })?;
+        object.insert("children".to_owned(), children);
+        let notations = match doc_info_item.notations(infoset) {
+            Some(notations) => self.values(|this| {
+                for &notation in notations {
+                    this.visit_notation(infoset, notation);
+                }
+            })?,
+            None => Value::Null,
+        };
+        object.insert("notations".to_owned(), notations);
+        let unparsed_entities = self.values(|this| {
+            for &ue in doc_info_item.unparsed_entities(infoset) {
+                this.visit_unparsed_entity(infoset, ue);
+            }
+        })?;
+        object.insert("unparsed_entities".to_owned(), unparsed_entities);
+        Ok(Value::Object(object))
+    }
+
+    fn element(&mut self, element: ElementInfoItem) -> Result<Value, JsonError> {
+        let infoset = self.infoset;
+        let mut object = Map::new();
+        object.insert("type".to_owned(), Value::String("element".to_owned()));
+        object.insert(
+            "namespace_name".to_owned(),
+            match element.namespace_name(infoset) {
+                Some(namespace_name) => Value::String(namespace_name.to_owned()),
+                None => Value::Null,
+            },
+        );
+        object.insert(
+            "local_name".to_owned(),
+            self.text(&element.local_name(infoset))?,
+        );
+        object.insert(
+            "prefix".to_owned(),
+            self.opt_text(&element.prefix(infoset))?,
+        );
+        object.insert("base_uri".to_owned(), self.uri(&element.base_uri(infoset))?);
+        let attributes = self.values(|this| {
+            for &attribute in element.attributes(infoset) {
+                this.visit_attribute(infoset, attribute);
+            }
+        })?;
+        object.insert("attributes".to_owned(), attributes);
+        let namespace_attributes = self.values(|this| {
+            for &attribute in element.namespace_attributes(infoset) {
+                this.visit_attribute(infoset, attribute);
+            }
+        })?;
+        object.insert("namespace_attributes".to_owned(), namespace_attributes);
+        let in_scope_namespaces =
+            self.values(|this| visit::walk_in_scope_namespaces(this, infoset, element))?;
+        object.insert("in_scope_namespaces".to_owned(), in_scope_namespaces);
+        let children = self.values(|this| {
+            for &child in element.children(infoset) {
+                this.visit_element_child(infoset, child);
+            }
+        })?;
+        object.insert("children".to_owned(), children);
+        Ok(Value::Object(object))
+    }
+
+    fn attribute(&self, attribute: AttributeInfoItem) -> Result<Value, JsonError> {
+        let infoset = self.infoset;
+        let mut object = Map::new();
+        object.insert(
+            "namespace_name".to_owned(),
+            self.opt_cow_text(&attribute.namespace_name(infoset))?,
+        );
+        object.insert(
+            "local_name".to_owned(),
+            self.text(&attribute.local_name(infoset))?,
+        );
+        object.insert(
+            "prefix".to_owned(),
+            self.opt_text(&attribute.prefix(infoset))?,
+        );
+        object.insert(
+            "normalized_value".to_owned(),
+            self.cow_text(&attribute.normalized_value(infoset))?,
+        );
+        object.insert(
+            "specified".to_owned(),
+            Value::Bool(matches!(
+                attribute.specified(infoset),
+                AttrSpecified::Specified
+            )),
+        );
+        object.insert(
+            "attribute_type".to_owned(),
+            match attribute.attribute_type_and_references(infoset) {
+                None => Value::Null,
+                Some(UnknownOr::Unknown) => unknown(),
+                Some(UnknownOr::Known(attr_type)) => {
+                    Value::String(attr_type_name(&attr_type).to_owned())
+                }
+            },
+        );
+        Ok(Value::Object(object))
+    }
+
+    fn namespace(&self, ns: NSInfoItem) -> Result<Value, JsonError> {
+        let mut object = Map::new();
+        object.insert(
+            "prefix".to_owned(),
+            self.opt_cow_text(&ns.prefix(self.infoset))?,
+        );
+        object.insert(
+            "namespace_name".to_owned(),
+            self.cow_text(&ns.namespace_name(self.infoset))?,
+        );
+        Ok(Value::Object(object))
+    }
+
+    fn char_group(&self, char_group: CharGroupInfoItem) -> Result<Value, JsonError> {
+        let mut object = Map::new();
+        object.insert("type".to_owned(), Value::String("characters".to_owned()));
+        object.insert(
+            "characters".to_owned(),
+            self.cow_text(&char_group.characters(self.infoset))?,
+        );
+        object.insert(
+            "element_content_whitespace".to_owned(),
+            match char_group.element_content_whitespace(self.infoset) {
+                UnknownOr::Unknown => unknown(),
+                UnknownOr::Known(None) => Value::Null,
+                UnknownOr::Known(Some(v)) => Value::Bool(v),
+            },
+        );
+        Ok(Value::Object(object))
+    }
+
+    fn comment(&self, comment: CommentInfoItem) -> Result<Value, JsonError> {
+        let mut object = Map::new();
+        object.insert("type".to_owned(), Value::String("comment".to_owned()));
+        object.insert(
+            "content".to_owned(),
+            self.cow_text(&comment.content(self.infoset))?,
+        );
+        Ok(Value::Object(object))
+    }
+
+    fn pi(&self, pi: PIInfoItem) -> Result<Value, JsonError> {
+        let infoset = self.infoset;
+        let mut object = Map::new();
+        object.insert("type".to_owned(), Value::String("pi".to_owned()));
+        object.insert("target".to_owned(), self.text(&pi.target(infoset))?);
+        object.insert(
+            "content".to_owned(),
+            self.opt_cow_text(&pi.content(infoset))?,
+        );
+        object.insert("base_uri".to_owned(), self.uri(&pi.base_uri(infoset))?);
+        object.insert(
+            "notation".to_owned(),
+            match pi.notation(infoset) {
+                None => Value::Null,
+                Some(UnknownOr::Unknown) => unknown(),
+                Some(UnknownOr::Known(notation)) => self.text(&notation.name(infoset))?,
+            },
+        );
+        Ok(Value::Object(object))
+    }
+
+    fn uer(&self, uer: UERInfoItem) -> Result<Value, JsonError> {
+        let infoset = self.infoset;
+        let mut object = Map::new();
+        object.insert(
+            "type".to_owned(),
+            Value::String("unexpanded_entity_reference".to_owned()),
+        );
+        object.insert("name".to_owned(), self.text(&uer.name(infoset))?);
+        object.insert(
+            "system_identifier".to_owned(),
+            match uer.system_identifier(infoset) {
+                UnknownOr::Unknown => unknown(),
+                UnknownOr::Known(span) => self.opt_text(&span)?,
+            },
+        );
+        object.insert(
+            "public_identifier".to_owned(),
+            match uer.public_identifier(infoset) {
+                UnknownOr::Unknown => unknown(),
+                UnknownOr::Known(span) => self.opt_text(&span)?,
+            },
+        );
+        object.insert(
+            "declaration_base_uri".to_owned(),
+            match uer.declaration_base_uri(infoset) {
+                UnknownOr::Unknown => unknown(),
+                UnknownOr::Known(uri) => self.uri(&uri)?,
+            },
+        );
+        Ok(Value::Object(object))
+    }
+
+    fn dtd(&mut self, dtd: DTDInfoItem) -> Result<Value, JsonError> {
+        let infoset = self.infoset;
+        let mut object = Map::new();
+        object.insert("type".to_owned(), Value::String("dtd".to_owned()));
+        object.insert(
+            "system_identifier".to_owned(),
+            self.opt_text(&dtd.system_identifier(infoset))?,
+        );
+        object.insert(
+            "public_identifier".to_owned(),
+            self.opt_text(&dtd.public_identifier(infoset))?,
+        );
+        let children = self.values(|this| visit::walk_dtd(this, infoset, dtd))?;
+        object.insert("children".to_owned(), children);
+        Ok(Value::Object(object))
+    }
+
+    fn notation(&self, notation: NotationInfoItem) -> Result<Value, JsonError> {
+        let infoset = self.infoset;
+        let mut object = Map::new();
+        object.insert("name".to_owned(), self.text(&notation.name(infoset))?);
+        object.insert(
+            "system_identifier".to_owned(),
+            self.opt_text(&notation.system_identifier(infoset))?,
+        );
+        object.insert(
+            "public_identifier".to_owned(),
+            self.opt_text(&notation.public_identifier(infoset))?,
+        );
+        object.insert(
+            "declaration_base_uri".to_owned(),
+            self.uri(&notation.declaration_base_uri(infoset))?,
+        );
+        Ok(Value::Object(object))
+    }
+
+    fn unparsed_entity(&self, ue: UEInfoItem) -> Result<Value, JsonError> {
+        let infoset = self.infoset;
+        let mut object = Map::new();
+        object.insert("name".to_owned(), self.text(&ue.name(infoset))?);
+        object.insert(
+            "system_identifier".to_owned(),
+            self.text(&ue.system_identifier(infoset))?,
+        );
+        object.insert(
+            "public_identifier".to_owned(),
+            self.opt_text(&ue.public_identifier(infoset))?,
+        );
+        object.insert(
+            "declaration_base_uri".to_owned(),
+            self.uri(&ue.declaration_base_uri(infoset))?,
+        );
+        object.insert(
+            "notation_name".to_owned(),
+            self.text(&ue.notation_name(infoset))?,
+        );
+        object.insert(
+            "notation".to_owned(),
+            match ue.notation(infoset) {
+                UnknownOr::Unknown => unknown(),
+                UnknownOr::Known(None) => Value::Null,
+                UnknownOr::Known(Some(notation)) => self.text(&notation.name(infoset))?,
+            },
+        );
+        Ok(Value::Object(object))
+    }
+}
+
+impl<'a> InfoSetVisitor<'a> for JsonWriter<'a> {
+    fn visit_doc(&mut self, _infoset: &'a InfoSet<'a>, item: DocInfoItem) {
+        let value = self.doc(item);
+        self.push(value);
+    }
+
+    fn visit_element(&mut self, _infoset: &'a InfoSet<'a>, item: ElementInfoItem) {
+        let value = self.element(item);
+        self.push(value);
+    }
+
+    fn visit_attribute(&mut self, _infoset: &'a InfoSet<'a>, item: AttributeInfoItem) {
+        let value = self.attribute(item);
+        self.push(value);
+    }
+
+    fn visit_namespace(&mut self, _infoset: &'a InfoSet<'a>, item: NSInfoItem) {
+        let value = self.namespace(item);
+        self.push(value);
+    }
+
+    fn visit_char_group(&mut self, _infoset: &'a InfoSet<'a>, item: CharGroupInfoItem) {
+        let value = self.char_group(item);
+        self.push(value);
+    }
+
+    fn visit_comment(&mut self, _infoset: &'a InfoSet<'a>, item: CommentInfoItem) {
+        let value = self.comment(item);
+        self.push(value);
+    }
+
+    fn visit_pi(&mut self, _infoset: &'a InfoSet<'a>, item: PIInfoItem) {
+        let value = self.pi(item);
+        self.push(value);
+    }
+
+    fn visit_uer(&mut self, _infoset: &'a InfoSet<'a>, item: UERInfoItem) {
+        let value = self.uer(item);
+        self.push(value);
+    }
+
+    fn visit_dtd(&mut self, _infoset: &'a InfoSet<'a>, item: DTDInfoItem) {
+        let value = self.dtd(item);
+        self.push(value);
+    }
+
+    fn visit_notation(&mut self, _infoset: &'a InfoSet<'a>, item: NotationInfoItem) {
+        let value = self.notation(item);
+        self.push(value);
+    }
+
+    fn visit_unparsed_entity(&mut self, _infoset: &'a InfoSet<'a>, item: UEInfoItem) {
+        let value = self.unparsed_entity(item);
+        self.push(value);
+    }
+}
+
+/// Writes the JsonML value of each visited element and character group into
+/// `values`, keeping the first error in `result`. Other items are dropped.
+struct JsonMLWriter<'a> {
+    infoset: &'a InfoSet<'a>,
+    values: Vec<Value>,
+    result: Result<(), JsonError>,
+}
+
+impl<'a> JsonMLWriter<'a> {
+    fn new(infoset: &'a InfoSet<'a>) -> Self {
+        JsonMLWriter {
+            infoset,
+            values: Vec::new(),
+            result: Ok(()),
+        }
+    }
+
+    fn qname(&self, prefix: &Option<Span>, local_name: &Span) -> Result<String, JsonError> {
+        let local_name = local_name.get(self.infoset)?;
+        Ok(match prefix {
+            Some(prefix) => format!("{}:{}", prefix.get(self.infoset)?, local_name),
+            None => local_name.to_owned(),
+        })
+    }
+
+    fn element(&mut self, element: ElementInfoItem) -> Result<Value, JsonError> {
+        let infoset = self.infoset;
+        let mut array = vec![Value::String(
+            self.qname(&element.prefix(infoset), &element.local_name(infoset))?,
+        )];
+        let mut attributes = Map::new();
+        for &attribute in element
+            .namespace_attributes(infoset)
+            .iter()
+            .chain(element.attributes(infoset))
+        {
+            attributes.insert(
+                self.qname(&attribute.prefix(infoset), &attribute.local_name(infoset))?,
+                Value::String(
+                    attribute
+                        .normalized_value(infoset)
+                        .get_ref(infoset)?
+                        .to_owned(),
+                ),
+            );
+        }
+        if !attributes.is_empty() {
+            array.push(Value::Object(attributes));
+        }
+        let outer = mem::take(&mut self.values);
+        for &child in element.children(infoset) {
+            self.visit_element_child(infoset, child);
+        }
+        array.append(&mut mem::replace(&mut self.values, outer));
+        mem::replace(&mut self.result, Ok(()))?;
+        Ok(Value::Array(array))
+    }
+
+    fn characters(&mut self, char_group: CharGroupInfoItem) -> Result<(), JsonError> {
+        let characters = char_group.characters(self.infoset);
+        let characters = characters.get_ref(self.infoset)?;
+        // Adjacent character groups are merged into one string.
+        if let Some(Value::String(last)) = self.values.last_mut() {
+            last.push_str(characters);
+        } else {
+            self.values.push(Value::String(characters.to_owned()));
+        }
+        Ok(())
+    }
+
+    fn keep_error(&mut self, result: Result<(), JsonError>) {
+        if let Err(error) = result {
+            if self.result.is_ok() {
+                self.result = Err(error);
+            }
+        }
+    }
+}
+
+impl<'a> InfoSetVisitor<'a> for JsonMLWriter<'a> {
+    fn visit_element(&mut self, _infoset: &'a InfoSet<'a>, item: ElementInfoItem) {
+        let result = self.element(item).map(|value| self.values.push(value));
+        self.keep_error(result);
+    }
+
+    fn visit_char_group(&mut self, _infoset: &'a InfoSet<'a>, item: CharGroupInfoItem) {
+        let result = self.characters(item);
+        self.keep_error(result);
+    }
+}
+
+fn parse_attr_type(name: &str) -> Option<AttrTypeAndReferences> {
+    Some(match name {
+        "ID" => AttrTypeAndReferences::ID(None),
+        "IDREF" => AttrTypeAndReferences::IDREF(None),
+        "IDREFS" => AttrTypeAndReferences::IDREFS(None),
+        "ENTITY" => AttrTypeAndReferences::ENTITY(None),
+        "ENTITIES" => AttrTypeAndReferences::ENTITIES(None),
+        "NMTOKEN" => AttrTypeAndReferences::NMTOKEN(None),
+        "NMTOKENS" => AttrTypeAndReferences::NMTOKENS(None),
+        "NOTATION" => AttrTypeAndReferences::NOTATION(None),
+        "CDATA" => AttrTypeAndReferences::CDATA(None),
+        "ENUMERATION" => AttrTypeAndReferences::ENUMERATION(None),
+        _ => return None,
+    })
+}
+
+fn property<'v>(value: &'v Value, key: &'static str) -> Result<&'v Value, JsonError> {
+    value.get(key).ok_or(JsonError::MissingProperty(key))
+}
+
+fn str_property<'v>(value: &'v Value, key: &'static str) -> Result<&'v str, JsonError> {
+    property(value, key)?
+        .as_str()
+        .ok_or(JsonError::InvalidProperty(key))
+}
+
+fn opt_str_property<'v>(value: &'v Value, key: &'static str) -> Result<Option<&'v str>, JsonError> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(JsonError::InvalidProperty(key)),
+    }
+}
+
+fn bool_property(value: &Value, key: &'static str) -> Result<bool, JsonError> {
+    property(value, key)?
+        .as_bool()
+        .ok_or(JsonError::InvalidProperty(key))
+}
+
+fn array_property<'v>(value: &'v Value, key: &'static str) -> Result<&'v [Value], JsonError> {
+    match value.get(key) {
+        None => Ok(&[]),
+        Some(Value::Array(array)) => Ok(array),
+        Some(_) => Err(JsonError::InvalidProperty(key)),
+    }
+}
+
+struct JsonReader {
+    builder: InfoSetBuilder,
+    notations: Vec<(String, NotationInfoItem)>,
+}
+
+impl JsonReader {
+    fn span(&mut self, s: &str) -> Span {
+        self.builder.span(s)
+    }
+
+    fn opt_span(&mut self, s: Option<&str>) -> Option<Span> {
+        s.map(|s| self.builder.span(s))
+    }
+
+    fn uri(&mut self, s: Option<&str>) -> Option<UriSpan> {
+        s.map(|s| UriSpan(CowSpan::Borrowed(self.builder.span(s))))
+    }
+
+    fn notation_ref(&self, name: &str) -> Result<NotationInfoItem, JsonError> {
+        self.notations
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|&(_, notation)| notation)
+            .ok_or_else(|| JsonError::UndeclaredNotation(name.to_owned()))
+    }
+
+    fn doc(&mut self, value: &Value) -> Result<DocInfoItem, JsonError> {
+        let doc_info_item = DocInfoItem::new_not_yet_parsed(&mut self.builder.data);
+
+        // Notations come first, so that items referring to them can be linked.
+        let notations = match value.get("notations") {
+            None | Some(Value::Null) => None,
+            Some(Value::Array(notations)) => {
+                let mut items = Vec::new();
+                for notation in notations {
+                    items.push(self.notation(notation)?);
+                }
+                Some(items)
+            }
+            Some(_) => return Err(JsonError::InvalidProperty("notations")),
+        };
+        let mut unparsed_entities = Vec::new();
+        for ue in array_property(value, "unparsed_entities")? {
+            unparsed_entities.push(self.unparsed_entity(ue)?);
+        }
+
+        let mut children = Vec::new();
+        let mut document_element = None;
+        for child in array_property(value, "children")? {
+            let child = match str_property(child, "type")? {
+                "element" => {
+                    let element = self.element(child, ElementParentInfoItem::Doc(doc_info_item))?;
+                    document_element = Some(element);
+                    DocChildInfoItem::Element(element)
+                }
+                "pi" => DocChildInfoItem::PI(self.pi(child, PIParentInfoItem::Doc(doc_info_item))?),
+                "comment" => DocChildInfoItem::Comment(
+                    self.comment(child, CommentParentInfoItem::Doc(doc_info_item))?,
+                ),
+                "dtd" => DocChildInfoItem::DTD(self.dtd(child, doc_info_item)?),
+                other => return Err(JsonError::UnknownItemType(other.to_owned())),
+            };
+            children.push(child);
+        }
+        let document_element = document_element.ok_or(JsonError::MissingProperty("children"))?;
+
+        let version = parse_version(str_property(value, "version")?);
+        let character_encoding_scheme =
+            opt_str_property(value, "character_encoding_scheme")?.map(parse_encoding_scheme);
+        let standalone = match value.get("standalone") {
+            None | Some(Value::Null) => None,
+            Some(Value::Bool(standalone)) => Some(*standalone),
+            Some(_) => return Err(JsonError::InvalidProperty("standalone")),
+        };
+        let base_uri = opt_str_property(value, "base_uri")?;
+        let base_uri = self.uri(base_uri);
+        let all_declarations_processed = bool_property(value, "all_declarations_processed")?;
+
+        doc_info_item.transition_to_parsed_from_not_yet_parsed(
+            repo::keyed!(version: version),
+            repo::keyed!(character_encoding_scheme: character_encoding_scheme),
+            repo::keyed!(standalone: standalone),
+            repo::keyed!(document_element: document_element),
+            repo::keyed!(children: children),
+            repo::keyed!(notations: notations),
+            repo::keyed!(unparsed_entities: unparsed_entities),
+            repo::keyed!(base_uri: base_uri),
+            repo::keyed!(all_declarations_processed: all_declarations_processed),
+            &mut self.builder.data,
+        );
+        Ok(doc_info_item)
+    }
+
+    /// Builds the scope of the in-scope namespaces of an element, sharing the
+    /// namespaces it has in common with its parent.
+    fn namespace_scope(
+        &mut self,
+        value: &Value,
+        parent: ElementParentInfoItem,
+    ) -> Result<NamespaceScope, JsonError> {
+        let mut namespaces = Vec::new();
+        for ns in array_property(value, "in_scope_namespaces")? {
+            let prefix = opt_str_property(ns, "prefix")?;
+            let namespace_name = str_property(ns, "namespace_name")?;
+            namespaces.push((prefix, namespace_name));
+        }
+        let data = &mut self.builder.data;
+        let atoms: Vec<_> = namespaces
+            .iter()
+            .map(|&(prefix, namespace_name)| {
+                let prefix = prefix.map(|prefix| data.atoms.intern(prefix));
+                (prefix, data.atoms.intern(namespace_name))
+            })
+            .collect();
+        let (parent, shared, missing) = match parent {
+            ElementParentInfoItem::Element(parent) => {
+                let parent = parent.namespace_scope(data);
+                let (shared, missing) = data.inherited_namespaces(parent, &atoms);
+                (Some(parent), shared, missing)
+            }
+            ElementParentInfoItem::Doc(_) => (None, 0, Vec::new()),
+        };
+        let mut bindings: Vec<_> = missing.into_iter().map(|prefix| (prefix, None)).collect();
+        for (&(prefix, namespace_name), &(prefix_atom, namespace_atom)) in
+            namespaces.iter().zip(&atoms).skip(shared)
+        {
+            let prefix = prefix.map(|prefix| self.builder.cow_span(prefix));
+            let namespace_name = self.builder.cow_span(namespace_name);
+            let ns_info_item = NSInfoItem::new(
+                prefix,
+                namespace_name,
+                namespace_atom,
+                &mut self.builder.data,
+            );
+            bindings.push((prefix_atom, Some(ns_info_item)));
+        }
+        Ok(match parent {
+            Some(parent) if bindings.is_empty() => parent,
+            _ => self.builder.data.push_namespace_bindings(parent, bindings),
+        })
+    }
+
+    fn element(
+        &mut self,
+        value: &Value,
+        parent: ElementParentInfoItem,
+    ) -> Result<ElementInfoItem, JsonError> {
+        let namespace_str = opt_str_property(value, "namespace_name")?;
+        let local_str = str_property(value, "local_name")?;
+        let expanded_name = self
+            .builder
+            .data
+            .atoms
+            .expanded_name(namespace_str, local_str);
+        let local_name = self.span(local_str);
+        let prefix = self.opt_span(opt_str_property(value, "prefix")?);
+        let base_uri = self.uri(opt_str_property(value, "base_uri")?);
+        let namespace_scope = self.namespace_scope(value, parent)?;
+        let element = ElementInfoItem::new(
+            local_name,
+            prefix,
+            expanded_name,
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            namespace_scope,
+            base_uri,
+            None,
+            parent,
+            &mut self.builder.data,
+        );
+
+        let mut attributes = Vec::new();
+        for attribute in array_property(value, "attributes")? {
+            attributes.push(self.attribute(attribute, element)?);
+        }
+        element.set_attributes(attributes, &mut self.builder.data);
+        let mut namespace_attributes = Vec::new();
+        for attribute in array_property(value, "namespace_attributes")? {
+            namespace_attributes.push(self.attribute(attribute, element)?);
+        }
+        element.set_namespace_attributes(namespace_attributes, &mut self.builder.data);
+
+        let mut children = Vec::new();
+        for child in array_property(value, "children")? {
+            let child = match str_property(child, "type")? {
+                "element" => ElementChildInfoItem::Element(
+                    self.element(child, ElementParentInfoItem::Element(element))?,
+                ),
+                "pi" => {
+                    ElementChildInfoItem::PI(self.pi(child, PIParentInfoItem::Element(element))?)
+                }
+                "unexpanded_entity_reference" => {
+                    ElementChildInfoItem::UER(self.uer(child, element)?)
+                }
+                "characters" => ElementChildInfoItem::CharGroup(self.char_group(child, element)?),
+                "comment" => ElementChildInfoItem::Comment(
+                    self.comment(child, CommentParentInfoItem::Element(element))?,
+                ),
+                other => return Err(JsonError::UnknownItemType(other.to_owned())),
+            };
+            children.push(child);
+        }
+        element.set_children(children, &mut self.builder.data);
+        Ok(element)
+    }
+
+    fn attribute(
+        &mut self,
+        value: &Value,
+        owner_element: ElementInfoItem,
+    ) -> Result<AttributeInfoItem, JsonError> {
+        let namespace_str = opt_str_property(value, "namespace_name")?;
+        let local_str = str_property(value, "local_name")?;
+        let expanded_name = self
+            .builder
+            .data
+            .atoms
+            .expanded_name(namespace_str, local_str);
+        let namespace_name =
+            namespace_str.map(|namespace_name| self.builder.cow_span(namespace_name));
+        let local_name = self.span(local_str);
+        let prefix = self.opt_span(opt_str_property(value, "prefix")?);
+        let normalized_value = self
+            .builder
+            .cow_span(str_property(value, "normalized_value")?);
+        let specified = if bool_property(value, "specified")? {
+            AttrSpecified::Specified
+        } else {
+            AttrSpecified::DefaultedFromDTD
+        };
+        let attribute_type = match value.get("attribute_type") {
+            None | Some(Value::Null) => None,
+            Some(v) if is_unknown(v) => Some(UnknownOr::Unknown),
+            Some(Value::String(name)) => Some(UnknownOr::Known(
+                parse_attr_type(name).ok_or(JsonError::InvalidProperty("attribute_type"))?,
+            )),
+            Some(_) => return Err(JsonError::InvalidProperty("attribute_type")),
+        };
+        Ok(AttributeInfoItem::new(
+            namespace_name,
+            local_name,
+            prefix,
+            expanded_name,
+            normalized_value,
+            specified,
+            attribute_type,
+            owner_element,
+            &mut self.builder.data,
+        ))
+    }
+
+    fn char_group(
+        &mut self,
+        value: &Value,
+        parent: ElementInfoItem,
+    ) -> Result<CharGroupInfoItem, JsonError> {
+        let characters = self.builder.cow_span(str_property(value, "characters")?);
+        let element_content_whitespace = match value.get("element_content_whitespace") {
+            None | Some(Value::Null) => UnknownOr::Known(None),
+            Some(v) if is_unknown(v) => UnknownOr::Unknown,
+            Some(Value::Bool(v)) => UnknownOr::Known(Some(*v)),
+            Some(_) => return Err(JsonError::InvalidProperty("element_content_whitespace")),
+        };
+        Ok(CharGroupInfoItem::new(
+            characters,
+            element_content_whitespace,
+            parent,
+            &mut self.builder.data,
+        ))
+    }
+
+    fn comment(
+        &mut self,
+        value: &Value,
+        parent: CommentParentInfoItem,
+    ) -> Result<CommentInfoItem, JsonError> {
+        let content = self.builder.cow_span(str_property(value, "content")?);
+        Ok(CommentInfoItem::new(
+            content,
+            parent,
+            &mut self.builder.data,
+        ))
+    }
+
+    fn pi(&mut self, value: &Value, parent: PIParentInfoItem) -> Result<PIInfoItem, JsonError> {
+        let target = self.span(str_property(value, "target")?);
+        let content = opt_str_property(value, "content")?.map(|s| self.builder.cow_span(s));
+        let base_uri = self.uri(opt_str_property(value, "base_uri")?);
+        let notation = match value.get("notation") {
+            None | Some(Value::Null) => None,
+            Some(v) if is_unknown(v) => Some(UnknownOr::Unknown),
+            Some(Value::String(name)) => Some(UnknownOr::Known(self.notation_ref(name)?)),
+            Some(_) => return Err(JsonError::InvalidProperty("notation")),
+        };
+        Ok(PIInfoItem::new(
+            target,
+            content,
+            base_uri,
+            notation,
+            parent,
+            &mut self.builder.data,
+        ))
+    }
+
+    fn unknown_or_str<'v>(
+        value: &'v Value,
+        key: &'static str,
+    ) -> Result<UnknownOr<Option<&'v str>>, JsonError> {
+        match value.get(key) {
+            Some(v) if is_unknown(v) => Ok(UnknownOr::Unknown),
+            _ => Ok(UnknownOr::Known(opt_str_property(value, key)?)),
+        }
+    }
+
+    fn uer(&mut self, value: &Value, parent: ElementInfoItem) -> Result<UERInfoItem, JsonError> {
+        let name = self.span(str_property(value, "name")?);
+        let system_identifier = match Self::unknown_or_str(value, "system_identifier")? {
+            UnknownOr::Unknown => UnknownOr::Unknown,
+            UnknownOr::Known(s) => UnknownOr::Known(self.opt_span(s)),
+        };
+        let public_identifier = match Self::unknown_or_str(value, "public_identifier")? {
+            UnknownOr::Unknown => UnknownOr::Unknown,
+            UnknownOr::Known(s) => UnknownOr::Known(self.opt_span(s)),
+        };
+        let declaration_base_uri = match Self::unknown_or_str(value, "declaration_base_uri")? {
+            UnknownOr::Unknown => UnknownOr::Unknown,
+            UnknownOr::Known(s) => UnknownOr::Known(self.uri(s)),
+        };
+        Ok(UERInfoItem::new(
+            name,
+            system_identifier,
+            public_identifier,
+            declaration_base_uri,
+            parent,
+            &mut self.builder.data,
+        ))
+    }
+
+    fn dtd(&mut self, value: &Value, parent: DocInfoItem) -> Result<DTDInfoItem, JsonError> {
+        let system_identifier = self.opt_span(opt_str_property(value, "system_identifier")?);
+        let public_identifier = self.opt_span(opt_str_property(value, "public_identifier")?);
+        let dtd = DTDInfoItem::new(
+            system_identifier,
+            public_identifier,
+            Vec::new(),
+            parent,
+            &mut self.builder.data,
+        );
+        let mut children = Vec::new();
+        for pi in array_property(value, "children")? {
+            children.push(self.pi(pi, PIParentInfoItem::DTD(dtd))?);
+        }
+        dtd.set_children(children, &mut self.builder.data);
+        Ok(dtd)
+    }
+
+    fn notation(&mut self, value: &Value) -> Result<NotationInfoItem, JsonError> {
+        let name = str_property(value, "name")?;
+        let name_span = self.span(name);
+        let system_identifier = self.opt_span(opt_str_property(value, "system_identifier")?);
+        let public_identifier = self.opt_span(opt_str_property(value, "public_identifier")?);
+        let declaration_base_uri = self.uri(opt_str_property(value, "declaration_base_uri")?);
+        let notation = NotationInfoItem::new(
+            name_span,
+            system_identifier,
+            public_identifier,
+            declaration_base_uri,
+            &mut self.builder.data,
+        );
+        self.notations.push((name.to_owned(), notation));
+        Ok(notation)
+    }
+
+    fn unparsed_entity(&mut self, value: &Value) -> Result<UEInfoItem, JsonError> {
+        let name = self.span(str_property(value, "name")?);
+        let system_identifier = self.span(str_property(value, "system_identifier")?);
+        let public_identifier = self.opt_span(opt_str_property(value, "public_identifier")?);
+        let declaration_base_uri = self.uri(opt_str_property(value, "declaration_base_uri")?);
+        let notation_name_str = str_property(value, "notation_name")?;
+        let notation_name = self.span(notation_name_str);
+        let notation = match value.get("notation") {
+            Some(v) if is_unknown(v) => UnknownOr::Unknown,
+            None | Some(Value::Null) => UnknownOr::Known(None),
+            Some(Value::String(name)) => UnknownOr::Known(Some(self.notation_ref(name)?)),
+            Some(_) => return Err(JsonError::InvalidProperty("notation")),
+        };
+        Ok(UEInfoItem::new(
+            name,
+            system_identifier,
+            public_identifier,
+            declaration_base_uri,
+            notation_name,
+            notation,
+            &mut self.builder.data,
+        ))
+    }
+}
@@ -1,12 +1,46 @@
 extern crate alloc;
 
 use alloc::borrow::Cow;
+use alloc::collections::{BTreeMap, BTreeSet};
 use core::ops;
 use thiserror::Error;
 
 #[macro_use]
 pub mod utils;
+mod atom;
+pub mod catalog;
 pub mod parse;
+pub mod resolver;
+pub mod uri;
+pub mod validate;
+pub mod visit;
+pub mod xinclude;
+
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "serde")]
+pub mod ser;
+
+#[cfg(feature = "json")]
+pub mod json;
+
+#[cfg(any(feature = "serde", feature = "json"))]
+mod builder;
+
+mod dtd;
+
+mod edit;
+
+mod display;
+
+mod scope;
+
+pub use atom::{Atom, AtomTable, ExpandedName};
+pub use display::InfoItemDisplay;
+pub use scope::NamespaceScope;
+
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+pub const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
 
 #[repo::repo]
 #[member(
@@ -25,11 +59,24 @@ pub mod parse;
 #[derive(Default)]
 pub struct InfoSetData {
     pub doc_info_item: Option<DocInfoItem>,
+    pub(crate) dtd_declarations: dtd::DtdDeclarations,
+    pub(crate) id_index: BTreeMap<String, ElementInfoItem>,
+    pub(crate) recovered_elements: Vec<ElementInfoItem>,
+    pub(crate) atoms: AtomTable,
+    pub(crate) namespace_scopes: scope::NamespaceScopes,
+}
+
+impl InfoSetData {
+    /// The interned names and namespace names of the items.
+    pub fn atoms(&self) -> &AtomTable {
+        &self.atoms
+    }
 }
 
 pub struct InfoSet<'input> {
     input: Cow<'input, str>,
     data: InfoSetData,
+    document: edit::DocumentText,
 }
 
 impl<'input> ops::Deref for InfoSet<'input> {
@@ -46,9 +93,46 @@ impl<'input> ops::DerefMut for InfoSet<'input> {
     }
 }
 
-#[derive(Default)]
+impl<'input> InfoSet<'input> {
+    pub fn debug_item(&self, item: impl Into<InfoItem>) -> InfoItemDisplay<'_, 'input> {
+        InfoItemDisplay {
+            infoset: self,
+            item: item.into(),
+        }
+    }
+}
+
+#[derive(Default, Debug)]
 pub struct InfoSetStatistics {
+    pub element_count: usize,
+    pub attribute_count: usize,
+    /// Number of character info item groups.
+    pub text_count: usize,
+    pub comment_count: usize,
+    pub pi_count: usize,
+    /// Nesting depth of the deepest element, the document element being at
+    /// depth 1.
+    pub max_depth: usize,
+    /// Length in bytes of the character data of all elements.
+    pub text_bytes: usize,
+    /// Number of distinct namespace names of elements and attributes.
+    pub namespace_count: usize,
+    /// Prefixes used in the names of elements and attributes.
+    pub prefixes: BTreeSet<String>,
+    /// Number of references to entities other than the predefined ones that
+    /// were replaced by their replacement text.
+    pub entity_expansions: usize,
+    pub has_dtd: bool,
+    pub has_cdata: bool,
+    /// Whether the document references entities other than the predefined
+    /// ones.
     pub has_nonstandard_entity_reference: bool,
+    /// Whether the document is declared standalone but depends on external
+    /// markup declarations.
+    pub has_standalone_violation: bool,
+    pub validity_errors: Vec<validate::ValidityError>,
+    /// Errors recovered from, when parsing with [`parse::ParseOptions::recover`].
+    pub recovered_errors: Vec<parse::RecoveredError>,
 }
 
 #[derive(Copy, Clone)]
@@ -98,10 +182,66 @@ pub enum CommentParentInfoItem {
     Element(ElementInfoItem),
 }
 
-#[derive(Clone)]
+#[derive(Copy, Clone)]
+pub enum InfoItem {
+    Doc(DocInfoItem),
+    Element(ElementInfoItem),
+    Attribute(AttributeInfoItem),
+    PI(PIInfoItem),
+    UER(UERInfoItem),
+    CharGroup(CharGroupInfoItem),
+    Comment(CommentInfoItem),
+    DTD(DTDInfoItem),
+    UE(UEInfoItem),
+    Notation(NotationInfoItem),
+    NS(NSInfoItem),
+}
+
+impl_enum_from_variant!(InfoItem, Doc, DocInfoItem);
+impl_enum_from_variant!(InfoItem, Element, ElementInfoItem);
+impl_enum_from_variant!(InfoItem, Attribute, AttributeInfoItem);
+impl_enum_from_variant!(InfoItem, PI, PIInfoItem);
+impl_enum_from_variant!(InfoItem, UER, UERInfoItem);
+impl_enum_from_variant!(InfoItem, CharGroup, CharGroupInfoItem);
+impl_enum_from_variant!(InfoItem, Comment, CommentInfoItem);
+impl_enum_from_variant!(InfoItem, DTD, DTDInfoItem);
+impl_enum_from_variant!(InfoItem, UE, UEInfoItem);
+impl_enum_from_variant!(InfoItem, Notation, NotationInfoItem);
+impl_enum_from_variant!(InfoItem, NS, NSInfoItem);
+
+impl From<DocChildInfoItem> for InfoItem {
+    fn from(v: DocChildInfoItem) -> InfoItem {
+        match v {
+            DocChildInfoItem::Element(v) => InfoItem::Element(v),
+            DocChildInfoItem::PI(v) => InfoItem::PI(v),
+            DocChildInfoItem::Comment(v) => InfoItem::Comment(v),
+            DocChildInfoItem::DTD(v) => InfoItem::DTD(v),
+        }
+    }
+}
+
+impl From<ElementChildInfoItem> for InfoItem {
+    fn from(v: ElementChildInfoItem) -> InfoItem {
+        match v {
+            ElementChildInfoItem::Element(v) => InfoItem::Element(v),
+            ElementChildInfoItem::PI(v) => InfoItem::PI(v),
+            ElementChildInfoItem::UER(v) => InfoItem::UER(v),
+            ElementChildInfoItem::CharGroup(v) => InfoItem::CharGroup(v),
+            ElementChildInfoItem::Comment(v) => InfoItem::Comment(v),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Span(ops::Range<usize>);
 
 impl Span {
+    /// The byte range of the span in the text held by the info set, which
+    /// is the input until it is edited; see [`InfoSet::document_range`].
+    pub fn range(&self) -> ops::Range<usize> {
+        self.0.clone()
+    }
+
     pub fn get<'a>(&self, infoset: &'a InfoSet<'_>) -> Result<&'a str, SpanError> {
         infoset.input.get(self.0.clone()).ok_or(SpanError)
     }
@@ -111,29 +251,68 @@ impl Span {
 #[error("Invalid string span in XML info set")]
 pub struct SpanError;
 
-#[derive(Clone)]
-pub struct UriSpan(Span);
+/// An absolute or relative URI reference.
+///
+/// Base URIs are usually computed by resolving `xml:base` attributes, so the
+/// value is not necessarily a slice of the input.
+#[derive(Clone, Debug)]
+pub struct UriSpan(CowSpan);
 
-#[derive(Clone)]
+impl UriSpan {
+    pub fn new(uri: CowSpan) -> Self {
+        UriSpan(uri)
+    }
+
+    pub fn as_cow_span(&self) -> &CowSpan {
+        &self.0
+    }
+
+    pub fn get_ref<'a>(&'a self, infoset: &'a InfoSet<'_>) -> Result<&'a str, SpanError> {
+        self.0.get_ref(infoset)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum Never {}
 
-#[derive(Clone)]
+/// The version of a document.
+#[derive(Clone, Debug)]
 pub enum Version {
     Version1_0,
     Version1_1,
     Other(String),
 }
 
-#[derive(Clone)]
+impl Version {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Version::Version1_0 => "1.0",
+            Version::Version1_1 => "1.1",
+            Version::Other(v) => v,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum EncodingScheme {
     Utf8,
     Other(String),
 }
 
-#[derive(Clone)]
+impl EncodingScheme {
+    pub fn as_str(&self) -> &str {
+        match self {
+            EncodingScheme::Utf8 => "UTF-8",
+            EncodingScheme::Other(v) => v,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum CowSpan {
     Borrowed(Span),
     Owned(String),
+    Static(&'static str),
 }
 
 impl CowSpan {
@@ -141,11 +320,12 @@ impl CowSpan {
         match self {
             CowSpan::Borrowed(span) => span.get(infoset),
             CowSpan::Owned(str) => Ok(str.as_ref()),
+            CowSpan::Static(str) => Ok(str),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum UnknownOr<T> {
     Unknown,
     Known(T),
@@ -180,6 +360,12 @@ pub enum DocInfoItem {
 }
 
 impl DocInfoItem {
+    /// Whether the document has been parsed, so that its properties can be
+    /// read.
+    pub fn is_parsed(self, __repo: &InfoSetData) -> bool {
+        all_declarations_processed_of_parsed_doc_info_item(self, __repo).map_or(false, |_| true)
+    }
+
     pub fn version(self, __repo: &InfoSetData) -> Version {
         version_of_parsed_doc_info_item(self, __repo).unwrap()
     }
@@ -271,22 +457,59 @@ impl DocInfoItem {
 
 #[repo::entity(repo = InfoSetData)]
 pub struct ElementInfoItem {
-    pub namespace_name: Option<Span>,
     pub local_name: Span,
     pub prefix: Option<Span>,
+    pub expanded_name: ExpandedName,
     #[by_ref]
     pub children: Vec<ElementChildInfoItem>,
     #[by_ref]
     pub attributes: Vec<AttributeInfoItem>,
     #[by_ref]
     pub namespace_attributes: Vec<AttributeInfoItem>,
-    #[by_ref]
-    pub in_scope_namespaces: Vec<NSInfoItem>,
+    pub namespace_scope: NamespaceScope,
     pub base_uri: Option<UriSpan>,
+    /// The span of the element in the input, from the start of its start tag
+    /// to the end of its end tag, if it was parsed from the input.
+    pub source_span: Option<Span>,
     pub parent: ElementParentInfoItem,
 }
 
-#[derive(Clone, Copy)]
+impl ElementInfoItem {
+    /// The namespace name of the element, read from its expanded name.
+    pub fn namespace_name(self, repo: &InfoSetData) -> Option<&str> {
+        let namespace_name = self.expanded_name(repo).namespace_name?;
+        Some(repo.atoms.resolve(namespace_name))
+    }
+
+    /// Returns whether the element has the given expanded name.
+    pub(crate) fn has_name(
+        self,
+        infoset: &InfoSet<'_>,
+        namespace_name: Option<&str>,
+        local_name: &str,
+    ) -> bool {
+        infoset.atoms.lookup(namespace_name, local_name) == Some(self.expanded_name(infoset))
+    }
+
+    /// Returns the normalized value of the attribute with the given expanded
+    /// name.
+    pub(crate) fn attribute_value(
+        self,
+        infoset: &InfoSet<'_>,
+        namespace_name: Option<&str>,
+        local_name: &str,
+    ) -> Option<String> {
+        let expanded_name = infoset.atoms.lookup(namespace_name, local_name)?;
+        let attribute = self
+            .attributes(infoset)
+            .iter()
+            .find(|attribute| attribute.expanded_name(infoset) == expanded_name)?;
+        let value = attribute.normalized_value(infoset);
+        Some(parse::input_str(&infoset.input, &value).to_owned())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
 pub enum AttrSpecified {
     Specified,
     DefaultedFromDTD,
@@ -296,21 +519,22 @@ pub enum AttrSpecified {
 pub enum AttrTypeAndReferences {
     ID(Option<Never>),
     IDREF(Option<Vec<AttrReferenceInfoItem>>),
-    IDREFS,
-    ENTITY,
-    ENTITIES,
+    IDREFS(Option<Vec<AttrReferenceInfoItem>>),
+    ENTITY(Option<Vec<AttrReferenceInfoItem>>),
+    ENTITIES(Option<Vec<AttrReferenceInfoItem>>),
     NMTOKEN(Option<Never>),
     NMTOKENS(Option<Never>),
-    NOTATION,
+    NOTATION(Option<Vec<AttrReferenceInfoItem>>),
     CDATA(Option<Never>),
     ENUMERATION(Option<Never>),
 }
 
 #[repo::entity(repo = InfoSetData)]
 pub struct AttributeInfoItem {
-    pub namespace_name: Option<Span>,
+    pub namespace_name: Option<CowSpan>,
     pub local_name: Span,
     pub prefix: Option<Span>,
+    pub expanded_name: ExpandedName,
     pub normalized_value: CowSpan,
     pub specified: AttrSpecified,
     pub attribute_type_and_references: Option<UnknownOr<AttrTypeAndReferences>>,
@@ -320,7 +544,7 @@ pub struct AttributeInfoItem {
 #[repo::entity(repo = InfoSetData)]
 pub struct PIInfoItem {
     pub target: Span,
-    pub content: Option<Span>,
+    pub content: Option<CowSpan>,
     pub base_uri: Option<UriSpan>,
     pub notation: Option<UnknownOr<NotationInfoItem>>,
     pub parent: PIParentInfoItem,
@@ -344,7 +568,7 @@ pub struct CharGroupInfoItem {
 
 #[repo::entity(repo = InfoSetData)]
 pub struct CommentInfoItem {
-    pub content: Span,
+    pub content: CowSpan,
     pub parent: CommentParentInfoItem,
 }
 
@@ -362,7 +586,7 @@ pub struct UEInfoItem {
     pub name: Span,
     pub system_identifier: Span,
     pub public_identifier: Option<Span>,
-    pub declaration_base_uri: UriSpan,
+    pub declaration_base_uri: Option<UriSpan>,
     pub notation_name: Span,
     pub notation: UnknownOr<Option<NotationInfoItem>>,
 }
@@ -372,11 +596,14 @@ pub struct NotationInfoItem {
     pub name: Span,
     pub system_identifier: Option<Span>,
     pub public_identifier: Option<Span>,
-    pub declaration_base_uri: UriSpan,
+    pub declaration_base_uri: Option<UriSpan>,
 }
 
 #[repo::entity(repo = InfoSetData)]
 pub struct NSInfoItem {
-    pub prefix: Option<Span>,
-    pub namespace_name: Span,
+    /// The prefix and namespace name are not necessarily in the input, as
+    /// for the implicitly bound `xml` prefix.
+    pub prefix: Option<CowSpan>,
+    pub namespace_name: CowSpan,
+    pub namespace_atom: Atom,
 }
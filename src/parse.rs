@@ -1,5 +1,6 @@
 #![allow(unused_variables, unused_assignments, unused_mut)]
 
+use super::visit::{InfoSetVisitor, InfoSetVisitorMut};
 use super::*;
 use core::iter::Peekable;
 use thiserror::Error;
@@ -43,6 +44,109 @@ pub enum ParseError {
     DuplicateNSAttribute,
     #[error("duplicate root element")]
     DuplicateRootElement,
+    #[error("duplicate attribute")]
+    DuplicateAttribute,
+    #[error("invalid ns attribute")]
+    InvalidNSAttribute,
+    #[error("undeclared ns prefix")]
+    UndeclaredNSPrefix,
+    #[error("malformed markup declaration")]
+    MalformedDeclaration,
+    #[error("malformed reference")]
+    MalformedReference,
+    #[error("invalid character reference")]
+    InvalidCharacterReference,
+    #[error("undefined entity")]
+    UndefinedEntity,
+    #[error("recursive entity reference")]
+    RecursiveEntityReference,
+    #[error("external entity reference in attribute value")]
+    ExternalEntityInAttributeValue,
+    #[error("failed to resolve external entity: {0}")]
+    EntityResolution(#[from] resolver::ResolveError),
+    #[error("external entity is not valid UTF-8")]
+    InvalidExternalEntityEncoding,
+    #[error("unsupported XML version {0:?}")]
+    UnsupportedVersion(String),
+    #[error("edit range is not a range of the document text")]
+    InvalidEditRange,
+}
+
+/// An error that the parser recovered from, with the span of the input it
+/// was found at.
+#[derive(Debug, Error)]
+#[error("{error}")]
+pub struct RecoveredError {
+    pub error: ParseError,
+    pub span: Span,
+}
+
+/// How a document whose version is neither 1.0 nor 1.1 is handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownVersionPolicy {
+    /// Process the document with the XML 1.0 rules, as XML 1.0 §2.8 suggests
+    /// for other 1.x versions.
+    #[default]
+    TreatAsXml10,
+    /// Fail with [`ParseError::UnsupportedVersion`].
+    Reject,
+}
+
+/// Options controlling how a document is parsed.
+///
+/// Nothing outside the document entity is read unless an entity resolver is
+/// provided and loading is enabled.
+#[derive(Default)]
+pub struct ParseOptions<'r> {
+    pub entity_resolver: Option<&'r mut dyn resolver::EntityResolver>,
+    /// Read the external subset of the document type declaration and the
+    /// external parameter entities referenced in the DTD.
+    pub load_external_subset: bool,
+    /// Read external parsed general entities referenced in content.
+    pub load_external_entities: bool,
+    /// Base URI of the document entity, against which `xml:base` attributes
+    /// are resolved. It is also passed on to the entity resolver.
+    pub base_uri: Option<String>,
+    pub unknown_version_policy: UnknownVersionPolicy,
+    /// Keep going after errors in the content of the document, collecting
+    /// them in [`InfoSetStatistics::recovered_errors`]: mismatched end tags
+    /// close the elements left open, stray tokens are skipped, and further
+    /// root elements become siblings of the document element.
+    pub recover: bool,
+}
+
+impl ParseOptions<'_> {
+    /// Reads an external entity, returning its text without the text
+    /// declaration and with its line ends normalized.
+    pub(crate) fn load_external_text(
+        &mut self,
+        public_id: Option<&str>,
+        system_id: &str,
+        base_uri: Option<&str>,
+        xml_1_1: bool,
+    ) -> Result<Option<String>, ParseError> {
+        let entity_resolver = match &mut self.entity_resolver {
+            Some(entity_resolver) => entity_resolver,
+            None => return Ok(None),
+        };
+        let bytes = entity_resolver.resolve_entity(public_id, system_id, base_uri)?;
+        let mut text =
+            String::from_utf8(bytes).map_err(|_| ParseError::InvalidExternalEntityEncoding)?;
+        if text.starts_with('\u{FEFF}') {
+            text.drain(..'\u{FEFF}'.len_utf8());
+        }
+        // TextDecl ::= '<?xml' VersionInfo? EncodingDecl S? '?>'
+        if text.starts_with("<?xml")
+            && text[5..].starts_with(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        {
+            let end = text.find("?>").ok_or(ParseError::MalformedDeclaration)?;
+            text.drain(..end + 2);
+        }
+        if let Cow::Owned(normalized) = normalize_line_ends(&text, xml_1_1) {
+            text = normalized;
+        }
+        Ok(Some(text))
+    }
 }
 
 pub fn parse<'input>(input: &'input str) -> Result<InfoSet<'input>, ParseError> {
@@ -50,28 +154,486 @@ pub fn parse<'input>(input: &'input str) -> Result<InfoSet<'input>, ParseError>
     Ok(i)
 }
 
+/// Parses a document, recovering from errors in its content where possible.
+///
+/// The errors recovered from are returned along with the info set; see
+/// [`ParseOptions::recover`].
+pub fn parse_recovering<'input>(
+    input: &'input str,
+) -> Result<(InfoSet<'input>, Vec<RecoveredError>), ParseError> {
+    let options = ParseOptions {
+        recover: true,
+        ..ParseOptions::default()
+    };
+    let (infoset, statistics) = parse_with_options(input, options)?;
+    Ok((infoset, statistics.recovered_errors))
+}
+
 pub fn parse_with_statistics<'input>(
     input: &'input str,
 ) -> Result<(InfoSet<'input>, InfoSetStatistics), ParseError> {
+    parse_with_options(input, ParseOptions::default())
+}
+
+pub fn parse_with_options<'input>(
+    input: &'input str,
+    options: ParseOptions<'_>,
+) -> Result<(InfoSet<'input>, InfoSetStatistics), ParseError> {
+    // The entities read are kept, so that the document can be parsed again
+    // after an edit.
+    let ParseOptions {
+        entity_resolver,
+        load_external_subset,
+        load_external_entities,
+        base_uri,
+        unknown_version_policy,
+        recover,
+    } = options;
+    let mut recording_resolver = entity_resolver.map(edit::RecordingResolver::new);
+    let mut options = ParseOptions {
+        entity_resolver: recording_resolver
+            .as_mut()
+            .map(|recording| recording as &mut dyn resolver::EntityResolver),
+        load_external_subset,
+        load_external_entities,
+        base_uri,
+        unknown_version_policy,
+        recover,
+    };
+    // The item storage is not pre-sized from `input.len()`: the number of
+    // items per byte ranges from one per few bytes for deeply nested markup to
+    // almost none for text, so any estimate would either regrow or reserve
+    // far more than the info set retains.
     let mut info_set_data = InfoSetData::default();
     let xml_tokenizer = XmlTokenizer::from(input);
     let mut tokens = xml_tokenizer.into_iter().peekable();
     let mut xmlinfoset_statistics = InfoSetStatistics::default();
     let doc_info_item = DocInfoItem::new_not_yet_parsed(&mut info_set_data);
-    parse_xml_doc(&mut info_set_data, &mut tokens, doc_info_item)?;
+    let document_len = input.len();
+    let input = parse_xml_doc(
+        &mut info_set_data,
+        input,
+        &mut tokens,
+        doc_info_item,
+        &mut options,
+        &mut xmlinfoset_statistics,
+    )?;
     info_set_data.doc_info_item = Some(doc_info_item);
-    let xmlinfoset = InfoSet {
-        input: Cow::Borrowed(input),
+    let mut stored_options = edit::StoredOptions::new(&options);
+    if let Some(recording_resolver) = recording_resolver {
+        stored_options.keep_entities(recording_resolver);
+    }
+    let mut xmlinfoset = InfoSet {
+        input,
         data: info_set_data,
+        document: edit::DocumentText::new(document_len, stored_options),
     };
+    link_pi_notations(&mut xmlinfoset);
+    xmlinfoset_statistics.validity_errors = validate::resolve_references(&mut xmlinfoset);
+    let standalone_violations = validate::check_standalone(&xmlinfoset);
+    xmlinfoset_statistics.has_standalone_violation = !standalone_violations.is_empty();
+    xmlinfoset_statistics
+        .validity_errors
+        .extend(standalone_violations);
+    StatisticsCollector::new(&mut xmlinfoset_statistics).collect(&xmlinfoset);
     Ok((xmlinfoset, xmlinfoset_statistics))
 }
 
-fn parse_xml_doc(
+/// What is in scope where a fragment is parsed.
+#[derive(Clone, Debug, Default)]
+pub struct FragmentContext {
+    /// In-scope namespaces as prefix and namespace name pairs, `None` being
+    /// the default namespace. The `xml` prefix is always in scope.
+    pub namespaces: Vec<(Option<String>, String)>,
+    /// Internal general entities by name, with their replacement text.
+    pub entities: BTreeMap<String, String>,
+    pub base_uri: Option<String>,
+}
+
+/// Parses content that is not a full document: any number of elements,
+/// character data, comments and processing instructions, optionally preceded
+/// by a text declaration.
+///
+/// The items are returned in order. They are children of a synthetic element
+/// with an empty name, the document element of a synthetic document.
+pub fn parse_fragment<'input>(
+    input: &'input str,
+    context: &FragmentContext,
+) -> Result<(InfoSet<'input>, Vec<ElementChildInfoItem>), ParseError> {
+    let mut repo = InfoSetData::default();
+    let mut options = ParseOptions {
+        base_uri: context.base_uri.clone(),
+        ..ParseOptions::default()
+    };
+    let mut statistics = InfoSetStatistics::default();
+    for (name, replacement_text) in &context.entities {
+        repo.dtd_declarations.general_entities.insert(
+            name.clone(),
+            dtd::EntityDecl::Internal {
+                replacement_text: replacement_text.clone(),
+            },
+        );
+    }
+
+    let mut start = 0;
+    if input.starts_with('\u{FEFF}') {
+        start = '\u{FEFF}'.len_utf8();
+    }
+    // TextDecl ::= '<?xml' VersionInfo? EncodingDecl S? '?>'
+    if input[start..].starts_with("<?xml")
+        && input[start + 5..].starts_with(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+    {
+        let end = input[start..]
+            .find("?>")
+            .ok_or(ParseError::MalformedDeclaration)?;
+        start += end + 2;
+    }
+
+    let namespace_atom = repo.atoms.intern(XML_NAMESPACE);
+    let xml_namespace = NSInfoItem::new(
+        Some(CowSpan::Static("xml")),
+        CowSpan::Static(XML_NAMESPACE),
+        namespace_atom,
+        &mut repo,
+    );
+    let mut bindings = vec![(Some("xml"), Some(xml_namespace))];
+    for (prefix, namespace) in &context.namespaces {
+        let namespace_atom = repo.atoms.intern(namespace);
+        let ns_info_item = NSInfoItem::new(
+            prefix.clone().map(CowSpan::Owned),
+            CowSpan::Owned(namespace.clone()),
+            namespace_atom,
+            &mut repo,
+        );
+        bindings.push((prefix.as_deref(), Some(ns_info_item)));
+    }
+    let namespace_scope = repo.push_namespace_scope(None, bindings);
+    let doc_info_item = DocInfoItem::new_not_yet_parsed(&mut repo);
+    let expanded_name = repo.atoms.expanded_name(None, "");
+    let parent = ElementInfoItem::new(
+        Span(start..start),
+        None,
+        expanded_name,
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+        namespace_scope,
+        document_base_uri(&options),
+        None,
+        ElementParentInfoItem::Doc(doc_info_item),
+        &mut repo,
+    );
+    let (notations, unparsed_entities) = create_notations_and_unparsed_entities(&mut repo, input);
+    doc_info_item.transition_to_parsed_from_not_yet_parsed(
+        repo::keyed!(version: Version::Version1_0),
+        repo::keyed!(character_encoding_scheme: None),
+        repo::keyed!(standalone: None),
+        repo::keyed!(document_element: parent),
+        repo::keyed!(children: vec![DocChildInfoItem::Element(parent)]),
+        repo::keyed!(notations: notations),
+        repo::keyed!(unparsed_entities: unparsed_entities),
+        repo::keyed!(base_uri: document_base_uri(&options)),
+        repo::keyed!(all_declarations_processed: true),
+        &mut repo,
+    );
+    repo.doc_info_item = Some(doc_info_item);
+
+    repo.dtd_declarations.add_entity_contents(input);
+    let mut document_text = Cow::Borrowed(input);
+    if !repo.dtd_declarations.external_texts.is_empty() {
+        let mut text = input.to_owned();
+        repo.dtd_declarations.append_external_texts(&mut text);
+        document_text = Cow::Owned(text);
+    }
+    let mut tokens = XmlTokenizer::from_fragment(input, start..input.len()).peekable();
+    let mut pending_text = PendingText::default();
+    parse_content(
+        &mut repo,
+        &document_text,
+        &mut tokens,
+        parent,
+        None,
+        &mut pending_text,
+        &mut options,
+        &mut statistics,
+    )?;
+    pending_text.flush(&mut repo, &document_text, parent, None);
+
+    let children = parent.children(&repo).clone();
+    let infoset = InfoSet {
+        input: document_text,
+        data: repo,
+        document: edit::DocumentText::new(input.len(), edit::StoredOptions::new(&options)),
+    };
+    Ok((infoset, children))
+}
+
+/// Parses content up to the end of `tokens` as children of `parent`, as in a
+/// fragment or in the replacement text of an entity.
+fn parse_content<'a, 't: 'a>(
     repo: &mut InfoSetData,
-    mut tokens: &mut Peekable<XmlTokenizer>,
-    doc_info_item: DocInfoItem,
+    input: &'a str,
+    tokens: &mut Peekable<XmlTokenizer<'t>>,
+    parent: ElementInfoItem,
+    is_element_content: Option<bool>,
+    pending_text: &mut PendingText,
+    options: &mut ParseOptions<'_>,
+    statistics: &mut InfoSetStatistics,
 ) -> Result<(), ParseError> {
+    let xml_1_1 = repo.dtd_declarations.xml_1_1;
+    loop {
+        // An error is taken out of `tokens` rather than cloned.
+        if let Some(Err(e)) = tokens.next_if(Result::is_err) {
+            let offset = text_pos_offset(input, e.pos());
+            let span = Span(offset..offset);
+            return recover(options, statistics, ParseError::TokenError(e), span);
+        }
+        let peeked_token = match tokens.peek() {
+            Some(Ok(peeked_token)) => peeked_token,
+            Some(Err(_)) | None => return Ok(()),
+        };
+        if !matches!(peeked_token, XmlToken::Text { .. } | XmlToken::Cdata { .. }) {
+            pending_text.flush(repo, input, parent, is_element_content);
+        }
+        match *peeked_token {
+            XmlToken::ElementStart { .. } => {
+                let tree_context = TreeContext {
+                    parent: ElementParentInfoItem::Element(parent),
+                    namespace_scope: parent.namespace_scope(repo),
+                    base_uri: parent.base_uri(repo),
+                };
+                let element =
+                    parse_element_tree(repo, input, tokens, tree_context, options, statistics)?;
+                append_to_element_as_child(repo, parent, element);
+            }
+            XmlToken::Text { text } => {
+                tokens.next();
+                append_text(
+                    repo,
+                    input,
+                    parent,
+                    is_element_content,
+                    text,
+                    pending_text,
+                    options,
+                    statistics,
+                )?;
+            }
+            XmlToken::Cdata { text, .. } => {
+                tokens.next();
+                statistics.has_cdata = true;
+                pending_text.push_chars(input, Span::from_xml_strspan(text), xml_1_1);
+            }
+            XmlToken::Comment { text, .. } => {
+                tokens.next();
+                let comment_info_item = CommentInfoItem::new(
+                    normalized_strspan(text, xml_1_1),
+                    CommentParentInfoItem::Element(parent),
+                    repo,
+                );
+                append_to_element_as_child(repo, parent, comment_info_item);
+            }
+            XmlToken::ProcessingInstruction {
+                target, content, ..
+            } => {
+                tokens.next();
+                let pi_info_item = PIInfoItem::new(
+                    Span::from_xml_strspan(target),
+                    content.map(|content| normalized_strspan(content, xml_1_1)),
+                    parent.base_uri(repo),
+                    None,
+                    PIParentInfoItem::Element(parent),
+                    repo,
+                );
+                append_to_element_as_child(repo, parent, pi_info_item);
+            }
+            ref token => {
+                let span = token_span(token);
+                tokens.next();
+                recover(options, statistics, ParseError::UnexpectedToken, span)?;
+            }
+        }
+    }
+}
+
+impl InfoSet<'_> {
+    /// Elements whose end tag was supplied while recovering from errors, or
+    /// that were kept as additional root elements.
+    pub fn recovered_elements(&self) -> &[ElementInfoItem] {
+        &self.data.recovered_elements
+    }
+}
+
+/// Records `error` if the parser recovers from errors, or fails with it.
+fn recover(
+    options: &ParseOptions<'_>,
+    statistics: &mut InfoSetStatistics,
+    error: ParseError,
+    span: Span,
+) -> Result<(), ParseError> {
+    if !options.recover {
+        return Err(error);
+    }
+    statistics
+        .recovered_errors
+        .push(RecoveredError { error, span });
+    Ok(())
+}
+
+/// Skips the tokens of a document type declaration that is out of place.
+fn skip_dtd(tokens: &mut Peekable<XmlTokenizer<'_>>) {
+    if let Some(Ok(XmlToken::DtdStart { .. })) = tokens.next() {
+        while let Some(Ok(token)) = tokens.next() {
+            if matches!(token, XmlToken::DtdEnd { .. }) {
+                break;
+            }
+        }
+    }
+}
+
+/// Returns the byte offset of a position reported by the tokenizer.
+fn text_pos_offset(input: &str, pos: xmlparser::TextPos) -> usize {
+    let line_start: usize = input
+        .split_inclusive('\n')
+        .take(pos.row as usize - 1)
+        .map(str::len)
+        .sum();
+    input[line_start..]
+        .char_indices()
+        .nth(pos.col as usize - 1)
+        .map_or(input.len(), |(i, _)| line_start + i)
+}
+
+/// Returns the error for a token, or the lack of one, that cuts an element
+/// short, with its span.
+fn premature_end(
+    input: &str,
+    item: Option<Result<XmlToken<'_>, xmlparser::Error>>,
+) -> (ParseError, Span) {
+    match item {
+        None => (ParseError::UnexpectedEOF, Span(input.len()..input.len())),
+        Some(Err(e)) => {
+            let offset = text_pos_offset(input, e.pos());
+            (ParseError::TokenError(e), Span(offset..offset))
+        }
+        Some(Ok(token)) => (ParseError::UnexpectedToken, token_span(&token)),
+    }
+}
+
+/// Returns the span of the input a token was read from.
+fn token_span(token: &XmlToken<'_>) -> Span {
+    Span::from_xml_strspan(match *token {
+        XmlToken::Declaration { span, .. }
+        | XmlToken::ProcessingInstruction { span, .. }
+        | XmlToken::Comment { span, .. }
+        | XmlToken::DtdStart { span, .. }
+        | XmlToken::EmptyDtd { span, .. }
+        | XmlToken::EntityDeclaration { span, .. }
+        | XmlToken::DtdEnd { span }
+        | XmlToken::ElementStart { span, .. }
+        | XmlToken::Attribute { span, .. }
+        | XmlToken::ElementEnd { span, .. }
+        | XmlToken::Cdata { span, .. } => span,
+        XmlToken::Text { text } => text,
+    })
+}
+
+/// Counts the items of a parsed info set into its statistics.
+struct StatisticsCollector<'s> {
+    statistics: &'s mut InfoSetStatistics,
+    namespaces: BTreeSet<String>,
+    depth: usize,
+}
+
+impl<'s> StatisticsCollector<'s> {
+    fn new(statistics: &'s mut InfoSetStatistics) -> Self {
+        StatisticsCollector {
+            statistics,
+            namespaces: BTreeSet::new(),
+            depth: 0,
+        }
+    }
+
+    fn collect(mut self, infoset: &InfoSet<'_>) {
+        visit::visit_infoset(&mut self, infoset);
+        self.statistics.namespace_count = self.namespaces.len();
+    }
+
+    fn note_name(&mut self, input: &str, prefix: Option<Span>, namespace_name: Option<&str>) {
+        if let Some(prefix) = prefix {
+            self.statistics.prefixes.insert(input[prefix.0].to_owned());
+        }
+        if let Some(namespace_name) = namespace_name {
+            self.namespaces.insert(namespace_name.to_owned());
+        }
+    }
+}
+
+impl<'a> InfoSetVisitor<'a> for StatisticsCollector<'_> {
+    fn visit_element(&mut self, infoset: &'a InfoSet<'a>, item: ElementInfoItem) {
+        self.statistics.element_count += 1;
+        self.depth += 1;
+        self.statistics.max_depth = self.statistics.max_depth.max(self.depth);
+        self.note_name(
+            &infoset.input,
+            item.prefix(infoset),
+            item.namespace_name(infoset),
+        );
+        // Namespace declarations are not counted as attributes.
+        for &attribute in item.attributes(infoset) {
+            self.visit_attribute(infoset, attribute);
+        }
+        for &child in item.children(infoset) {
+            self.visit_element_child(infoset, child);
+        }
+        self.depth -= 1;
+    }
+
+    fn visit_attribute(&mut self, infoset: &'a InfoSet<'a>, item: AttributeInfoItem) {
+        self.statistics.attribute_count += 1;
+        let namespace_name = item.namespace_name(infoset);
+        self.note_name(
+            &infoset.input,
+            item.prefix(infoset),
+            namespace_name
+                .as_ref()
+                .map(|namespace_name| input_str(&infoset.input, namespace_name)),
+        );
+        visit::walk_attribute(self, infoset, item);
+    }
+
+    fn visit_char_group(&mut self, infoset: &'a InfoSet<'a>, item: CharGroupInfoItem) {
+        self.statistics.text_count += 1;
+        self.statistics.text_bytes += input_str(&infoset.input, &item.characters(infoset)).len();
+        visit::walk_char_group(self, infoset, item);
+    }
+
+    fn visit_comment(&mut self, infoset: &'a InfoSet<'a>, item: CommentInfoItem) {
+        self.statistics.comment_count += 1;
+        visit::walk_comment(self, infoset, item);
+    }
+
+    fn visit_pi(&mut self, infoset: &'a InfoSet<'a>, item: PIInfoItem) {
+        self.statistics.pi_count += 1;
+        visit::walk_pi(self, infoset, item);
+    }
+
+    fn visit_dtd(&mut self, infoset: &'a InfoSet<'a>, item: DTDInfoItem) {
+        self.statistics.has_dtd = true;
+        visit::walk_dtd(self, infoset, item);
+    }
+}
+
+/// Parses the document, returning its text followed by the text of the
+/// external entities read while processing the DTD.
+fn parse_xml_doc<'input>(
+    repo: &mut InfoSetData,
+    input: &'input str,
+    mut tokens: &mut Peekable<XmlTokenizer<'input>>,
+    doc_info_item: DocInfoItem,
+    options: &mut ParseOptions<'_>,
+    statistics: &mut InfoSetStatistics,
+) -> Result<Cow<'input, str>, ParseError> {
     enum DocState {
         Initial,
         AfterXmlDecl,
@@ -84,11 +646,10 @@ fn parse_xml_doc(
     state = DocState::Initial;
     let (mut xml_version, mut xml_encoding, mut xml_standalone) = (None, None, None);
 
-    if let Some(peeked_token) = tokens.peek() {
-        let peeked_token = match peeked_token {
-            Ok(peeked_token) => peeked_token,
-            Err(e) => return Err(ParseError::TokenError(e.clone())),
-        };
+    if let Some(Err(e)) = tokens.next_if(Result::is_err) {
+        return Err(ParseError::TokenError(e));
+    }
+    if let Some(Ok(peeked_token)) = tokens.peek() {
         if let XmlToken::Declaration { .. } = peeked_token {
             let token = tokens.next().unwrap().unwrap();
             if let XmlToken::Declaration {
@@ -104,36 +665,125 @@ fn parse_xml_doc(
             }
         }
     };
+    match &xml_version {
+        Some(Version::Other(version))
+            if options.unknown_version_policy == UnknownVersionPolicy::Reject =>
+        {
+            return Err(ParseError::UnsupportedVersion(version.clone()));
+        }
+        Some(Version::Version1_1) => repo.dtd_declarations.xml_1_1 = true,
+        _ => {}
+    }
 
     state = DocState::AfterXmlDecl;
+    let mut document_text = Cow::Borrowed(input);
     let mut root_element = None;
-    while let Some(peeked_token) = tokens.peek() {
-        let peeked_token = match peeked_token {
-            Ok(peeked_token) => peeked_token,
-            Err(e) => return Err(ParseError::TokenError(e.clone())),
+    let mut children = Vec::new();
+    loop {
+        // An error is taken out of `tokens` rather than cloned.
+        if let Some(Err(e)) = tokens.next_if(Result::is_err) {
+            let offset = text_pos_offset(input, e.pos());
+            let span = Span(offset..offset);
+            recover(options, statistics, ParseError::TokenError(e), span)?;
+            break;
+        }
+        let peeked_token = match tokens.peek() {
+            Some(Ok(peeked_token)) => peeked_token,
+            Some(Err(_)) | None => break,
         };
         match peeked_token {
             XmlToken::DtdStart { .. } | XmlToken::EmptyDtd { .. } => {
                 if !matches!(state, DocState::AfterXmlDecl) {
-                    return Err(ParseError::UnexpectedToken);
+                    let span = token_span(peeked_token);
+                    recover(options, statistics, ParseError::UnexpectedToken, span)?;
+                    skip_dtd(tokens);
+                    continue;
+                }
+                repo.dtd_declarations.standalone = xml_standalone == Some(true);
+                let dtd = parse_dtd(repo, input, &mut tokens, doc_info_item, options)?;
+                if options.load_external_entities {
+                    preload_external_entities(repo, input, options);
                 }
-                let dtd = parse_dtd(repo, &mut tokens)?;
+                repo.dtd_declarations.add_entity_contents(input);
+                if !repo.dtd_declarations.external_texts.is_empty() {
+                    let mut text = input.to_owned();
+                    repo.dtd_declarations.append_external_texts(&mut text);
+                    document_text = Cow::Owned(text);
+                }
+                children.push(DocChildInfoItem::DTD(dtd));
                 state = DocState::AfterDTD;
             }
-            XmlToken::ElementStart { .. } => {
-                if !matches!(state, DocState::AfterXmlDecl | DocState::AfterDTD) {
-                    return Err(ParseError::UnexpectedToken);
+            &XmlToken::ElementStart { span, .. } => {
+                let is_duplicate = matches!(state, DocState::AfterRootElement);
+                if is_duplicate {
+                    recover(
+                        options,
+                        statistics,
+                        ParseError::DuplicateRootElement,
+                        Span::from_xml_strspan(span),
+                    )?;
+                } else if !matches!(state, DocState::AfterXmlDecl | DocState::AfterDTD) {
+                    let span = Span::from_xml_strspan(span);
+                    recover(options, statistics, ParseError::UnexpectedToken, span)?;
+                }
+                let context =
+                    TreeContext::document(repo, doc_info_item, document_base_uri(options));
+                let element = parse_element_tree(
+                    repo,
+                    &document_text,
+                    &mut tokens,
+                    context,
+                    options,
+                    statistics,
+                )?;
+                children.push(DocChildInfoItem::Element(element));
+                if is_duplicate {
+                    // Kept as a sibling of the document element.
+                    repo.recovered_elements.push(element);
+                } else {
+                    root_element = Some(element);
                 }
-                let element = parse_element_tree(repo, &mut tokens, doc_info_item)?;
-                root_element = Some(element);
                 state = DocState::AfterRootElement;
             }
             XmlToken::Comment { .. } | XmlToken::ProcessingInstruction { .. } => {
-                let misc_item = tokens.next().unwrap().unwrap();
-                // FIXME
+                let misc_item = match tokens.next().unwrap().unwrap() {
+                    XmlToken::Comment { text, .. } => {
+                        DocChildInfoItem::Comment(CommentInfoItem::new(
+                            normalized_strspan(text, repo.dtd_declarations.xml_1_1),
+                            CommentParentInfoItem::Doc(doc_info_item),
+                            repo,
+                        ))
+                    }
+                    XmlToken::ProcessingInstruction {
+                        target, content, ..
+                    } => DocChildInfoItem::PI(PIInfoItem::new(
+                        Span::from_xml_strspan(target),
+                        content.map(|content| {
+                            normalized_strspan(content, repo.dtd_declarations.xml_1_1)
+                        }),
+                        document_base_uri(options),
+                        None,
+                        PIParentInfoItem::Doc(doc_info_item),
+                        repo,
+                    )),
+                    _ => unreachable!(),
+                };
+                children.push(misc_item);
+            }
+            // Once the internal subset has been read, the rest of the document
+            // is tokenized as a fragment, which reports white space as text.
+            XmlToken::Text { text }
+                if text
+                    .as_str()
+                    .trim_start_matches(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+                    .is_empty() =>
+            {
+                tokens.next();
             }
             _ => {
-                return Err(ParseError::UnexpectedToken);
+                let span = token_span(peeked_token);
+                recover(options, statistics, ParseError::UnexpectedToken, span)?;
+                tokens.next();
             }
         };
     }
@@ -143,27 +793,236 @@ fn parse_xml_doc(
     }
     state = DocState::Done;
 
+    let (notations, unparsed_entities) =
+        create_notations_and_unparsed_entities(repo, &document_text);
+
     doc_info_item.transition_to_parsed_from_not_yet_parsed(
         repo::keyed!(version: xml_version.unwrap_or(Version::Version1_0)),
         repo::keyed!(character_encoding_scheme: xml_encoding),
         repo::keyed!(standalone: xml_standalone),
         repo::keyed!(document_element: root_element.unwrap()),
-        repo::keyed!(children: fixme_impl!(Vec::new())),
-        repo::keyed!(notations: fixme_impl!(None)),
-        repo::keyed!(unparsed_entities: fixme_impl!(Vec::new())),
-        repo::keyed!(base_uri: fixme_impl!(None)),
-        repo::keyed!(all_declarations_processed: fixme_impl!(true)),
+        repo::keyed!(children: children),
+        repo::keyed!(notations: notations),
+        repo::keyed!(unparsed_entities: unparsed_entities),
+        repo::keyed!(base_uri: document_base_uri(options)),
+        repo::keyed!(
+            all_declarations_processed: !repo.dtd_declarations.skipped_declarations
+        ),
         repo,
     );
 
-    Ok(())
+    Ok(document_text)
 }
 
-fn parse_dtd(
+/// Creates the notation and unparsed entity items declared in the DTD.
+///
+/// If a notation is declared more than once, the notations of the document
+/// have no value.
+fn create_notations_and_unparsed_entities(
     repo: &mut InfoSetData,
-    tokens: &mut Peekable<XmlTokenizer>,
-) -> Result<(), ParseError> {
-    todo!();
+    input: &str,
+) -> (Option<Vec<NotationInfoItem>>, Vec<UEInfoItem>) {
+    let declarations = &repo.dtd_declarations;
+    let notation_decls = declarations
+        .notations
+        .iter()
+        .map(|decl| {
+            (
+                declarations.text(input, &decl.name_span).to_owned(),
+                decl.name_span.clone(),
+                decl.system_id.clone(),
+                decl.public_id.clone(),
+                owned_uri(decl.base_uri.clone()),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut notations = Some(Vec::with_capacity(notation_decls.len()));
+    let mut notation_items = BTreeMap::new();
+    for (name_str, name, system_identifier, public_identifier, declaration_base_uri) in
+        notation_decls
+    {
+        let notation = NotationInfoItem::new(
+            name,
+            system_identifier,
+            public_identifier,
+            declaration_base_uri,
+            repo,
+        );
+        if notation_items.contains_key(&name_str) {
+            notation_items.insert(name_str, None);
+            notations = None;
+        } else {
+            notation_items.insert(name_str, Some(notation));
+        }
+        if let Some(notations) = &mut notations {
+            notations.push(notation);
+        }
+    }
+    repo.dtd_declarations.notation_items = notation_items;
+
+    let declarations = &repo.dtd_declarations;
+    let unparsed_entity_decls = declarations
+        .unparsed_entities
+        .iter()
+        .map(|decl| {
+            (
+                decl.name.clone(),
+                decl.system_id.clone(),
+                decl.public_id.clone(),
+                decl.notation_name_span.clone(),
+                declarations.notation_named(declarations.text(input, &decl.notation_name_span)),
+                owned_uri(decl.base_uri.clone()),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut unparsed_entities = Vec::with_capacity(unparsed_entity_decls.len());
+    for (
+        name,
+        system_identifier,
+        public_identifier,
+        notation_name,
+        notation,
+        declaration_base_uri,
+    ) in unparsed_entity_decls
+    {
+        unparsed_entities.push(UEInfoItem::new(
+            name,
+            system_identifier,
+            public_identifier,
+            declaration_base_uri,
+            notation_name,
+            notation,
+            repo,
+        ));
+    }
+    (notations, unparsed_entities)
+}
+
+/// Links every processing instruction to the notation its target names.
+fn link_pi_notations(infoset: &mut InfoSet<'_>) {
+    let declarations = &infoset.data.dtd_declarations;
+    if declarations.notation_items.is_empty() && !declarations.skipped_declarations {
+        return;
+    }
+    visit::visit_infoset_mut(&mut NotationLinker, infoset);
+}
+
+struct NotationLinker;
+
+impl InfoSetVisitorMut for NotationLinker {
+    fn visit_pi_mut(&mut self, infoset: &mut InfoSet<'_>, item: PIInfoItem) {
+        let declarations = &infoset.data.dtd_declarations;
+        let target = declarations.text(&infoset.input, &item.target(infoset));
+        let notation = match declarations.notation_named(target) {
+            UnknownOr::Unknown => Some(UnknownOr::Unknown),
+            UnknownOr::Known(notation) => notation.map(UnknownOr::Known),
+        };
+        item.set_notation(notation, &mut infoset.data);
+    }
+}
+
+fn external_id_spans(
+    external_id: Option<xmlparser::ExternalId<'_>>,
+) -> (Option<Span>, Option<Span>) {
+    use xmlparser::ExternalId;
+    match external_id {
+        None => (None, None),
+        Some(ExternalId::System(system)) => (Some(Span::from_xml_strspan(system)), None),
+        Some(ExternalId::Public(public, system)) => (
+            Some(Span::from_xml_strspan(system)),
+            Some(Span::from_xml_strspan(public)),
+        ),
+    }
+}
+
+fn parse_dtd<'input>(
+    repo: &mut InfoSetData,
+    input: &'input str,
+    tokens: &mut Peekable<XmlTokenizer<'input>>,
+    doc_info_item: DocInfoItem,
+    options: &mut ParseOptions<'_>,
+) -> Result<DTDInfoItem, ParseError> {
+    let (name, external_id, subset_start) = match tokens.next() {
+        Some(Ok(XmlToken::EmptyDtd {
+            name, external_id, ..
+        })) => (name, external_id, None),
+        Some(Ok(XmlToken::DtdStart {
+            name,
+            external_id,
+            span,
+        })) => (name, external_id, Some(span.end())),
+        Some(Err(e)) => return Err(ParseError::TokenError(e)),
+        _ => return Err(ParseError::UnexpectedToken),
+    };
+    repo.dtd_declarations.doctype_name = Some(Span::from_xml_strspan(name));
+    let (system_identifier, public_identifier) = external_id_spans(external_id);
+    let dtd_info_item = DTDInfoItem::new(
+        system_identifier.clone(),
+        public_identifier.clone(),
+        Vec::new(),
+        doc_info_item,
+        repo,
+    );
+    if let Some(subset_start) = subset_start {
+        // The tokenizer does not understand parameter entity references, so
+        // the internal subset is read here and tokenizing resumes after it.
+        let dtd_end =
+            dtd::parse_internal_subset(input, subset_start, &mut repo.dtd_declarations, options)?;
+        *tokens = XmlTokenizer::from_fragment(input, dtd_end..input.len()).peekable();
+    }
+    // The internal subset is read first, so its declarations take precedence
+    // over the ones of the external subset.
+    if let Some(system_identifier) = &system_identifier {
+        let base_uri = options.base_uri.clone();
+        let text = if options.load_external_subset {
+            options.load_external_text(
+                public_identifier
+                    .as_ref()
+                    .map(|span| &input[span.0.clone()]),
+                &input[system_identifier.0.clone()],
+                base_uri.as_deref(),
+                repo.dtd_declarations.xml_1_1,
+            )?
+        } else {
+            None
+        };
+        match text {
+            Some(text) => {
+                let (offset, text) = repo.dtd_declarations.add_external_text(input, text);
+                let subset_uri =
+                    uri::resolve_opt(base_uri.as_deref(), &input[system_identifier.0.clone()]);
+                dtd::parse_external_subset(
+                    input,
+                    &text,
+                    offset,
+                    Some(&subset_uri),
+                    &mut repo.dtd_declarations,
+                    options,
+                )?;
+            }
+            None => repo.dtd_declarations.skipped_declarations = true,
+        }
+    }
+    for (target, content, base_uri) in repo.dtd_declarations.processing_instructions.clone() {
+        let declarations = &repo.dtd_declarations;
+        let content = content.map(|content| {
+            normalized_span(
+                declarations.text(input, &content),
+                content,
+                declarations.xml_1_1,
+            )
+        });
+        let pi_info_item = PIInfoItem::new(
+            target,
+            content,
+            owned_uri(base_uri),
+            None,
+            PIParentInfoItem::DTD(dtd_info_item),
+            repo,
+        );
+        dtd_info_item.children_mut(repo).push(pi_info_item);
+    }
+    Ok(dtd_info_item)
 }
 
 fn append_to_element_as_child(
@@ -172,20 +1031,568 @@ fn append_to_element_as_child(
     v: impl Into<ElementChildInfoItem>,
 ) {
     let parent_children = parent.children_mut(repo);
-    todo!();
+    parent_children.push(v.into());
 }
 
-fn parse_element_tree(
+fn document_base_uri(options: &ParseOptions<'_>) -> Option<UriSpan> {
+    owned_uri(options.base_uri.clone())
+}
+
+fn owned_uri(uri: Option<String>) -> Option<UriSpan> {
+    uri.map(|uri| UriSpan(CowSpan::Owned(uri)))
+}
+
+/// Normalizes line ends to `\n` as described in XML 1.0 §2.11, or XML 1.1
+/// §2.11, which adds `#x85` and `#x2028`.
+pub(crate) fn normalize_line_ends(text: &str, xml_1_1: bool) -> Cow<'_, str> {
+    let is_line_end = |c: char| c == '\r' || (xml_1_1 && matches!(c, '\u{85}' | '\u{2028}'));
+    if !text.contains(is_line_end) {
+        return Cow::Borrowed(text);
+    }
+    let mut normalized = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                if chars.peek() == Some(&'\n') || (xml_1_1 && chars.peek() == Some(&'\u{85}')) {
+                    chars.next();
+                }
+                normalized.push('\n');
+            }
+            '\u{85}' | '\u{2028}' if xml_1_1 => normalized.push('\n'),
+            c => normalized.push(c),
+        }
+    }
+    Cow::Owned(normalized)
+}
+
+/// Returns the text of `span` with its line ends normalized, keeping it
+/// borrowed if there is nothing to normalize.
+fn normalized_span(text: &str, span: Span, xml_1_1: bool) -> CowSpan {
+    match normalize_line_ends(text, xml_1_1) {
+        Cow::Borrowed(_) => CowSpan::Borrowed(span),
+        Cow::Owned(text) => CowSpan::Owned(text),
+    }
+}
+
+fn normalized_strspan(text: XmlStrSpan<'_>, xml_1_1: bool) -> CowSpan {
+    normalized_span(text.as_str(), Span::from_xml_strspan(text), xml_1_1)
+}
+
+pub(crate) fn input_str<'a>(input: &'a str, span: &'a CowSpan) -> &'a str {
+    match span {
+        CowSpan::Borrowed(span) => &input[span.0.clone()],
+        CowSpan::Owned(str) => str,
+        CowSpan::Static(str) => str,
+    }
+}
+
+fn split_qname(name: &str, name_span: &Span) -> (Option<Span>, Span) {
+    match name.find(':') {
+        Some(colon) => {
+            let start = name_span.0.start;
+            (
+                Some(Span(start..start + colon)),
+                Span(start + colon + 1..name_span.0.end),
+            )
+        }
+        None => (None, name_span.clone()),
+    }
+}
+
+/// Character data read since the last non-character child of an element.
+///
+/// Adjacent pieces that are contiguous in the input stay borrowed.
+#[derive(Default)]
+struct PendingText {
+    characters: Option<CowSpan>,
+}
+
+impl PendingText {
+    fn push_span(&mut self, input: &str, span: Span) {
+        match &mut self.characters {
+            None => {
+                self.characters = Some(CowSpan::Borrowed(span));
+                return;
+            }
+            Some(CowSpan::Borrowed(prev)) if prev.0.end == span.0.start => {
+                prev.0.end = span.0.end;
+                return;
+            }
+            Some(_) => {}
+        }
+        self.push_str(input, &input[span.0]);
+    }
+
+    /// Appends character data from the input, with its line ends
+    /// normalized.
+    fn push_chars(&mut self, input: &str, span: Span, xml_1_1: bool) {
+        match normalize_line_ends(&input[span.0.clone()], xml_1_1) {
+            Cow::Borrowed(_) => self.push_span(input, span),
+            Cow::Owned(text) => self.push_str(input, &text),
+        }
+    }
+
+    fn push_str(&mut self, input: &str, s: &str) {
+        let mut characters = match self.characters.take() {
+            None => String::new(),
+            Some(CowSpan::Owned(characters)) => characters,
+            Some(characters) => input_str(input, &characters).to_owned(),
+        };
+        characters.push_str(s);
+        self.characters = Some(CowSpan::Owned(characters));
+    }
+
+    fn push_char(&mut self, input: &str, c: char) {
+        self.push_str(input, c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Appends the pending characters to `parent` as a character group.
+    fn flush(
+        &mut self,
+        repo: &mut InfoSetData,
+        input: &str,
+        parent: ElementInfoItem,
+        is_element_content: Option<bool>,
+    ) {
+        let characters = match self.characters.take() {
+            Some(characters) => characters,
+            None => return,
+        };
+        let is_whitespace = input_str(input, &characters)
+            .chars()
+            .all(|c| matches!(c, ' ' | '\t' | '\n' | '\r'));
+        // White space has no such property without a declaration of the
+        // parent, unless the declaration may be among the ones not read.
+        let element_content_whitespace = match is_element_content {
+            _ if !is_whitespace => UnknownOr::Known(Some(false)),
+            Some(is_element_content) => UnknownOr::Known(Some(is_element_content)),
+            None if repo.dtd_declarations.skipped_declarations => UnknownOr::Unknown,
+            None => UnknownOr::Known(None),
+        };
+        let char_group_info_item =
+            CharGroupInfoItem::new(characters, element_content_whitespace, parent, repo);
+        append_to_element_as_child(repo, parent, char_group_info_item);
+    }
+}
+
+/// Appends the character data of a text token to `pending_text`, expanding
+/// character and entity references.
+///
+/// The replacement text of an entity that contains markup is parsed as
+/// content; references to entities whose replacement text is not available are
+/// appended to `parent` as unexpanded entity reference items.
+fn append_text(
     repo: &mut InfoSetData,
-    tokens: &mut Peekable<XmlTokenizer>,
-    doc_info_item: DocInfoItem,
+    input: &str,
+    parent: ElementInfoItem,
+    is_element_content: Option<bool>,
+    text: XmlStrSpan<'_>,
+    pending_text: &mut PendingText,
+    options: &mut ParseOptions<'_>,
+    statistics: &mut InfoSetStatistics,
+) -> Result<(), ParseError> {
+    note_external_entity_references(&mut repo.dtd_declarations, text);
+    let raw = text.as_str();
+    let base = text.start();
+    let xml_1_1 = repo.dtd_declarations.xml_1_1;
+    let mut expansions = 0;
+    let mut pos = 0;
+    while let Some(start) = raw[pos..].find('&') {
+        let start = pos + start;
+        if start > pos {
+            pending_text.push_chars(input, Span(base + pos..base + start), xml_1_1);
+        }
+        let end = match raw[start..].find(';') {
+            Some(end) => start + end,
+            None => {
+                let span = Span(base + start..base + raw.len());
+                recover(options, statistics, ParseError::MalformedReference, span)?;
+                // The rest of the text is kept as it is.
+                break;
+            }
+        };
+        let reference = &raw[start + 1..end];
+        let reference_span = Span(base + start..base + end + 1);
+        pos = end + 1;
+        match dtd::parse_char_reference(reference, xml_1_1) {
+            Ok(Some(c)) => {
+                pending_text.push_char(input, c);
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                recover(options, statistics, e, reference_span)?;
+                continue;
+            }
+        }
+        if let Some(c) = dtd::predefined_entity(reference) {
+            pending_text.push_char(input, c);
+            continue;
+        }
+        if options.load_external_entities {
+            if let Err(e) = load_external_entity(repo, input, options, reference) {
+                recover(options, statistics, e, reference_span.clone())?;
+            }
+        }
+        let mut expansion = String::new();
+        let mut reference_expansions = 0;
+        match repo.dtd_declarations.expand_entity_text(
+            reference,
+            &mut expansion,
+            &mut reference_expansions,
+        ) {
+            Ok(true) => {
+                expansions += reference_expansions;
+                pending_text.push_str(input, &expansion);
+                continue;
+            }
+            Ok(false) => {}
+            Err(e) => {
+                recover(options, statistics, e, reference_span)?;
+                continue;
+            }
+        }
+        if let Some(span) = repo
+            .dtd_declarations
+            .entity_contents
+            .get(reference)
+            .cloned()
+        {
+            let declarations = &mut repo.dtd_declarations;
+            if declarations
+                .open_entities
+                .iter()
+                .any(|name| name == reference)
+            {
+                let error = ParseError::RecursiveEntityReference;
+                recover(options, statistics, error, reference_span)?;
+                continue;
+            }
+            declarations.open_entities.push(reference.to_owned());
+            // The references in the replacement text are counted as it is
+            // parsed.
+            expansions += 1;
+            let mut tokens = XmlTokenizer::from_fragment(input, span.0).peekable();
+            let result = parse_content(
+                repo,
+                input,
+                &mut tokens,
+                parent,
+                is_element_content,
+                pending_text,
+                options,
+                statistics,
+            );
+            repo.dtd_declarations.open_entities.pop();
+            result?;
+            continue;
+        }
+        let (system_identifier, public_identifier, declaration_base_uri) =
+            match repo.dtd_declarations.general_entities.get(reference) {
+                Some(dtd::EntityDecl::External {
+                    system_id,
+                    public_id,
+                    base_uri,
+                    ..
+                }) => (
+                    Some(system_id.clone()),
+                    public_id.clone(),
+                    owned_uri(base_uri.clone()),
+                ),
+                _ => (None, None, None),
+            };
+        pending_text.flush(repo, input, parent, is_element_content);
+        let uer_info_item = UERInfoItem::new(
+            Span(base + start + 1..base + end),
+            UnknownOr::Known(system_identifier),
+            UnknownOr::Known(public_identifier),
+            UnknownOr::Known(declaration_base_uri),
+            parent,
+            repo,
+        );
+        append_to_element_as_child(repo, parent, uer_info_item);
+    }
+    if pos < raw.len() {
+        pending_text.push_chars(input, Span(base + pos..base + raw.len()), xml_1_1);
+    }
+    note_entity_references(statistics, raw, expansions);
+    Ok(())
+}
+
+/// Returns the offsets and names of the entity references in `text`, leaving
+/// out character references.
+fn entity_references(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut pos = 0;
+    core::iter::from_fn(move || loop {
+        let start = pos + text[pos..].find('&')?;
+        let end = start + text[start..].find(';')?;
+        pos = end + 1;
+        if !text[start + 1..end].starts_with('#') {
+            return Some((start, &text[start + 1..end]));
+        }
+    })
+}
+
+/// Records the references in `text` to entities declared in external markup
+/// declarations, if the document is standalone.
+fn note_external_entity_references(declarations: &mut dtd::DtdDeclarations, text: XmlStrSpan<'_>) {
+    if !declarations.standalone {
+        return;
+    }
+    for (start, name) in entity_references(text.as_str()) {
+        if declarations.external_general_entities.contains(name) {
+            let start = text.start() + start;
+            declarations
+                .external_entity_references
+                .push(Span(start..start + name.len() + 2));
+        }
+    }
+}
+
+/// Reads the external parsed entities referenced in `input` or in the
+/// replacement texts of internal entities, so that their replacement texts can
+/// be parsed as content where they are referenced.
+fn preload_external_entities(repo: &mut InfoSetData, input: &str, options: &mut ParseOptions<'_>) {
+    let mut names: BTreeSet<&str> = entity_references(input).map(|(_, name)| name).collect();
+    for decl in repo.dtd_declarations.general_entities.values() {
+        if let dtd::EntityDecl::Internal { replacement_text } = decl {
+            names.extend(entity_references(replacement_text).map(|(_, name)| name));
+        }
+    }
+    let names: Vec<String> = names.into_iter().map(str::to_owned).collect();
+    for name in names {
+        // A failure is reported where the entity is referenced, as the entity
+        // is read again there.
+        let _ = load_external_entity(repo, input, options, &name);
+    }
+}
+
+/// Reads the replacement text of the external parsed entity `name`, and of
+/// the external entities referenced from it, unless they have been read
+/// already.
+fn load_external_entity(
+    repo: &mut InfoSetData,
+    input: &str,
+    options: &mut ParseOptions<'_>,
+    name: &str,
+) -> Result<(), ParseError> {
+    let mut names = vec![name.to_owned()];
+    while let Some(name) = names.pop() {
+        let declarations = &repo.dtd_declarations;
+        if declarations.external_replacement_texts.contains_key(&name) {
+            continue;
+        }
+        let (public_id, system_id, base_uri) = match declarations.general_entities.get(&name) {
+            Some(dtd::EntityDecl::External {
+                public_id,
+                system_id,
+                notation_name: None,
+                base_uri,
+            }) => (
+                public_id
+                    .as_ref()
+                    .map(|span| declarations.text(input, span).to_owned()),
+                declarations.text(input, system_id).to_owned(),
+                base_uri.clone(),
+            ),
+            _ => continue,
+        };
+        let text = match options.load_external_text(
+            public_id.as_deref(),
+            &system_id,
+            base_uri.as_deref(),
+            declarations.xml_1_1,
+        )? {
+            Some(text) => text,
+            None => return Ok(()),
+        };
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find('&') {
+            rest = &rest[start + 1..];
+            if let Some(end) = rest.find(';') {
+                names.push(rest[..end].to_owned());
+                rest = &rest[end + 1..];
+            }
+        }
+        repo.dtd_declarations
+            .external_replacement_texts
+            .insert(name, text);
+    }
+    Ok(())
+}
+
+struct PendingAttribute<'a> {
+    qname: &'a str,
+    prefix: Option<Span>,
+    local_name: Span,
+    normalized_value: CowSpan,
+    specified: AttrSpecified,
+    attribute_type_and_references: Option<UnknownOr<AttrTypeAndReferences>>,
+}
+
+/// Normalizes the specified attributes of an element and appends the ones
+/// defaulted from the attribute-list declarations.
+///
+/// When recovering from errors, a repeated attribute is dropped, and a value
+/// that cannot be normalized is kept as written.
+fn collect_attributes<'a>(
+    declarations: &dtd::DtdDeclarations,
+    input: &'a str,
+    element_qname: &str,
+    specified_attributes: Vec<(XmlStrSpan<'a>, XmlStrSpan<'a>, XmlStrSpan<'a>)>,
+    options: &ParseOptions<'_>,
+    statistics: &mut InfoSetStatistics,
+) -> Result<Vec<PendingAttribute<'a>>, ParseError> {
+    let mut attributes: Vec<PendingAttribute<'a>> = Vec::with_capacity(specified_attributes.len());
+    for (attr_prefix, attr_local, attr_value) in specified_attributes {
+        let qname = if attr_prefix.is_empty() {
+            attr_local.as_str()
+        } else {
+            &input[attr_prefix.start()..attr_local.end()]
+        };
+        if attributes.iter().any(|attribute| attribute.qname == qname) {
+            let span = Span::from_xml_strspan(attr_local);
+            recover(options, statistics, ParseError::DuplicateAttribute, span)?;
+            continue;
+        }
+        let decl = declarations.attribute_decl(element_qname, qname);
+        let tokenized = decl.map_or(false, |decl| decl.attr_type.is_tokenized());
+        let mut expansions = 0;
+        let normalized_value =
+            declarations.normalize_attribute_value(attr_value.as_str(), tokenized, &mut expansions);
+        note_entity_references(statistics, attr_value.as_str(), expansions);
+        let normalized_value = match normalized_value {
+            Ok(None) => CowSpan::Borrowed(Span::from_xml_strspan(attr_value)),
+            Ok(Some(value)) => CowSpan::Owned(value),
+            Err(e) => {
+                recover(options, statistics, e, Span::from_xml_strspan(attr_value))?;
+                CowSpan::Borrowed(Span::from_xml_strspan(attr_value))
+            }
+        };
+        attributes.push(PendingAttribute {
+            qname,
+            prefix: Option::<Span>::from_xml_strspan(attr_prefix),
+            local_name: Span::from_xml_strspan(attr_local),
+            normalized_value,
+            specified: AttrSpecified::Specified,
+            attribute_type_and_references: decl
+                .map(|decl| UnknownOr::Known(decl.attr_type.to_attr_type_and_references())),
+        });
+    }
+    let decls = match declarations.attribute_lists.get(element_qname) {
+        Some(decls) => decls,
+        None => return Ok(attributes),
+    };
+    for decl in decls {
+        let default = match decl.default.value() {
+            Some(default) => default,
+            None => continue,
+        };
+        if attributes
+            .iter()
+            .any(|attribute| attribute.qname == decl.name)
+        {
+            continue;
+        }
+        let mut expansions = 0;
+        let normalized_value = declarations.normalize_attribute_value(
+            &default.raw,
+            decl.attr_type.is_tokenized(),
+            &mut expansions,
+        );
+        note_entity_references(statistics, &default.raw, expansions);
+        let normalized_value = match normalized_value {
+            Ok(None) => CowSpan::Borrowed(default.span.clone()),
+            Ok(Some(value)) => CowSpan::Owned(value),
+            Err(e) => {
+                recover(options, statistics, e, default.span.clone())?;
+                continue;
+            }
+        };
+        let (prefix, local_name) = split_qname(&decl.name, &decl.name_span);
+        attributes.push(PendingAttribute {
+            qname: &input[decl.name_span.0.clone()],
+            prefix,
+            local_name,
+            normalized_value,
+            specified: AttrSpecified::DefaultedFromDTD,
+            attribute_type_and_references: Some(UnknownOr::Known(
+                decl.attr_type.to_attr_type_and_references(),
+            )),
+        });
+    }
+    Ok(attributes)
+}
+
+/// Records the references in `raw`, character data or an attribute value,
+/// to entities other than the predefined ones, and the `expansions` made to
+/// replace them, the references in replacement texts included.
+fn note_entity_references(statistics: &mut InfoSetStatistics, raw: &str, expansions: usize) {
+    if entity_references(raw).any(|(_, name)| dtd::predefined_entity(name).is_none()) {
+        statistics.has_nonstandard_entity_reference = true;
+    }
+    statistics.entity_expansions += expansions;
+}
+
+/// Where an element tree is placed: the parent of its root element, and the
+/// namespaces and base URI in scope there.
+pub(crate) struct TreeContext {
+    pub(crate) parent: ElementParentInfoItem,
+    pub(crate) namespace_scope: NamespaceScope,
+    pub(crate) base_uri: Option<UriSpan>,
+}
+
+impl TreeContext {
+    /// The context of the document element.
+    pub(crate) fn document(
+        repo: &mut InfoSetData,
+        doc_info_item: DocInfoItem,
+        base_uri: Option<UriSpan>,
+    ) -> Self {
+        let namespace_atom = repo.atoms.intern(XML_NAMESPACE);
+        let xml_namespace = NSInfoItem::new(
+            Some(CowSpan::Static("xml")),
+            CowSpan::Static(XML_NAMESPACE),
+            namespace_atom,
+            repo,
+        );
+        let namespace_scope = repo.push_namespace_scope(None, [(Some("xml"), Some(xml_namespace))]);
+        TreeContext {
+            parent: ElementParentInfoItem::Doc(doc_info_item),
+            namespace_scope,
+            base_uri,
+        }
+    }
+}
+
+/// Parses an element and its content, returning the element without
+/// appending it to the parent given by `context`.
+pub(crate) fn parse_element_tree<'a, 't: 'a>(
+    repo: &mut InfoSetData,
+    input: &'a str,
+    tokens: &mut Peekable<XmlTokenizer<'t>>,
+    context: TreeContext,
+    options: &mut ParseOptions<'_>,
+    statistics: &mut InfoSetStatistics,
 ) -> Result<ElementInfoItem, ParseError> {
     struct ParseStackEntry<'a> {
         element_info_item: ElementInfoItem,
+        start: usize,
         prefix: XmlStrSpan<'a>,
         local_name: XmlStrSpan<'a>,
+        namespace_scope: NamespaceScope,
+        is_element_content: Option<bool>,
+        base_uri: Option<UriSpan>,
     }
-    let mut parse_stack: Vec<ParseStackEntry<'_>> = Vec::new();
+    impl ParseStackEntry<'_> {
+        /// Records the span of the element, which ends at `end`.
+        fn close(&self, repo: &mut InfoSetData, end: usize) {
+            self.element_info_item
+                .set_source_span(Some(Span(self.start..end)), repo);
+        }
+    }
+    let mut parse_stack: Vec<ParseStackEntry<'a>> = Vec::new();
     let mut root = None;
     enum ParseState {
         Initial,
@@ -195,61 +1602,89 @@ fn parse_element_tree(
         Done,
     }
     let mut parse_state = ParseState::Initial;
+    let mut pending_text = PendingText::default();
     'parse_elem_tree: loop {
         let next_token = match tokens.next() {
-            None => return Err(ParseError::UnexpectedEOF),
-            Some(Err(e)) => return Err(ParseError::TokenError(e)),
             Some(Ok(t)) => t,
+            end => {
+                let (error, span) = premature_end(input, end);
+                let end = span.0.end;
+                recover(options, statistics, error, span)?;
+                if let Some(entry) = parse_stack.last() {
+                    pending_text.flush(
+                        repo,
+                        input,
+                        entry.element_info_item,
+                        entry.is_element_content,
+                    );
+                }
+                // The elements left open are closed here.
+                for entry in parse_stack.drain(..) {
+                    entry.close(repo, end);
+                    repo.recovered_elements.push(entry.element_info_item);
+                }
+                parse_state = ParseState::Done;
+                break 'parse_elem_tree;
+            }
         };
+        if !matches!(next_token, XmlToken::Text { .. } | XmlToken::Cdata { .. }) {
+            if let Some(entry) = parse_stack.last() {
+                pending_text.flush(
+                    repo,
+                    input,
+                    entry.element_info_item,
+                    entry.is_element_content,
+                );
+            }
+        }
         match next_token {
             XmlToken::ElementStart {
                 prefix: element_prefix,
                 local: element_local,
-                ..
+                span: element_span,
             } => {
                 let self_close;
-                let mut non_namespace_attrs = vec![];
-                let mut namespace_attributes = vec![];
-                let mut default_namespace_attribute = None;
+                let start_tag_end;
+                let mut input_ended = false;
+                let mut specified_attributes = vec![];
                 'parse_attr_list: loop {
                     use xmlparser::ElementEnd;
                     let iterate_item = tokens.next();
                     let iterate_token = match iterate_item {
-                        None => return Err(ParseError::UnexpectedEOF),
-                        Some(Err(e)) => return Err(ParseError::TokenError(e)),
                         Some(Ok(XmlToken::Attribute {
                             prefix: attr_prefix,
                             local: attr_local,
                             value: attr_value,
                             ..
                         })) => {
-                            if attr_prefix.is_empty() && attr_local.as_str() == "xmlns" {
-                                if default_namespace_attribute.is_some() {
-                                    return Err(ParseError::DuplicateNSAttribute);
-                                }
-                                default_namespace_attribute = Some((attr_local, attr_value));
-                            } else if attr_prefix.as_str() == "xmlns" {
-                                let local_str = attr_local.as_str();
-                                namespace_attributes.push((attr_local, attr_value));
-                            } else {
-                                non_namespace_attrs.push((attr_prefix, attr_local, attr_value));
-                            }
+                            specified_attributes.push((attr_prefix, attr_local, attr_value));
                         }
                         Some(Ok(XmlToken::ElementEnd {
                             end: ElementEnd::Open,
-                            ..
+                            span,
                         })) => {
                             self_close = false;
+                            start_tag_end = span.end();
                             break 'parse_attr_list;
                         }
                         Some(Ok(XmlToken::ElementEnd {
                             end: ElementEnd::Empty,
-                            ..
+                            span,
                         })) => {
                             self_close = true;
+                            start_tag_end = span.end();
+                            break 'parse_attr_list;
+                        }
+                        end => {
+                            let (error, span) = premature_end(input, end);
+                            start_tag_end = span.0.end;
+                            recover(options, statistics, error, span)?;
+                            // The element is created with the attributes read
+                            // so far, and closed with the others below.
+                            self_close = false;
+                            input_ended = true;
                             break 'parse_attr_list;
                         }
-                        _ => return Err(ParseError::UnexpectedToken),
                     };
                 }
                 let parent = if let Some(e) = parse_stack.last() {
@@ -257,22 +1692,206 @@ fn parse_element_tree(
                 } else {
                     None
                 };
+                let (parent_namespace_scope, parent_base_uri) = match parse_stack.last() {
+                    Some(e) => (e.namespace_scope, e.base_uri.clone()),
+                    None => (context.namespace_scope, context.base_uri.clone()),
+                };
+                // Skip the leading `<` of the start tag.
+                let element_qname = &element_span.as_str()[1..];
+                for &(_, _, attr_value) in &specified_attributes {
+                    note_external_entity_references(&mut repo.dtd_declarations, attr_value);
+                }
+                let attributes = collect_attributes(
+                    &repo.dtd_declarations,
+                    input,
+                    element_qname,
+                    specified_attributes,
+                    options,
+                    statistics,
+                )?;
+                let (namespace_declarations, mut attributes): (Vec<_>, Vec<_>) =
+                    attributes.into_iter().partition(|attribute| {
+                        attribute.qname == "xmlns" || attribute.qname.starts_with("xmlns:")
+                    });
+
+                let mut bindings = Vec::with_capacity(namespace_declarations.len());
+                for attribute in &namespace_declarations {
+                    let prefix = attribute
+                        .prefix
+                        .as_ref()
+                        .map(|_| &attribute.qname["xmlns:".len()..]);
+                    let namespace_name = input_str(input, &attribute.normalized_value);
+                    let is_reserved_namespace =
+                        namespace_name == XML_NAMESPACE || namespace_name == XMLNS_NAMESPACE;
+                    let is_invalid = match prefix {
+                        Some("xml") if namespace_name == XML_NAMESPACE => continue,
+                        Some("xml") | Some("xmlns") => true,
+                        // Namespaces in XML 1.1 allow prefixes to be
+                        // undeclared.
+                        Some(_) if namespace_name.is_empty() && !repo.dtd_declarations.xml_1_1 => {
+                            true
+                        }
+                        _ => is_reserved_namespace,
+                    };
+                    if is_invalid {
+                        let span = attribute.local_name.clone();
+                        recover(options, statistics, ParseError::InvalidNSAttribute, span)?;
+                        continue;
+                    }
+                    if namespace_name.is_empty() {
+                        // `xmlns=""` undeclares the default namespace, and
+                        // `xmlns:p=""` the prefix `p`.
+                        bindings.push((prefix, None));
+                        continue;
+                    }
+                    let namespace_atom = repo.atoms.intern(namespace_name);
+                    let ns_info_item = NSInfoItem::new(
+                        attribute
+                            .prefix
+                            .as_ref()
+                            .map(|_| CowSpan::Borrowed(attribute.local_name.clone())),
+                        attribute.normalized_value.clone(),
+                        namespace_atom,
+                        repo,
+                    );
+                    bindings.push((prefix, Some(ns_info_item)));
+                }
+                let namespace_scope = if bindings.is_empty() {
+                    parent_namespace_scope
+                } else {
+                    repo.push_namespace_scope(Some(parent_namespace_scope), bindings)
+                };
+                let resolve_prefix =
+                    |prefix: Option<&str>| repo.resolve_prefix(namespace_scope, prefix);
+                let element_prefix_str = Some(element_prefix.as_str()).filter(|p| !p.is_empty());
+                let element_namespace = match resolve_prefix(element_prefix_str) {
+                    Some(ns_info_item) => Some(ns_info_item),
+                    None if element_prefix_str.is_none() => None,
+                    None => {
+                        let span = Span::from_xml_strspan(element_prefix);
+                        recover(options, statistics, ParseError::UndeclaredNSPrefix, span)?;
+                        None
+                    }
+                };
+                let mut attribute_namespaces = Vec::with_capacity(attributes.len());
+                for attribute in &attributes {
+                    let namespace_name = match &attribute.prefix {
+                        None => None,
+                        Some(prefix_span) => {
+                            let (prefix, _) = attribute.qname.split_once(':').unwrap();
+                            match resolve_prefix(Some(prefix)) {
+                                Some(ns_info_item) => Some(ns_info_item),
+                                None => {
+                                    let span = prefix_span.clone();
+                                    recover(
+                                        options,
+                                        statistics,
+                                        ParseError::UndeclaredNSPrefix,
+                                        span,
+                                    )?;
+                                    None
+                                }
+                            }
+                        }
+                    };
+                    attribute_namespaces.push(namespace_name);
+                }
+                let mut attribute_names = Vec::with_capacity(attributes.len());
+                for (attribute, ns_info_item) in attributes.iter().zip(&attribute_namespaces) {
+                    attribute_names.push(ExpandedName {
+                        namespace_name: ns_info_item
+                            .map(|ns_info_item| ns_info_item.namespace_atom(repo)),
+                        local_name: repo.atoms.intern(&input[attribute.local_name.0.clone()]),
+                    });
+                }
+                // No two attributes may have the same expanded name.
+                let mut i = 0;
+                while i < attributes.len() {
+                    if attribute_names[..i].contains(&attribute_names[i]) {
+                        let span = attributes[i].local_name.clone();
+                        recover(options, statistics, ParseError::DuplicateAttribute, span)?;
+                        attributes.remove(i);
+                        attribute_namespaces.remove(i);
+                        attribute_names.remove(i);
+                    } else {
+                        i += 1;
+                    }
+                }
+
+                let base_uri = match attributes
+                    .iter()
+                    .find(|attribute| attribute.qname == "xml:base")
+                {
+                    Some(xml_base) => Some(match &parent_base_uri {
+                        Some(parent_base_uri) => UriSpan(CowSpan::Owned(uri::resolve(
+                            input_str(input, &parent_base_uri.0),
+                            input_str(input, &xml_base.normalized_value),
+                        ))),
+                        None => UriSpan(xml_base.normalized_value.clone()),
+                    }),
+                    None => parent_base_uri,
+                };
+                let expanded_name = ExpandedName {
+                    namespace_name: element_namespace
+                        .map(|ns_info_item| ns_info_item.namespace_atom(repo)),
+                    local_name: repo.atoms.intern(element_local.as_str()),
+                };
                 let element_info_item = ElementInfoItem::new(
-                    fixme_impl!(None),
                     Span::from_xml_strspan(element_local),
                     Option::<Span>::from_xml_strspan(element_prefix),
-                    fixme_impl!(Vec::new()),
-                    fixme_impl!(Vec::new()),
-                    fixme_impl!(Vec::new()),
-                    fixme_impl!(Vec::new()),
-                    fixme_impl!(None),
+                    expanded_name,
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                    namespace_scope,
+                    base_uri.clone(),
+                    None,
                     if let Some(e) = parent {
                         ElementParentInfoItem::Element(e)
                     } else {
-                        ElementParentInfoItem::Doc(doc_info_item)
+                        context.parent
                     },
                     repo,
                 );
+                let namespace_attributes = namespace_declarations
+                    .into_iter()
+                    .map(|attribute| {
+                        let expanded_name = repo.atoms.expanded_name(
+                            Some(XMLNS_NAMESPACE),
+                            &input[attribute.local_name.0.clone()],
+                        );
+                        AttributeInfoItem::new(
+                            Some(CowSpan::Static(XMLNS_NAMESPACE)),
+                            attribute.local_name,
+                            attribute.prefix,
+                            expanded_name,
+                            attribute.normalized_value,
+                            attribute.specified,
+                            attribute.attribute_type_and_references,
+                            element_info_item,
+                            repo,
+                        )
+                    })
+                    .collect();
+                element_info_item.set_namespace_attributes(namespace_attributes, repo);
+                let attributes = attributes
+                    .into_iter()
+                    .zip(attribute_namespaces.into_iter().zip(attribute_names))
+                    .map(|(attribute, (ns_info_item, expanded_name))| {
+                        AttributeInfoItem::new(
+                            ns_info_item.map(|ns_info_item| ns_info_item.namespace_name(repo)),
+                            attribute.local_name,
+                            attribute.prefix,
+                            expanded_name,
+                            attribute.normalized_value,
+                            attribute.specified,
+                            attribute.attribute_type_and_references,
+                            element_info_item,
+                            repo,
+                        )
+                    })
+                    .collect();
+                element_info_item.set_attributes(attributes, repo);
                 if let Some(parent) = parent {
                     append_to_element_as_child(repo, parent, element_info_item);
                 } else {
@@ -284,11 +1903,29 @@ fn parse_element_tree(
                 if !self_close {
                     parse_stack.push(ParseStackEntry {
                         element_info_item,
+                        start: element_span.start(),
                         prefix: element_prefix,
                         local_name: element_local,
+                        namespace_scope,
+                        is_element_content: repo
+                            .dtd_declarations
+                            .element_decls
+                            .get(element_qname)
+                            .map(|decl| matches!(decl.content_spec, dtd::ContentSpec::Children(_))),
+                        base_uri,
                     });
                     parse_state = ParseState::AfterDescent;
+                    if input_ended {
+                        for entry in parse_stack.drain(..) {
+                            entry.close(repo, start_tag_end);
+                            repo.recovered_elements.push(entry.element_info_item);
+                        }
+                        parse_state = ParseState::Done;
+                        break 'parse_elem_tree;
+                    }
                 } else {
+                    element_info_item
+                        .set_source_span(Some(Span(element_span.start()..start_tag_end)), repo);
                     if parent.is_some() {
                         parse_state = ParseState::AfterAppend;
                     } else {
@@ -297,16 +1934,18 @@ fn parse_element_tree(
                     }
                 }
             }
-            XmlToken::Comment { text, .. } => {
+            XmlToken::Comment { text, span } => {
                 if !matches!(
                     parse_state,
                     ParseState::AfterDescent | ParseState::AfterAppend | ParseState::AfterUnwind
                 ) {
-                    return Err(ParseError::UnexpectedToken);
+                    let span = Span::from_xml_strspan(span);
+                    recover(options, statistics, ParseError::UnexpectedToken, span)?;
+                    continue 'parse_elem_tree;
                 }
                 let parent = parse_stack.last().unwrap().element_info_item;
                 let comment_info_item = CommentInfoItem::new(
-                    Span::from_xml_strspan(text),
+                    normalized_strspan(text, repo.dtd_declarations.xml_1_1),
                     CommentParentInfoItem::Element(parent),
                     repo,
                 );
@@ -314,59 +1953,125 @@ fn parse_element_tree(
                 parse_state = ParseState::AfterAppend;
             }
             XmlToken::ProcessingInstruction {
-                target, content, ..
+                target,
+                content,
+                span,
             } => {
                 if !matches!(
                     parse_state,
                     ParseState::AfterDescent | ParseState::AfterAppend | ParseState::AfterUnwind
                 ) {
-                    return Err(ParseError::UnexpectedToken);
+                    let span = Span::from_xml_strspan(span);
+                    recover(options, statistics, ParseError::UnexpectedToken, span)?;
+                    continue 'parse_elem_tree;
                 }
 
-                let parent = parse_stack.last().unwrap().element_info_item;
+                let entry = parse_stack.last().unwrap();
+                let parent = entry.element_info_item;
+                let xml_1_1 = repo.dtd_declarations.xml_1_1;
                 let pi_info_item = PIInfoItem::new(
                     Span::from_xml_strspan(target),
-                    content.map(Span::from_xml_strspan),
-                    fixme_impl!(None),
-                    fixme_impl!(None),
+                    content.map(|content| normalized_strspan(content, xml_1_1)),
+                    entry.base_uri.clone(),
+                    None,
                     PIParentInfoItem::Element(parent),
                     repo,
                 );
                 append_to_element_as_child(repo, parent, pi_info_item);
                 parse_state = ParseState::AfterAppend;
             }
-            XmlToken::ElementEnd { end, .. } => {
+            XmlToken::ElementEnd { end, span } => {
                 use xmlparser::ElementEnd;
+                let span = Span::from_xml_strspan(span);
                 if !matches!(
                     parse_state,
                     ParseState::AfterDescent | ParseState::AfterAppend | ParseState::AfterUnwind
                 ) {
-                    return Err(ParseError::UnexpectedToken);
+                    recover(options, statistics, ParseError::UnexpectedToken, span)?;
+                    continue 'parse_elem_tree;
                 }
+                let (end_tag_start, end_tag_end) = (span.0.start, span.0.end);
                 let (prefix, local_name) = match end {
                     ElementEnd::Close(prefix, local_name) => (prefix, local_name),
-                    _ => return Err(ParseError::UnexpectedToken),
+                    _ => {
+                        recover(options, statistics, ParseError::UnexpectedToken, span)?;
+                        continue 'parse_elem_tree;
+                    }
                 };
-                match parse_stack.pop() {
-                    Some(entry) if entry.prefix == prefix && entry.local_name == local_name => {
-                        // do nothing.
+                let open_position = parse_stack.iter().rposition(|entry| {
+                    entry.prefix.as_str() == prefix.as_str()
+                        && entry.local_name.as_str() == local_name.as_str()
+                });
+                match open_position {
+                    Some(position) if position + 1 == parse_stack.len() => {}
+                    Some(position) => {
+                        recover(options, statistics, ParseError::UnexpectedToken, span)?;
+                        // The elements left open inside are closed as well.
+                        for entry in parse_stack.drain(position + 1..) {
+                            entry.close(repo, end_tag_start);
+                            repo.recovered_elements.push(entry.element_info_item);
+                        }
+                    }
+                    None => {
+                        // A stray end tag is skipped.
+                        recover(options, statistics, ParseError::UnexpectedToken, span)?;
+                        continue 'parse_elem_tree;
                     }
-                    _ => return Err(ParseError::UnexpectedToken),
                 }
+                parse_stack.pop().unwrap().close(repo, end_tag_end);
                 if parse_stack.is_empty() {
                     parse_state = ParseState::Done;
                     break 'parse_elem_tree;
                 }
                 parse_state = ParseState::AfterUnwind;
             }
-            _ => return Err(ParseError::UnexpectedToken),
+            XmlToken::Text { text } | XmlToken::Cdata { text, .. } => {
+                if !matches!(
+                    parse_state,
+                    ParseState::AfterDescent | ParseState::AfterAppend | ParseState::AfterUnwind
+                ) {
+                    let span = Span::from_xml_strspan(text);
+                    recover(options, statistics, ParseError::UnexpectedToken, span)?;
+                    continue 'parse_elem_tree;
+                }
+                let entry = parse_stack.last().unwrap();
+                if matches!(next_token, XmlToken::Cdata { .. }) {
+                    statistics.has_cdata = true;
+                    pending_text.push_chars(
+                        input,
+                        Span::from_xml_strspan(text),
+                        repo.dtd_declarations.xml_1_1,
+                    );
+                } else {
+                    append_text(
+                        repo,
+                        input,
+                        entry.element_info_item,
+                        entry.is_element_content,
+                        text,
+                        &mut pending_text,
+                        options,
+                        statistics,
+                    )?;
+                }
+                parse_state = ParseState::AfterAppend;
+            }
+            token => {
+                recover(
+                    options,
+                    statistics,
+                    ParseError::UnexpectedToken,
+                    token_span(&token),
+                )?;
+            }
         }
     }
     debug_assert!(matches!(parse_state, ParseState::Done));
-    Ok(root.unwrap())
+    // When recovering, the input may end before any element was read.
+    root.ok_or(ParseError::UnexpectedEOF)
 }
 
-fn parse_version(version: &str) -> Version {
+pub(crate) fn parse_version(version: &str) -> Version {
     if version == "1.0" {
         Version::Version1_0
     } else if version == "1.1" {
@@ -376,7 +2081,7 @@ fn parse_version(version: &str) -> Version {
     }
 }
 
-fn parse_encoding_scheme(encoding_scheme: &str) -> EncodingScheme {
+pub(crate) fn parse_encoding_scheme(encoding_scheme: &str) -> EncodingScheme {
     if encoding_scheme == "UTF-8" {
         EncodingScheme::Utf8
     } else {
@@ -0,0 +1,24 @@
+use thiserror::Error;
+
+/// Reads the content of external entities, such as the external DTD subset
+/// and external parsed entities.
+pub trait EntityResolver {
+    /// Returns the bytes of the entity with the given identifiers.
+    ///
+    /// `base_uri` is the base URI of the entity containing the declaration,
+    /// against which a relative `system_id` should be resolved.
+    fn resolve_entity(
+        &mut self,
+        public_id: Option<&str>,
+        system_id: &str,
+        base_uri: Option<&str>,
+    ) -> Result<Vec<u8>, ResolveError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ResolveError {
+    #[error("no entity found for system identifier {0:?}")]
+    NotFound(String),
+    #[error("failed to read entity {system_id:?}: {message}")]
+    ReadFailed { system_id: String, message: String },
+}
@@ -0,0 +1,161 @@
+use super::*;
+
+/// The namespaces in scope for an element.
+///
+/// Scopes form a chain through the ancestors of an element, each link holding
+/// the namespace declarations of one element, so elements that declare no
+/// namespaces share the scope of their parent. Each link also keeps the
+/// namespaces in scope, so listing them does not walk the chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NamespaceScope(u32);
+
+struct NamespaceScopeNode {
+    parent: Option<NamespaceScope>,
+    /// Prefixes bound in this scope, `None` being the default namespace. A
+    /// prefix without a namespace is undeclared.
+    bindings: Vec<(Option<Atom>, Option<NSInfoItem>)>,
+    /// The namespaces in scope and their prefixes in declaration order,
+    /// outermost first.
+    in_scope: Vec<(Option<Atom>, NSInfoItem)>,
+}
+
+#[derive(Default)]
+pub(crate) struct NamespaceScopes {
+    nodes: Vec<NamespaceScopeNode>,
+}
+
+impl NamespaceScopes {
+    /// Returns the in-scope namespaces and their prefixes in declaration
+    /// order, outermost first.
+    pub(crate) fn bindings(&self, scope: NamespaceScope) -> &[(Option<Atom>, NSInfoItem)] {
+        &self.nodes[scope.0 as usize].in_scope
+    }
+
+    /// Returns the namespace bound to `prefix` in `scope`, looking through
+    /// the enclosing scopes from the innermost one.
+    fn resolve(&self, scope: NamespaceScope, prefix: Option<Atom>) -> Option<NSInfoItem> {
+        let mut next = Some(scope);
+        while let Some(scope) = next {
+            let node = &self.nodes[scope.0 as usize];
+            // A later binding of the same prefix wins.
+            let binding = node.bindings.iter().rev().find(|&&(p, _)| p == prefix);
+            if let Some(&(_, ns_info_item)) = binding {
+                return ns_info_item;
+            }
+            next = node.parent;
+        }
+        None
+    }
+}
+
+impl InfoSetData {
+    /// Adds a scope binding `bindings` on top of `parent`.
+    pub(crate) fn push_namespace_scope<'p>(
+        &mut self,
+        parent: Option<NamespaceScope>,
+        bindings: impl IntoIterator<Item = (Option<&'p str>, Option<NSInfoItem>)>,
+    ) -> NamespaceScope {
+        let bindings = bindings
+            .into_iter()
+            .map(|(prefix, ns_info_item)| {
+                (prefix.map(|prefix| self.atoms.intern(prefix)), ns_info_item)
+            })
+            .collect();
+        self.push_namespace_bindings(parent, bindings)
+    }
+
+    /// Adds a scope binding `bindings`, with interned prefixes, on top of
+    /// `parent`.
+    pub(crate) fn push_namespace_bindings(
+        &mut self,
+        parent: Option<NamespaceScope>,
+        bindings: Vec<(Option<Atom>, Option<NSInfoItem>)>,
+    ) -> NamespaceScope {
+        let scopes = &mut self.namespace_scopes;
+        let mut in_scope = match parent {
+            Some(parent) => scopes.bindings(parent).to_vec(),
+            None => Vec::new(),
+        };
+        for &(prefix, ns_info_item) in &bindings {
+            in_scope.retain(|&(p, _)| p != prefix);
+            if let Some(ns_info_item) = ns_info_item {
+                in_scope.push((prefix, ns_info_item));
+            }
+        }
+        let scope = NamespaceScope(scopes.nodes.len() as u32);
+        scopes.nodes.push(NamespaceScopeNode {
+            parent,
+            bindings,
+            in_scope,
+        });
+        scope
+    }
+
+    /// Returns the namespace bound to `prefix` in `scope`, `None` being the
+    /// default namespace.
+    pub(crate) fn resolve_prefix(
+        &self,
+        scope: NamespaceScope,
+        prefix: Option<&str>,
+    ) -> Option<NSInfoItem> {
+        let prefix = match prefix {
+            // A prefix that was never interned is bound nowhere.
+            Some(prefix) => Some(self.atoms.get(prefix)?),
+            None => None,
+        };
+        self.namespace_scopes.resolve(scope, prefix)
+    }
+
+    /// Returns how many of the leading `namespaces`, given as interned prefix
+    /// and namespace name, `parent` has in scope in the same order, and the
+    /// prefixes bound in `parent` that are missing from `namespaces`.
+    ///
+    /// A scope holding exactly `namespaces` is `parent` with the rest of
+    /// `namespaces` bound and the missing prefixes undeclared.
+    pub(crate) fn inherited_namespaces(
+        &self,
+        parent: NamespaceScope,
+        namespaces: &[(Option<Atom>, Atom)],
+    ) -> (usize, Vec<Option<Atom>>) {
+        let inherited = self.namespace_scopes.bindings(parent);
+        let shared = namespaces
+            .iter()
+            .zip(inherited)
+            .take_while(|&(&(prefix, namespace_atom), &(p, ns_info_item))| {
+                prefix == p && namespace_atom == ns_info_item.namespace_atom(self)
+            })
+            .count();
+        let missing = inherited
+            .iter()
+            .map(|&(prefix, _)| prefix)
+            .filter(|&prefix| !namespaces.iter().any(|&(p, _)| p == prefix))
+            .collect();
+        (shared, missing)
+    }
+}
+
+impl ElementInfoItem {
+    /// The namespaces in scope for the element, including those declared by
+    /// its ancestors.
+    ///
+    /// The list is copied from the scope of the element, so its cost grows
+    /// with the number of namespaces in scope; use
+    /// [`ElementInfoItem::namespace_for_prefix`] to look up a single prefix.
+    pub fn in_scope_namespaces(self, repo: &InfoSetData) -> Vec<NSInfoItem> {
+        let bindings = repo.namespace_scopes.bindings(self.namespace_scope(repo));
+        bindings
+            .iter()
+            .map(|&(_, ns_info_item)| ns_info_item)
+            .collect()
+    }
+
+    /// The namespace bound to `prefix` for the element, `None` being the
+    /// default namespace, without collecting the other in-scope namespaces.
+    pub fn namespace_for_prefix(
+        self,
+        repo: &InfoSetData,
+        prefix: Option<&str>,
+    ) -> Option<NSInfoItem> {
+        repo.resolve_prefix(self.namespace_scope(repo), prefix)
+    }
+}
@@ -0,0 +1,928 @@
+//! Building an [`InfoSet`] from Rust values with `serde`.
+//!
+//! This follows the conventions of [`de`](crate::de): fields named `@name`
+//! become attributes, `$value` becomes text content (or child elements named
+//! after enum variants), and every other field becomes a child element.
+//! Sequences repeat the element of their field, or become a space separated
+//! list when serialized as an attribute or as `$value`.
+//!
+//! Names of the form `prefix:local` are resolved with the prefix mappings of
+//! the [`SerializerConfig`], and unprefixed element names are placed in its
+//! default namespace. Names of the form `{namespace}local`, as read by the
+//! deserializer, take the prefix the configuration maps to `namespace`, or
+//! none for the default namespace and for `{}local`. All configured mappings
+//! are declared on the root element.
+
+use super::builder::InfoSetBuilder;
+use super::utils::{classify_qname, QNameCategory};
+use super::*;
+use core::fmt;
+use serde::ser::{self, Impossible, Serialize};
+
+#[derive(Debug, Error)]
+pub enum SerError {
+    #[error("{0}")]
+    Custom(String),
+    #[error("{0} cannot be serialized here")]
+    Unsupported(&'static str),
+    #[error("invalid XML name {0:?}")]
+    InvalidName(String),
+    #[error("undeclared namespace prefix {0:?}")]
+    UndeclaredPrefix(String),
+    #[error("no prefix is bound to namespace {0:?}")]
+    UnboundNamespace(String),
+    #[error("value did not serialize to a single root element")]
+    NoRootElement,
+}
+
+impl ser::Error for SerError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerError::Custom(msg.to_string())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SerializerConfig {
+    /// Name of the document element. Defaults to the name of the serialized
+    /// struct or enum variant.
+    pub root_name: Option<String>,
+    pub default_namespace: Option<String>,
+    /// Pairs of prefix and namespace name.
+    pub namespaces: Vec<(String, String)>,
+}
+
+pub fn to_infoset<T: Serialize + ?Sized>(
+    value: &T,
+    config: &SerializerConfig,
+) -> Result<InfoSet<'static>, SerError> {
+    let mut holder = ElementNode::new(String::new());
+    let root_name = config.root_name.as_deref().unwrap_or("");
+    value.serialize(FieldSerializer {
+        parent: &mut holder,
+        name: root_name,
+    })?;
+    let root = match holder.children.pop() {
+        Some(Content::Element(root)) if holder.children.is_empty() => root,
+        _ => return Err(SerError::NoRootElement),
+    };
+    Materializer::build(config, root)
+}
+
+enum Content {
+    Text(String),
+    Element(ElementNode),
+}
+
+struct ElementNode {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Content>,
+}
+
+impl ElementNode {
+    fn new(name: String) -> Self {
+        ElementNode {
+            name,
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn push_text(&mut self, text: String) {
+        if let Some(Content::Text(last)) = self.children.last_mut() {
+            last.push(' ');
+            last.push_str(&text);
+        } else {
+            self.children.push(Content::Text(text));
+        }
+    }
+}
+
+macro_rules! serialize_display {
+    ($($method:ident($ty:ty),)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, SerError> {
+                self.serialize_text(v.to_string())
+            }
+        )*
+    };
+}
+
+/// Serializes attribute values, map keys and list items.
+struct TextSerializer;
+
+impl TextSerializer {
+    fn serialize_text(self, text: String) -> Result<String, SerError> {
+        Ok(text)
+    }
+}
+
+impl ser::Serializer for TextSerializer {
+    type Ok = String;
+    type Error = SerError;
+    type SerializeSeq = Impossible<String, SerError>;
+    type SerializeTuple = Impossible<String, SerError>;
+    type SerializeTupleStruct = Impossible<String, SerError>;
+    type SerializeTupleVariant = Impossible<String, SerError>;
+    type SerializeMap = Impossible<String, SerError>;
+    type SerializeStruct = Impossible<String, SerError>;
+    type SerializeStructVariant = Impossible<String, SerError>;
+
+    serialize_display! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, SerError> {
+        Err(SerError::Unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<String, SerError> {
+        Ok(String::new())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, SerError> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, SerError> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, SerError> {
+        Ok(variant.to_owned())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> Result<String, SerError> {
+        Err(SerError::Unsupported("newtype variant"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerError> {
+        Err(SerError::Unsupported("nested sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerError> {
+        Err(SerError::Unsupported("nested tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        Err(SerError::Unsupported("nested tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        Err(SerError::Unsupported("tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerError> {
+        Err(SerError::Unsupported("map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerError> {
+        Err(SerError::Unsupported("struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        Err(SerError::Unsupported("struct variant"))
+    }
+}
+
+/// Serializes a value as the field `name` of the element `parent`.
+///
+/// An empty name stands for the document element, whose name is taken from
+/// the serialized type.
+struct FieldSerializer<'a> {
+    parent: &'a mut ElementNode,
+    name: &'a str,
+}
+
+impl<'a> FieldSerializer<'a> {
+    fn is_attribute(&self) -> bool {
+        self.name.starts_with('@')
+    }
+
+    fn is_content(&self) -> bool {
+        self.name == "$value" || self.name.is_empty()
+    }
+
+    fn element_name(&self, type_name: &str) -> String {
+        if self.is_content() {
+            type_name.to_owned()
+        } else {
+            self.name.to_owned()
+        }
+    }
+
+    fn serialize_text(self, text: String) -> Result<(), SerError> {
+        if let Some(attribute_name) = self.name.strip_prefix('@') {
+            self.parent
+                .attributes
+                .push((attribute_name.to_owned(), text));
+        } else if self.name == "$value" {
+            self.parent.push_text(text);
+        } else {
+            let mut node = ElementNode::new(self.name.to_owned());
+            node.children.push(Content::Text(text));
+            self.parent.children.push(Content::Element(node));
+        }
+        Ok(())
+    }
+
+    fn variant_node(self, variant: &str) -> Result<NodeSerializer<'a>, SerError> {
+        if self.is_attribute() {
+            return Err(SerError::Unsupported(
+                "enum variant with fields in an attribute",
+            ));
+        }
+        let wrapper = if self.is_content() {
+            None
+        } else {
+            Some(self.name.to_owned())
+        };
+        Ok(NodeSerializer {
+            parent: self.parent,
+            node: ElementNode::new(variant.to_owned()),
+            wrapper,
+            pending_key: None,
+        })
+    }
+}
+
+impl<'a> ser::Serializer for FieldSerializer<'a> {
+    type Ok = ();
+    type Error = SerError;
+    type SerializeSeq = FieldSeqSerializer<'a>;
+    type SerializeTuple = FieldSeqSerializer<'a>;
+    type SerializeTupleStruct = FieldSeqSerializer<'a>;
+    type SerializeTupleVariant = NodeSerializer<'a>;
+    type SerializeMap = NodeSerializer<'a>;
+    type SerializeStruct = NodeSerializer<'a>;
+    type SerializeStructVariant = NodeSerializer<'a>;
+
+    serialize_display! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), SerError> {
+        Err(SerError::Unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<(), SerError> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerError> {
+        if self.is_attribute() {
+            self.serialize_text(String::new())
+        } else if self.is_content() {
+            Ok(())
+        } else {
+            let node = ElementNode::new(self.name.to_owned());
+            self.parent.children.push(Content::Element(node));
+            Ok(())
+        }
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), SerError> {
+        if self.is_attribute() {
+            return self.serialize_text(String::new());
+        }
+        let node = ElementNode::new(self.element_name(name));
+        self.parent.children.push(Content::Element(node));
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerError> {
+        if self.is_content() {
+            let node = ElementNode::new(variant.to_owned());
+            self.parent.children.push(Content::Element(node));
+            Ok(())
+        } else {
+            self.serialize_text(variant.to_owned())
+        }
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        if self.is_attribute() {
+            return Err(SerError::Unsupported("newtype variant in an attribute"));
+        }
+        if self.is_content() {
+            return value.serialize(FieldSerializer {
+                parent: self.parent,
+                name: variant,
+            });
+        }
+        let mut wrapper = ElementNode::new(self.name.to_owned());
+        value.serialize(FieldSerializer {
+            parent: &mut wrapper,
+            name: variant,
+        })?;
+        self.parent.children.push(Content::Element(wrapper));
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<FieldSeqSerializer<'a>, SerError> {
+        let texts = if self.is_attribute() {
+            Some(Vec::new())
+        } else {
+            None
+        };
+        Ok(FieldSeqSerializer {
+            parent: self.parent,
+            name: self.name,
+            texts,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<FieldSeqSerializer<'a>, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<FieldSeqSerializer<'a>, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<NodeSerializer<'a>, SerError> {
+        self.variant_node(variant)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<NodeSerializer<'a>, SerError> {
+        if self.is_attribute() {
+            return Err(SerError::Unsupported("map in an attribute"));
+        }
+        Ok(NodeSerializer {
+            node: ElementNode::new(self.name.to_owned()),
+            parent: self.parent,
+            wrapper: None,
+            pending_key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<NodeSerializer<'a>, SerError> {
+        if self.is_attribute() {
+            return Err(SerError::Unsupported("struct in an attribute"));
+        }
+        Ok(NodeSerializer {
+            node: ElementNode::new(self.element_name(name)),
+            parent: self.parent,
+            wrapper: None,
+            pending_key: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<NodeSerializer<'a>, SerError> {
+        self.variant_node(variant)
+    }
+}
+
+/// Serializes the items of a sequence as repeated fields of the same name.
+struct FieldSeqSerializer<'a> {
+    parent: &'a mut ElementNode,
+    name: &'a str,
+    /// Collected items of a list valued attribute.
+    texts: Option<Vec<String>>,
+}
+
+impl FieldSeqSerializer<'_> {
+    fn item<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        match &mut self.texts {
+            Some(texts) => texts.push(value.serialize(TextSerializer)?),
+            None => value.serialize(FieldSerializer {
+                parent: &mut *self.parent,
+                name: self.name,
+            })?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), SerError> {
+        if let Some(texts) = self.texts {
+            FieldSerializer {
+                parent: self.parent,
+                name: self.name,
+            }
+            .serialize_text(texts.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for FieldSeqSerializer<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for FieldSeqSerializer<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for FieldSeqSerializer<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        self.finish()
+    }
+}
+
+/// Serializes the fields of a struct, map or enum variant into a new element.
+///
+/// Enum variants in a named field are wrapped into an element carrying the
+/// field name.
+struct NodeSerializer<'a> {
+    parent: &'a mut ElementNode,
+    node: ElementNode,
+    wrapper: Option<String>,
+    pending_key: Option<String>,
+}
+
+impl NodeSerializer<'_> {
+    fn field<T: Serialize + ?Sized>(&mut self, name: &str, value: &T) -> Result<(), SerError> {
+        value.serialize(FieldSerializer {
+            parent: &mut self.node,
+            name,
+        })
+    }
+
+    fn finish(self) -> Result<(), SerError> {
+        let node = match self.wrapper {
+            Some(wrapper) => {
+                let mut outer = ElementNode::new(wrapper);
+                outer.children.push(Content::Element(self.node));
+                outer
+            }
+            None => self.node,
+        };
+        self.parent.children.push(Content::Element(node));
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for NodeSerializer<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for NodeSerializer<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for NodeSerializer<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.field("$value", value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for NodeSerializer<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerError> {
+        self.pending_key = Some(key.serialize(TextSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        let key = self
+            .pending_key
+            .take()
+            .ok_or_else(|| SerError::Custom("map value serialized before its key".to_owned()))?;
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        self.finish()
+    }
+}
+
+/// Turns the serialized element tree into info items.
+struct Materializer<'c> {
+    builder: InfoSetBuilder,
+    config: &'c SerializerConfig,
+    namespace_scope: NamespaceScope,
+}
+
+impl<'c> Materializer<'c> {
+    fn build(
+        config: &'c SerializerConfig,
+        root: ElementNode,
+    ) -> Result<InfoSet<'static>, SerError> {
+        let mut builder = InfoSetBuilder::new();
+        let doc_info_item = DocInfoItem::new_not_yet_parsed(&mut builder.data);
+        let mut bindings = Vec::new();
+        for (prefix, namespace_name) in Self::declarations(config) {
+            let prefix_span = prefix.map(|prefix| builder.cow_span(prefix));
+            let namespace_atom = builder.data.atoms.intern(namespace_name);
+            let namespace_name = builder.cow_span(namespace_name);
+            let ns_info_item = NSInfoItem::new(
+                prefix_span,
+                namespace_name,
+                namespace_atom,
+                &mut builder.data,
+            );
+            bindings.push((prefix, Some(ns_info_item)));
+        }
+        let prefix = CowSpan::Static("xml");
+        let namespace_atom = builder.data.atoms.intern(XML_NAMESPACE);
+        let namespace_name = CowSpan::Static(XML_NAMESPACE);
+        let ns_info_item = NSInfoItem::new(
+            Some(prefix),
+            namespace_name,
+            namespace_atom,
+            &mut builder.data,
+        );
+        bindings.push((Some("xml"), Some(ns_info_item)));
+        // Every element has the namespaces declared on the root element.
+        let namespace_scope = builder.data.push_namespace_scope(None, bindings);
+        let mut materializer = Materializer {
+            builder,
+            config,
+            namespace_scope,
+        };
+        let root_element =
+            materializer.element(root, ElementParentInfoItem::Doc(doc_info_item), true)?;
+        let mut builder = materializer.builder;
+        doc_info_item.transition_to_parsed_from_not_yet_parsed(
+            repo::keyed!(version: Version::Version1_0),
+            repo::keyed!(character_encoding_scheme: None),
+            repo::keyed!(standalone: None),
+            repo::keyed!(document_element: root_element),
+            repo::keyed!(children: vec![DocChildInfoItem::Element(root_element)]),
+            repo::keyed!(notations: Some(Vec::new())),
+            repo::keyed!(unparsed_entities: Vec::new()),
+            repo::keyed!(base_uri: None),
+            repo::keyed!(all_declarations_processed: true),
+            &mut builder.data,
+        );
+        builder.data.doc_info_item = Some(doc_info_item);
+        Ok(builder.finish())
+    }
+
+    fn declarations(config: &SerializerConfig) -> Vec<(Option<&str>, &str)> {
+        let mut declarations = Vec::new();
+        if let Some(namespace_name) = &config.default_namespace {
+            declarations.push((None, namespace_name.as_str()));
+        }
+        for (prefix, namespace_name) in &config.namespaces {
+            declarations.push((Some(prefix.as_str()), namespace_name.as_str()));
+        }
+        declarations
+    }
+
+    fn split_name<'n>(&self, name: &'n str) -> Result<(Option<&'n str>, &'n str), SerError> {
+        match classify_qname(name) {
+            QNameCategory::UnprefixedName(local) => Ok((None, local)),
+            QNameCategory::PrefixedName(prefix, local) => Ok((Some(prefix), local)),
+            QNameCategory::None => Err(SerError::InvalidName(name.to_owned())),
+        }
+    }
+
+    fn namespace_for(
+        &self,
+        prefix: Option<&str>,
+        is_element: bool,
+    ) -> Result<Option<&'c str>, SerError> {
+        match prefix {
+            None if is_element => Ok(self.config.default_namespace.as_deref()),
+            None => Ok(None),
+            Some("xml") => Ok(Some(XML_NAMESPACE)),
+            Some(prefix) => self
+                .config
+                .namespaces
+                .iter()
+                .find(|(p, _)| p == prefix)
+                .map(|(_, namespace_name)| Some(namespace_name.as_str()))
+                .ok_or_else(|| SerError::UndeclaredPrefix(prefix.to_owned())),
+        }
+    }
+
+    /// Returns the prefix bound to `namespace_name`, an empty namespace name
+    /// being no namespace.
+    fn prefix_for(
+        &self,
+        namespace_name: &str,
+        is_element: bool,
+    ) -> Result<Option<&'c str>, SerError> {
+        let default_namespace = match is_element {
+            true => self.config.default_namespace.as_deref(),
+            false => None,
+        };
+        if namespace_name.is_empty() {
+            // An unprefixed element name is in the default namespace.
+            return match default_namespace {
+                None => Ok(None),
+                Some(_) => Err(SerError::UnboundNamespace(String::new())),
+            };
+        }
+        if default_namespace == Some(namespace_name) {
+            return Ok(None);
+        }
+        if namespace_name == XML_NAMESPACE {
+            return Ok(Some("xml"));
+        }
+        self.config
+            .namespaces
+            .iter()
+            .find(|(_, n)| n == namespace_name)
+            .map(|(prefix, _)| Some(prefix.as_str()))
+            .ok_or_else(|| SerError::UnboundNamespace(namespace_name.to_owned()))
+    }
+
+    /// Returns the prefix, local name and namespace name of `name`.
+    fn resolve_name<'n>(
+        &self,
+        name: &'n str,
+        is_element: bool,
+    ) -> Result<(Option<&'n str>, &'n str, Option<&'n str>), SerError>
+    where
+        'c: 'n,
+    {
+        let expanded = name.strip_prefix('{').and_then(|name| name.split_once('}'));
+        if let Some((namespace_name, local_name)) = expanded {
+            if !matches!(classify_qname(local_name), QNameCategory::UnprefixedName(_)) {
+                return Err(SerError::InvalidName(name.to_owned()));
+            }
+            let prefix = self.prefix_for(namespace_name, is_element)?;
+            let namespace_name = Some(namespace_name).filter(|n| !n.is_empty());
+            return Ok((prefix, local_name, namespace_name));
+        }
+        let (prefix, local_name) = self.split_name(name)?;
+        let namespace_name = self.namespace_for(prefix, is_element)?;
+        Ok((prefix, local_name, namespace_name))
+    }
+
+    fn element(
+        &mut self,
+        node: ElementNode,
+        parent: ElementParentInfoItem,
+        is_root: bool,
+    ) -> Result<ElementInfoItem, SerError> {
+        let (prefix, local_name, namespace_name) = self.resolve_name(&node.name, true)?;
+        let expanded_name = self
+            .builder
+            .data
+            .atoms
+            .expanded_name(namespace_name, local_name);
+        let local_name = self.builder.span(local_name);
+        let prefix = prefix.map(|prefix| self.builder.span(prefix));
+        let element = ElementInfoItem::new(
+            local_name,
+            prefix,
+            expanded_name,
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            self.namespace_scope,
+            None,
+            None,
+            parent,
+            &mut self.builder.data,
+        );
+
+        if is_root {
+            let mut namespace_attributes = Vec::new();
+            for (prefix, namespace_name) in Self::declarations(self.config) {
+                let (prefix, local_name) = match prefix {
+                    Some(prefix) => (Some("xmlns"), prefix),
+                    None => (None, "xmlns"),
+                };
+                let expanded_name = self
+                    .builder
+                    .data
+                    .atoms
+                    .expanded_name(Some(XMLNS_NAMESPACE), local_name);
+                let prefix = prefix.map(|prefix| self.builder.span(prefix));
+                let local_name = self.builder.span(local_name);
+                let namespace = CowSpan::Static(XMLNS_NAMESPACE);
+                let value = self.builder.cow_span(namespace_name);
+                namespace_attributes.push(AttributeInfoItem::new(
+                    Some(namespace),
+                    local_name,
+                    prefix,
+                    expanded_name,
+                    value,
+                    AttrSpecified::Specified,
+                    None,
+                    element,
+                    &mut self.builder.data,
+                ));
+            }
+            element.set_namespace_attributes(namespace_attributes, &mut self.builder.data);
+        }
+
+        let mut attributes = Vec::new();
+        for (name, value) in &node.attributes {
+            let (prefix, local_name, namespace_name) = self.resolve_name(name, false)?;
+            let expanded_name = self
+                .builder
+                .data
+                .atoms
+                .expanded_name(namespace_name, local_name);
+            let namespace_name =
+                namespace_name.map(|namespace_name| self.builder.cow_span(namespace_name));
+            let local_name = self.builder.span(local_name);
+            let prefix = prefix.map(|prefix| self.builder.span(prefix));
+            let value = self.builder.cow_span(value);
+            attributes.push(AttributeInfoItem::new(
+                namespace_name,
+                local_name,
+                prefix,
+                expanded_name,
+                value,
+                AttrSpecified::Specified,
+                None,
+                element,
+                &mut self.builder.data,
+            ));
+        }
+        element.set_attributes(attributes, &mut self.builder.data);
+
+        let mut children = Vec::new();
+        for child in node.children {
+            match child {
+                Content::Text(text) => {
+                    let characters = self.builder.cow_span(&text);
+                    let char_group = CharGroupInfoItem::new(
+                        characters,
+                        UnknownOr::Known(None),
+                        element,
+                        &mut self.builder.data,
+                    );
+                    children.push(ElementChildInfoItem::CharGroup(char_group));
+                }
+                Content::Element(node) => {
+                    let child =
+                        self.element(node, ElementParentInfoItem::Element(element), false)?;
+                    children.push(ElementChildInfoItem::Element(child));
+                }
+            }
+        }
+        element.set_children(children, &mut self.builder.data);
+        Ok(element)
+    }
+}
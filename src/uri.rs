@@ -0,0 +1,170 @@
+//! Resolution of URI references, as described in RFC 3986 §5.
+
+/// The components of a URI reference, RFC 3986 §3.
+struct UriReference<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> UriReference<'a> {
+    fn parse(reference: &'a str) -> Self {
+        let (rest, fragment) = match reference.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (reference, None),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let (scheme, rest) = match rest.find(':') {
+            Some(colon) if is_scheme(&rest[..colon]) => (Some(&rest[..colon]), &rest[colon + 1..]),
+            _ => (None, rest),
+        };
+        let (authority, path) = match rest.strip_prefix("//") {
+            Some(rest) => {
+                let end = rest.find('/').unwrap_or(rest.len());
+                (Some(&rest[..end]), &rest[end..])
+            }
+            None => (None, rest),
+        };
+        UriReference {
+            scheme,
+            authority,
+            path,
+            query,
+            fragment,
+        }
+    }
+}
+
+// scheme ::= ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )
+fn is_scheme(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Resolves `reference` against the base URI `base`.
+///
+/// `base` should be absolute; a relative base is merged with the reference
+/// all the same, which gives a reference relative to the same location.
+pub fn resolve(base: &str, reference: &str) -> String {
+    let base = UriReference::parse(base);
+    let r = UriReference::parse(reference);
+    let path;
+    let target = if r.scheme.is_some() {
+        path = remove_dot_segments(r.path);
+        UriReference { path: &path, ..r }
+    } else if r.authority.is_some() {
+        path = remove_dot_segments(r.path);
+        UriReference {
+            scheme: base.scheme,
+            path: &path,
+            ..r
+        }
+    } else if r.path.is_empty() {
+        UriReference {
+            scheme: base.scheme,
+            authority: base.authority,
+            path: base.path,
+            query: r.query.or(base.query),
+            fragment: r.fragment,
+        }
+    } else {
+        path = if r.path.starts_with('/') {
+            remove_dot_segments(r.path)
+        } else {
+            remove_dot_segments(&merge(&base, r.path))
+        };
+        UriReference {
+            scheme: base.scheme,
+            authority: base.authority,
+            path: &path,
+            query: r.query,
+            fragment: r.fragment,
+        }
+    };
+    recompose(&target)
+}
+
+/// Resolves `reference` against `base` if there is one.
+pub(crate) fn resolve_opt(base: Option<&str>, reference: &str) -> String {
+    match base {
+        Some(base) => resolve(base, reference),
+        None => reference.to_owned(),
+    }
+}
+
+// RFC 3986 §5.2.3
+fn merge(base: &UriReference<'_>, path: &str) -> String {
+    if base.authority.is_some() && base.path.is_empty() {
+        return format!("/{}", path);
+    }
+    match base.path.rfind('/') {
+        Some(slash) => format!("{}{}", &base.path[..=slash], path),
+        None => path.to_owned(),
+    }
+}
+
+// RFC 3986 §5.2.4
+fn remove_dot_segments(path: &str) -> String {
+    let mut input = path;
+    let mut output = String::with_capacity(path.len());
+    while !input.is_empty() {
+        if let Some(rest) = input
+            .strip_prefix("../")
+            .or_else(|| input.strip_prefix("./"))
+        {
+            input = rest;
+        } else if input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") {
+            input = &input[3..];
+            pop_segment(&mut output);
+        } else if input == "/.." {
+            input = "/";
+            pop_segment(&mut output);
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            let start = usize::from(input.starts_with('/'));
+            let end = input[start..].find('/').map_or(input.len(), |i| start + i);
+            output.push_str(&input[..end]);
+            input = &input[end..];
+        }
+    }
+    output
+}
+
+fn pop_segment(output: &mut String) {
+    let last_slash = output.rfind('/').unwrap_or(0);
+    output.truncate(last_slash);
+}
+
+// RFC 3986 §5.3
+fn recompose(reference: &UriReference<'_>) -> String {
+    let mut result = String::new();
+    if let Some(scheme) = reference.scheme {
+        result.push_str(scheme);
+        result.push(':');
+    }
+    if let Some(authority) = reference.authority {
+        result.push_str("//");
+        result.push_str(authority);
+    }
+    result.push_str(reference.path);
+    if let Some(query) = reference.query {
+        result.push('?');
+        result.push_str(query);
+    }
+    if let Some(fragment) = reference.fragment {
+        result.push('#');
+        result.push_str(fragment);
+    }
+    result
+}
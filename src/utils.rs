@@ -7,7 +7,68 @@ pub enum QNameCategory<'a> {
 }
 
 pub fn classify_qname(s: &str) -> QNameCategory {
-    todo!()
+    match s.split_once(':') {
+        None if !s.is_empty() => QNameCategory::UnprefixedName(s),
+        Some((prefix, local))
+            if !prefix.is_empty() && !local.is_empty() && !local.contains(':') =>
+        {
+            QNameCategory::PrefixedName(prefix, local)
+        }
+        _ => QNameCategory::None,
+    }
+}
+
+// Char ::= #x9 | #xA | #xD | [#x20-#xD7FF] | [#xE000-#xFFFD] | [#x10000-#x10FFFF]
+pub fn is_xml_char(c: char) -> bool {
+    matches!(
+        c,
+        '\u{9}' | '\u{A}' | '\u{D}'
+            | '\u{20}'..='\u{D7FF}'
+            | '\u{E000}'..='\u{FFFD}'
+            | '\u{10000}'..='\u{10FFFF}'
+    )
+}
+
+// Char ::= [#x1-#xD7FF] | [#xE000-#xFFFD] | [#x10000-#x10FFFF]  (XML 1.1)
+pub fn is_xml_1_1_char(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..='\u{10FFFF}'
+    )
+}
+
+// The name productions below are the ones of XML 1.0 fifth edition, which
+// uses the XML 1.1 name characters, so they serve both versions. The
+// tokenizer checks the names of the input against the same productions, so a
+// 1.0 document using names only allowed since that edition is accepted.
+
+pub fn is_name_start_char(c: char) -> bool {
+    matches!(
+        c,
+        ':' | 'A'..='Z'
+            | '_'
+            | 'a'..='z'
+            | '\u{C0}'..='\u{D6}'
+            | '\u{D8}'..='\u{F6}'
+            | '\u{F8}'..='\u{2FF}'
+            | '\u{370}'..='\u{37D}'
+            | '\u{37F}'..='\u{1FFF}'
+            | '\u{200C}'..='\u{200D}'
+            | '\u{2070}'..='\u{218F}'
+            | '\u{2C00}'..='\u{2FEF}'
+            | '\u{3001}'..='\u{D7FF}'
+            | '\u{F900}'..='\u{FDCF}'
+            | '\u{FDF0}'..='\u{FFFD}'
+            | '\u{10000}'..='\u{EFFFF}'
+    )
+}
+
+pub fn is_name_char(c: char) -> bool {
+    is_name_start_char(c)
+        || matches!(
+            c,
+            '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}'
+        )
 }
 
 macro_rules! impl_enum_from_variant {
@@ -19,9 +80,3 @@ macro_rules! impl_enum_from_variant {
         }
     };
 }
-
-macro_rules! fixme_impl {
-    ($v:expr) => {
-        $v
-    };
-}
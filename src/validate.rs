@@ -0,0 +1,651 @@
+use super::parse::input_str;
+use super::visit::{ChildItems, InfoSetVisitor};
+use super::*;
+use alloc::collections::BTreeMap;
+
+#[derive(Debug, Error)]
+#[error("{kind}")]
+pub struct ValidityError {
+    pub kind: ValidityErrorKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Error)]
+pub enum ValidityErrorKind {
+    #[error("duplicate ID {0:?}")]
+    DuplicateId(String),
+    #[error("IDREF {0:?} does not match any ID")]
+    DanglingIdRef(String),
+    #[error("{0:?} is not the name of an unparsed entity")]
+    UndeclaredUnparsedEntity(String),
+    #[error("{0:?} is not the name of a notation")]
+    UndeclaredNotation(String),
+    #[error("root element {0:?} does not match the document type declaration")]
+    RootElementMismatch(String),
+    #[error("element type {0:?} is not declared")]
+    UndeclaredElement(String),
+    #[error("content of element {0:?} does not match its declaration")]
+    InvalidContent(String),
+    #[error("attribute {0:?} is not declared")]
+    UndeclaredAttribute(String),
+    #[error("value of attribute {0:?} does not match its declared type")]
+    InvalidAttributeValue(String),
+    #[error("value of attribute {0:?} differs from its fixed default")]
+    FixedAttributeMismatch(String),
+    #[error("required attribute {0:?} is not specified")]
+    MissingRequiredAttribute(String),
+    #[error("standalone document depends on external markup declarations")]
+    StandaloneViolation,
+}
+
+impl<'input> InfoSet<'input> {
+    pub fn element_by_id(&self, id: &str) -> Option<ElementInfoItem> {
+        self.data.id_index.get(id).copied()
+    }
+}
+
+fn collect_elements(infoset: &InfoSet<'_>) -> Vec<ElementInfoItem> {
+    let mut collector = ElementCollector::default();
+    visit::visit_infoset(&mut collector, infoset);
+    collector.elements
+}
+
+/// Collects the document element and its descendants in document order.
+#[derive(Default)]
+struct ElementCollector {
+    elements: Vec<ElementInfoItem>,
+}
+
+impl<'a> InfoSetVisitor<'a> for ElementCollector {
+    fn visit_doc(&mut self, infoset: &'a InfoSet<'a>, item: DocInfoItem) {
+        // Elements kept after the document element when recovering from
+        // errors are not validated.
+        self.visit_element(infoset, item.document_element(infoset));
+    }
+
+    fn visit_element(&mut self, infoset: &'a InfoSet<'a>, item: ElementInfoItem) {
+        self.elements.push(item);
+        for &child in item.children(infoset) {
+            self.visit_element_child(infoset, child);
+        }
+    }
+}
+
+/// Indexes the elements by the values of their ID attributes, and checks the
+/// validity constraints ID and IDREF.
+fn index_ids(
+    input: &str,
+    repo: &InfoSetData,
+    elements: &[ElementInfoItem],
+) -> (BTreeMap<String, ElementInfoItem>, Vec<ValidityError>) {
+    let mut id_index = BTreeMap::new();
+    let mut errors = Vec::new();
+    let mut idrefs = Vec::new();
+    for &element in elements {
+        for &attribute in element.attributes(repo) {
+            let value = attribute.normalized_value(repo);
+            let value = input_str(input, &value);
+            match attribute.attribute_type_and_references(repo) {
+                Some(UnknownOr::Known(AttrTypeAndReferences::ID(_))) if !value.is_empty() => {
+                    if id_index.contains_key(value) {
+                        errors.push(ValidityError {
+                            kind: ValidityErrorKind::DuplicateId(value.to_owned()),
+                            span: attribute.local_name(repo),
+                        });
+                    } else {
+                        id_index.insert(value.to_owned(), element);
+                    }
+                }
+                Some(UnknownOr::Known(
+                    AttrTypeAndReferences::IDREF(_) | AttrTypeAndReferences::IDREFS(_),
+                )) => {
+                    idrefs.extend(
+                        reference_names(value)
+                            .map(|name| (name.to_owned(), attribute.local_name(repo))),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+    for (name, span) in idrefs {
+        if !id_index.contains_key(&name) {
+            errors.push(ValidityError {
+                kind: ValidityErrorKind::DanglingIdRef(name),
+                span,
+            });
+        }
+    }
+    (id_index, errors)
+}
+
+/// Splits the value of a reference-typed attribute into the names it refers
+/// to. An empty value refers to nothing.
+fn reference_names(value: &str) -> impl Iterator<Item = &str> {
+    value.split(' ').filter(|name| !name.is_empty())
+}
+
+/// Builds the ID index of the info set and fills in the references of
+/// IDREF, IDREFS, ENTITY, ENTITIES and NOTATION attributes.
+///
+/// A reference-typed attribute whose value names something that does not
+/// exist gets no references, and the mismatch is reported.
+pub(crate) fn resolve_references(infoset: &mut InfoSet<'_>) -> Vec<ValidityError> {
+    if infoset.data.doc_info_item.is_none() {
+        return Vec::new();
+    }
+    let elements = collect_elements(infoset);
+    let (id_index, mut errors) = index_ids(&infoset.input, &infoset.data, &elements);
+    infoset.data.id_index = id_index;
+    errors.extend(link_references(infoset, &elements));
+    errors
+}
+
+/// Fills in the references of the attributes of `new_element` and its
+/// descendants, which replaced `old_element`.
+///
+/// The ID index only changes when either of them has ID attributes, and the
+/// references of the whole info set are resolved again then.
+pub(crate) fn resolve_replaced_references(
+    infoset: &mut InfoSet<'_>,
+    old_element: ElementInfoItem,
+    new_element: ElementInfoItem,
+) {
+    let old_elements = collect_subtree(infoset, old_element);
+    let new_elements = collect_subtree(infoset, new_element);
+    if has_ids(&infoset.data, &old_elements) || has_ids(&infoset.data, &new_elements) {
+        resolve_references(infoset);
+    } else {
+        link_references(infoset, &new_elements);
+    }
+}
+
+fn collect_subtree(infoset: &InfoSet<'_>, element: ElementInfoItem) -> Vec<ElementInfoItem> {
+    let mut collector = ElementCollector::default();
+    collector.visit_element(infoset, element);
+    collector.elements
+}
+
+fn has_ids(repo: &InfoSetData, elements: &[ElementInfoItem]) -> bool {
+    elements.iter().any(|&element| {
+        element.attributes(repo).iter().any(|&attribute| {
+            matches!(
+                attribute.attribute_type_and_references(repo),
+                Some(UnknownOr::Known(AttrTypeAndReferences::ID(_)))
+            )
+        })
+    })
+}
+
+/// Fills in the references of the reference-typed attributes of `elements`
+/// from the ID index and the declarations of the info set.
+fn link_references(infoset: &mut InfoSet<'_>, elements: &[ElementInfoItem]) -> Vec<ValidityError> {
+    let input = &*infoset.input;
+    let repo = &mut infoset.data;
+    let doc_info_item = match repo.doc_info_item {
+        Some(doc_info_item) => doc_info_item,
+        None => return Vec::new(),
+    };
+    let id_index = core::mem::take(&mut repo.id_index);
+    let mut errors = Vec::new();
+
+    let mut unparsed_entities = BTreeMap::new();
+    for &ue in doc_info_item.unparsed_entities(repo) {
+        unparsed_entities.insert(&input[ue.name(repo).0], ue);
+    }
+    let mut notations = BTreeMap::new();
+    if let Some(notation_items) = doc_info_item.notations(repo) {
+        for &notation in notation_items {
+            notations.insert(&input[notation.name(repo).0], notation);
+        }
+    }
+
+    for &element in elements {
+        for attribute in element.attributes(repo).clone() {
+            let attr_type = match attribute.attribute_type_and_references(repo) {
+                Some(UnknownOr::Known(attr_type)) => attr_type,
+                _ => continue,
+            };
+            let value = attribute.normalized_value(repo);
+            let value = input_str(input, &value);
+            let names = match attr_type {
+                AttrTypeAndReferences::IDREF(_)
+                | AttrTypeAndReferences::ENTITY(_)
+                | AttrTypeAndReferences::NOTATION(_)
+                | AttrTypeAndReferences::IDREFS(_)
+                | AttrTypeAndReferences::ENTITIES(_) => reference_names(value).collect::<Vec<_>>(),
+                _ => continue,
+            };
+            let mut references =
+                Some(Vec::with_capacity(names.len())).filter(|_| !names.is_empty());
+            for name in names {
+                let reference = match attr_type {
+                    AttrTypeAndReferences::IDREF(_) | AttrTypeAndReferences::IDREFS(_) => id_index
+                        .get(name)
+                        .map(|&element| AttrReferenceInfoItem::Element(element)),
+                    AttrTypeAndReferences::ENTITY(_) | AttrTypeAndReferences::ENTITIES(_) => {
+                        unparsed_entities
+                            .get(name)
+                            .map(|&ue| AttrReferenceInfoItem::UE(ue))
+                    }
+                    _ => notations
+                        .get(name)
+                        .map(|&notation| AttrReferenceInfoItem::Notation(notation)),
+                };
+                if let Some(reference) = reference {
+                    if let Some(references) = &mut references {
+                        references.push(reference);
+                    }
+                    continue;
+                }
+                references = None;
+                let kind = match attr_type {
+                    // Reported by `index_ids`.
+                    AttrTypeAndReferences::IDREF(_) | AttrTypeAndReferences::IDREFS(_) => continue,
+                    AttrTypeAndReferences::ENTITY(_) | AttrTypeAndReferences::ENTITIES(_) => {
+                        ValidityErrorKind::UndeclaredUnparsedEntity(name.to_owned())
+                    }
+                    _ => ValidityErrorKind::UndeclaredNotation(name.to_owned()),
+                };
+                errors.push(ValidityError {
+                    kind,
+                    span: attribute.local_name(repo),
+                });
+            }
+            let attr_type = match attr_type {
+                AttrTypeAndReferences::IDREF(_) => AttrTypeAndReferences::IDREF(references),
+                AttrTypeAndReferences::IDREFS(_) => AttrTypeAndReferences::IDREFS(references),
+                AttrTypeAndReferences::ENTITY(_) => AttrTypeAndReferences::ENTITY(references),
+                AttrTypeAndReferences::ENTITIES(_) => AttrTypeAndReferences::ENTITIES(references),
+                _ => AttrTypeAndReferences::NOTATION(references),
+            };
+            attribute.set_attribute_type_and_references(Some(UnknownOr::Known(attr_type)), repo);
+        }
+    }
+
+    repo.id_index = id_index;
+    errors
+}
+
+fn qualified_name<'a>(input: &'a str, prefix: &Option<Span>, local_name: &Span) -> &'a str {
+    let start = prefix
+        .as_ref()
+        .map_or(local_name.0.start, |prefix| prefix.0.start);
+    &input[start..local_name.0.end]
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().map_or(false, utils::is_name_start_char) && chars.all(utils::is_name_char)
+}
+
+fn is_nmtoken(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(utils::is_name_char)
+}
+
+/// Glushkov automaton of an element content model.
+///
+/// Every element type name occurring in the content particle is a position
+/// of the automaton; the start state is the one before any position.
+struct ContentAutomaton<'a> {
+    symbols: Vec<&'a str>,
+    first: Vec<usize>,
+    last: Vec<bool>,
+    follow: Vec<Vec<usize>>,
+    nullable: bool,
+}
+
+struct ParticleSets {
+    nullable: bool,
+    first: Vec<usize>,
+    last: Vec<usize>,
+}
+
+impl<'a> ContentAutomaton<'a> {
+    fn compile(particle: &'a dtd::ContentParticle) -> Self {
+        let mut automaton = ContentAutomaton {
+            symbols: Vec::new(),
+            first: Vec::new(),
+            last: Vec::new(),
+            follow: Vec::new(),
+            nullable: false,
+        };
+        let sets = automaton.particle(particle);
+        automaton.last = vec![false; automaton.symbols.len()];
+        for position in sets.last {
+            automaton.last[position] = true;
+        }
+        automaton.first = sets.first;
+        automaton.nullable = sets.nullable;
+        automaton
+    }
+
+    fn add_follow(&mut self, from: &[usize], to: &[usize]) {
+        for &position in from {
+            for &next in to {
+                if !self.follow[position].contains(&next) {
+                    self.follow[position].push(next);
+                }
+            }
+        }
+    }
+
+    fn particle(&mut self, particle: &'a dtd::ContentParticle) -> ParticleSets {
+        let mut sets = match &particle.kind {
+            dtd::ContentParticleKind::Name(name) => {
+                let position = self.symbols.len();
+                self.symbols.push(name);
+                self.follow.push(Vec::new());
+                ParticleSets {
+                    nullable: false,
+                    first: vec![position],
+                    last: vec![position],
+                }
+            }
+            dtd::ContentParticleKind::Choice(particles) => {
+                let mut sets = ParticleSets {
+                    nullable: false,
+                    first: Vec::new(),
+                    last: Vec::new(),
+                };
+                for particle in particles {
+                    let particle_sets = self.particle(particle);
+                    sets.nullable |= particle_sets.nullable;
+                    sets.first.extend(particle_sets.first);
+                    sets.last.extend(particle_sets.last);
+                }
+                sets
+            }
+            dtd::ContentParticleKind::Seq(particles) => {
+                let mut sets = ParticleSets {
+                    nullable: true,
+                    first: Vec::new(),
+                    last: Vec::new(),
+                };
+                for particle in particles {
+                    let particle_sets = self.particle(particle);
+                    self.add_follow(&sets.last, &particle_sets.first);
+                    if sets.nullable {
+                        sets.first.extend_from_slice(&particle_sets.first);
+                    }
+                    if particle_sets.nullable {
+                        sets.last.extend(particle_sets.last);
+                    } else {
+                        sets.last = particle_sets.last;
+                    }
+                    sets.nullable &= particle_sets.nullable;
+                }
+                sets
+            }
+        };
+        match particle.occurrence {
+            dtd::Occurrence::Once => {}
+            dtd::Occurrence::Optional => sets.nullable = true,
+            dtd::Occurrence::ZeroOrMore => {
+                self.add_follow(&sets.last, &sets.first);
+                sets.nullable = true;
+            }
+            dtd::Occurrence::OneOrMore => self.add_follow(&sets.last, &sets.first),
+        }
+        sets
+    }
+
+    /// Runs the automaton over a sequence of element type names.
+    fn matches<'n>(&self, names: impl IntoIterator<Item = &'n str>) -> bool {
+        // `None` is the start state.
+        let mut states = vec![None];
+        for name in names {
+            let mut next_states = Vec::new();
+            for state in states {
+                let candidates = match state {
+                    None => &self.first,
+                    Some(position) => &self.follow[position],
+                };
+                for &next in candidates {
+                    if self.symbols[next] == name && !next_states.contains(&Some(next)) {
+                        next_states.push(Some(next));
+                    }
+                }
+            }
+            if next_states.is_empty() {
+                return false;
+            }
+            states = next_states;
+        }
+        states.into_iter().any(|state| match state {
+            None => self.nullable,
+            Some(position) => self.last[position],
+        })
+    }
+}
+
+/// Validates the info set against the declarations of its document type
+/// declaration, as described by the validity constraints of XML 1.0.
+pub fn validate_dtd(infoset: &InfoSet<'_>) -> Vec<ValidityError> {
+    let mut errors = Vec::new();
+    let input = &*infoset.input;
+    let repo = &infoset.data;
+    let decls = &repo.dtd_declarations;
+    let doc_info_item = match repo.doc_info_item {
+        Some(doc_info_item) => doc_info_item,
+        None => return errors,
+    };
+    let document_element = doc_info_item.document_element(repo);
+
+    // VC: Root Element Type
+    let has_doctype = doc_info_item
+        .children(repo)
+        .iter()
+        .any(|child| matches!(child, DocChildInfoItem::DTD(_)));
+    let root_name = qualified_name(
+        input,
+        &document_element.prefix(repo),
+        &document_element.local_name(repo),
+    );
+    match &decls.doctype_name {
+        Some(doctype_name) if has_doctype && input[doctype_name.0.clone()] == *root_name => {}
+        _ => errors.push(ValidityError {
+            kind: ValidityErrorKind::RootElementMismatch(root_name.to_owned()),
+            span: document_element.local_name(repo),
+        }),
+    }
+
+    let automata: BTreeMap<&str, ContentAutomaton> = decls
+        .element_decls
+        .iter()
+        .filter_map(|(name, decl)| match &decl.content_spec {
+            dtd::ContentSpec::Children(particle) => {
+                Some((name.as_str(), ContentAutomaton::compile(particle)))
+            }
+            _ => None,
+        })
+        .collect();
+
+    // VC: ID, VC: IDREF
+    let elements = collect_elements(infoset);
+    errors.extend(index_ids(input, repo, &elements).1);
+    for element in elements {
+        let element_span = element.local_name(repo);
+        let element_name = qualified_name(input, &element.prefix(repo), &element_span);
+
+        // VC: Element Valid
+        match decls.element_decls.get(element_name) {
+            None => errors.push(ValidityError {
+                kind: ValidityErrorKind::UndeclaredElement(element_name.to_owned()),
+                span: element_span.clone(),
+            }),
+            Some(element_decl) => {
+                let children = ChildItems::of(infoset, element);
+                let child_names = children.elements.iter().map(|child| {
+                    qualified_name(input, &child.prefix(repo), &child.local_name(repo))
+                });
+                let valid = match &element_decl.content_spec {
+                    dtd::ContentSpec::Empty => element.children(repo).is_empty(),
+                    dtd::ContentSpec::Any => true,
+                    dtd::ContentSpec::Mixed(names) => child_names
+                        .clone()
+                        .all(|name| names.iter().any(|allowed| allowed == name)),
+                    dtd::ContentSpec::Children(_) => {
+                        children.uers.is_empty()
+                            && children.char_groups.iter().all(|char_group| {
+                                matches!(
+                                    char_group.element_content_whitespace(repo),
+                                    UnknownOr::Known(Some(true))
+                                )
+                            })
+                            && automata[element_name].matches(child_names)
+                    }
+                };
+                if !valid {
+                    errors.push(ValidityError {
+                        kind: ValidityErrorKind::InvalidContent(element_name.to_owned()),
+                        span: element_span.clone(),
+                    });
+                }
+            }
+        }
+
+        let attributes = element.attributes(repo);
+        let namespace_attributes = element.namespace_attributes(repo);
+        let mut present = Vec::new();
+        for attribute in attributes.iter().chain(namespace_attributes) {
+            let attribute_span = attribute.local_name(repo);
+            let attribute_name = qualified_name(input, &attribute.prefix(repo), &attribute_span);
+            present.push(attribute_name);
+            let error = |kind| ValidityError {
+                kind,
+                span: attribute_span.clone(),
+            };
+
+            // VC: Attribute Value Type
+            let decl = match decls.attribute_decl(element_name, attribute_name) {
+                Some(decl) => decl,
+                None => {
+                    errors.push(error(ValidityErrorKind::UndeclaredAttribute(
+                        attribute_name.to_owned(),
+                    )));
+                    continue;
+                }
+            };
+            let value = attribute.normalized_value(repo);
+            let value = input_str(input, &value);
+            let well_formed = match &decl.attr_type {
+                dtd::AttributeType::CData => true,
+                dtd::AttributeType::Id | dtd::AttributeType::IdRef | dtd::AttributeType::Entity => {
+                    is_name(value)
+                }
+                dtd::AttributeType::IdRefs | dtd::AttributeType::Entities => {
+                    value.split(' ').all(is_name)
+                }
+                dtd::AttributeType::NmToken => is_nmtoken(value),
+                dtd::AttributeType::NmTokens => value.split(' ').all(is_nmtoken),
+                // VC: Notation Attributes, VC: Enumeration
+                dtd::AttributeType::Notation(names) | dtd::AttributeType::Enumeration(names) => {
+                    names.iter().any(|name| name == value)
+                }
+            };
+            if !well_formed {
+                errors.push(error(ValidityErrorKind::InvalidAttributeValue(
+                    attribute_name.to_owned(),
+                )));
+            }
+
+            // VC: Fixed Attribute Default
+            if let dtd::DefaultDecl::Fixed(default) = &decl.default {
+                let expected = decls
+                    .normalize_attribute_value(&default.raw, decl.attr_type.is_tokenized(), &mut 0)
+                    .ok()
+                    .map(|normalized| normalized.unwrap_or_else(|| default.raw.clone()));
+                if expected.as_deref() != Some(value) {
+                    errors.push(error(ValidityErrorKind::FixedAttributeMismatch(
+                        attribute_name.to_owned(),
+                    )));
+                }
+            }
+        }
+
+        // VC: Required Attribute
+        if let Some(attribute_list) = decls.attribute_lists.get(element_name) {
+            for decl in attribute_list {
+                if matches!(decl.default, dtd::DefaultDecl::Required)
+                    && !present.contains(&decl.name.as_str())
+                {
+                    errors.push(ValidityError {
+                        kind: ValidityErrorKind::MissingRequiredAttribute(decl.name.clone()),
+                        span: element_span.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    errors.extend(check_standalone(infoset));
+    errors
+}
+
+/// Checks the validity constraint Standalone Document Declaration: a
+/// standalone document must not reference externally declared entities, nor
+/// take attribute defaults or element content whitespace from external
+/// markup declarations.
+pub(crate) fn check_standalone(infoset: &InfoSet<'_>) -> Vec<ValidityError> {
+    let input = &*infoset.input;
+    let repo = &infoset.data;
+    let decls = &repo.dtd_declarations;
+    match repo.doc_info_item {
+        Some(doc_info_item) if doc_info_item.standalone(repo) == Some(true) => {}
+        _ => return Vec::new(),
+    }
+    let mut errors: Vec<ValidityError> = decls
+        .external_entity_references
+        .iter()
+        .map(|span| ValidityError {
+            kind: ValidityErrorKind::StandaloneViolation,
+            span: span.clone(),
+        })
+        .collect();
+    for element in collect_elements(infoset) {
+        let element_span = element.local_name(repo);
+        let element_name = qualified_name(input, &element.prefix(repo), &element_span);
+        if decls
+            .element_decls
+            .get(element_name)
+            .map_or(false, |decl| decl.external)
+        {
+            for char_group in ChildItems::of(infoset, element).char_groups {
+                if matches!(
+                    char_group.element_content_whitespace(repo),
+                    UnknownOr::Known(Some(true))
+                ) {
+                    errors.push(ValidityError {
+                        kind: ValidityErrorKind::StandaloneViolation,
+                        span: match char_group.characters(repo) {
+                            CowSpan::Borrowed(span) => span,
+                            _ => element_span.clone(),
+                        },
+                    });
+                }
+            }
+        }
+        for attribute in element
+            .attributes(repo)
+            .iter()
+            .chain(element.namespace_attributes(repo))
+        {
+            if !matches!(attribute.specified(repo), AttrSpecified::DefaultedFromDTD) {
+                continue;
+            }
+            let attribute_span = attribute.local_name(repo);
+            let attribute_name = qualified_name(input, &attribute.prefix(repo), &attribute_span);
+            if decls
+                .attribute_decl(element_name, attribute_name)
+                .map_or(false, |decl| decl.external)
+            {
+                errors.push(ValidityError {
+                    kind: ValidityErrorKind::StandaloneViolation,
+                    span: attribute_span,
+                });
+            }
+        }
+    }
+    errors
+}
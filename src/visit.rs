@@ -0,0 +1,465 @@
+//! Typed traversal over the info items of an [`InfoSet`].
+//!
+//! Every `visit_*` method defaults to the matching `walk_*` function, which
+//! visits the children of the item. Override a method to intercept an item
+//! kind, and call the `walk_*` function from the override to keep descending.
+//!
+//! The walk of an element visits its attributes and children but not its
+//! in-scope namespaces, which are collected through all of its ancestors. A
+//! visitor that wants them calls [`walk_in_scope_namespaces`] from its
+//! `visit_element` override.
+
+use super::*;
+
+pub trait InfoSetVisitor<'a> {
+    fn visit_doc(&mut self, infoset: &'a InfoSet<'a>, item: DocInfoItem) {
+        walk_doc(self, infoset, item)
+    }
+
+    fn visit_doc_child(&mut self, infoset: &'a InfoSet<'a>, item: DocChildInfoItem) {
+        walk_doc_child(self, infoset, item)
+    }
+
+    fn visit_element(&mut self, infoset: &'a InfoSet<'a>, item: ElementInfoItem) {
+        walk_element(self, infoset, item)
+    }
+
+    fn visit_element_child(&mut self, infoset: &'a InfoSet<'a>, item: ElementChildInfoItem) {
+        walk_element_child(self, infoset, item)
+    }
+
+    fn visit_attribute(&mut self, infoset: &'a InfoSet<'a>, item: AttributeInfoItem) {
+        walk_attribute(self, infoset, item)
+    }
+
+    fn visit_namespace(&mut self, infoset: &'a InfoSet<'a>, item: NSInfoItem) {
+        walk_namespace(self, infoset, item)
+    }
+
+    fn visit_char_group(&mut self, infoset: &'a InfoSet<'a>, item: CharGroupInfoItem) {
+        walk_char_group(self, infoset, item)
+    }
+
+    fn visit_comment(&mut self, infoset: &'a InfoSet<'a>, item: CommentInfoItem) {
+        walk_comment(self, infoset, item)
+    }
+
+    fn visit_pi(&mut self, infoset: &'a InfoSet<'a>, item: PIInfoItem) {
+        walk_pi(self, infoset, item)
+    }
+
+    fn visit_uer(&mut self, infoset: &'a InfoSet<'a>, item: UERInfoItem) {
+        walk_uer(self, infoset, item)
+    }
+
+    fn visit_dtd(&mut self, infoset: &'a InfoSet<'a>, item: DTDInfoItem) {
+        walk_dtd(self, infoset, item)
+    }
+
+    fn visit_notation(&mut self, infoset: &'a InfoSet<'a>, item: NotationInfoItem) {
+        walk_notation(self, infoset, item)
+    }
+
+    fn visit_unparsed_entity(&mut self, infoset: &'a InfoSet<'a>, item: UEInfoItem) {
+        walk_unparsed_entity(self, infoset, item)
+    }
+}
+
+/// Visits the document info item of `infoset`, if it has been parsed.
+pub fn visit_infoset<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    visitor: &mut V,
+    infoset: &'a InfoSet<'a>,
+) {
+    if let Some(doc_info_item) = infoset.doc_info_item {
+        visitor.visit_doc(infoset, doc_info_item);
+    }
+}
+
+pub fn walk_doc<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    visitor: &mut V,
+    infoset: &'a InfoSet<'a>,
+    item: DocInfoItem,
+) {
+    for &child in item.children(infoset) {
+        visitor.visit_doc_child(infoset, child);
+    }
+    if let Some(notations) = item.notations(infoset) {
+        for &notation in notations {
+            visitor.visit_notation(infoset, notation);
+        }
+    }
+    for &unparsed_entity in item.unparsed_entities(infoset) {
+        visitor.visit_unparsed_entity(infoset, unparsed_entity);
+    }
+}
+
+pub fn walk_doc_child<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    visitor: &mut V,
+    infoset: &'a InfoSet<'a>,
+    item: DocChildInfoItem,
+) {
+    match item {
+        DocChildInfoItem::Element(element) => visitor.visit_element(infoset, element),
+        DocChildInfoItem::PI(pi) => visitor.visit_pi(infoset, pi),
+        DocChildInfoItem::Comment(comment) => visitor.visit_comment(infoset, comment),
+        DocChildInfoItem::DTD(dtd) => visitor.visit_dtd(infoset, dtd),
+    }
+}
+
+pub fn walk_element<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    visitor: &mut V,
+    infoset: &'a InfoSet<'a>,
+    item: ElementInfoItem,
+) {
+    for &attribute in item.namespace_attributes(infoset) {
+        visitor.visit_attribute(infoset, attribute);
+    }
+    for &attribute in item.attributes(infoset) {
+        visitor.visit_attribute(infoset, attribute);
+    }
+    for &child in item.children(infoset) {
+        visitor.visit_element_child(infoset, child);
+    }
+}
+
+/// Visits the namespaces in scope for `item`, including those declared by
+/// its ancestors.
+pub fn walk_in_scope_namespaces<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    visitor: &mut V,
+    infoset: &'a InfoSet<'a>,
+    item: ElementInfoItem,
+) {
+    for namespace in item.in_scope_namespaces(infoset) {
+        visitor.visit_namespace(infoset, namespace);
+    }
+}
+
+pub fn walk_element_child<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    visitor: &mut V,
+    infoset: &'a InfoSet<'a>,
+    item: ElementChildInfoItem,
+) {
+    match item {
+        ElementChildInfoItem::Element(element) => visitor.visit_element(infoset, element),
+        ElementChildInfoItem::PI(pi) => visitor.visit_pi(infoset, pi),
+        ElementChildInfoItem::UER(uer) => visitor.visit_uer(infoset, uer),
+        ElementChildInfoItem::CharGroup(char_group) => {
+            visitor.visit_char_group(infoset, char_group)
+        }
+        ElementChildInfoItem::Comment(comment) => visitor.visit_comment(infoset, comment),
+    }
+}
+
+pub fn walk_attribute<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &'a InfoSet<'a>,
+    _item: AttributeInfoItem,
+) {
+}
+
+pub fn walk_namespace<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &'a InfoSet<'a>,
+    _item: NSInfoItem,
+) {
+}
+
+pub fn walk_char_group<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &'a InfoSet<'a>,
+    _item: CharGroupInfoItem,
+) {
+}
+
+pub fn walk_comment<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &'a InfoSet<'a>,
+    _item: CommentInfoItem,
+) {
+}
+
+pub fn walk_pi<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &'a InfoSet<'a>,
+    _item: PIInfoItem,
+) {
+}
+
+pub fn walk_uer<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &'a InfoSet<'a>,
+    _item: UERInfoItem,
+) {
+}
+
+pub fn walk_dtd<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    visitor: &mut V,
+    infoset: &'a InfoSet<'a>,
+    item: DTDInfoItem,
+) {
+    for &pi in item.children(infoset) {
+        visitor.visit_pi(infoset, pi);
+    }
+}
+
+pub fn walk_notation<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &'a InfoSet<'a>,
+    _item: NotationInfoItem,
+) {
+}
+
+pub fn walk_unparsed_entity<'a, V: InfoSetVisitor<'a> + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &'a InfoSet<'a>,
+    _item: UEInfoItem,
+) {
+}
+
+/// The child elements, character groups and unexpanded entity references of
+/// an element, collected by visiting its children.
+#[derive(Default)]
+pub(crate) struct ChildItems {
+    pub(crate) elements: Vec<ElementInfoItem>,
+    pub(crate) char_groups: Vec<CharGroupInfoItem>,
+    pub(crate) uers: Vec<UERInfoItem>,
+}
+
+impl ChildItems {
+    pub(crate) fn of<'a>(infoset: &'a InfoSet<'a>, element: ElementInfoItem) -> Self {
+        let mut items = ChildItems::default();
+        for &child in element.children(infoset) {
+            items.visit_element_child(infoset, child);
+        }
+        items
+    }
+}
+
+impl<'a> InfoSetVisitor<'a> for ChildItems {
+    fn visit_element(&mut self, _infoset: &'a InfoSet<'a>, item: ElementInfoItem) {
+        self.elements.push(item);
+    }
+
+    fn visit_char_group(&mut self, _infoset: &'a InfoSet<'a>, item: CharGroupInfoItem) {
+        self.char_groups.push(item);
+    }
+
+    fn visit_uer(&mut self, _infoset: &'a InfoSet<'a>, item: UERInfoItem) {
+        self.uers.push(item);
+    }
+}
+
+pub trait InfoSetVisitorMut {
+    fn visit_doc_mut(&mut self, infoset: &mut InfoSet<'_>, item: DocInfoItem) {
+        walk_doc_mut(self, infoset, item)
+    }
+
+    fn visit_doc_child_mut(&mut self, infoset: &mut InfoSet<'_>, item: DocChildInfoItem) {
+        walk_doc_child_mut(self, infoset, item)
+    }
+
+    fn visit_element_mut(&mut self, infoset: &mut InfoSet<'_>, item: ElementInfoItem) {
+        walk_element_mut(self, infoset, item)
+    }
+
+    fn visit_element_child_mut(&mut self, infoset: &mut InfoSet<'_>, item: ElementChildInfoItem) {
+        walk_element_child_mut(self, infoset, item)
+    }
+
+    fn visit_attribute_mut(&mut self, infoset: &mut InfoSet<'_>, item: AttributeInfoItem) {
+        walk_attribute_mut(self, infoset, item)
+    }
+
+    fn visit_namespace_mut(&mut self, infoset: &mut InfoSet<'_>, item: NSInfoItem) {
+        walk_namespace_mut(self, infoset, item)
+    }
+
+    fn visit_char_group_mut(&mut self, infoset: &mut InfoSet<'_>, item: CharGroupInfoItem) {
+        walk_char_group_mut(self, infoset, item)
+    }
+
+    fn visit_comment_mut(&mut self, infoset: &mut InfoSet<'_>, item: CommentInfoItem) {
+        walk_comment_mut(self, infoset, item)
+    }
+
+    fn visit_pi_mut(&mut self, infoset: &mut InfoSet<'_>, item: PIInfoItem) {
+        walk_pi_mut(self, infoset, item)
+    }
+
+    fn visit_uer_mut(&mut self, infoset: &mut InfoSet<'_>, item: UERInfoItem) {
+        walk_uer_mut(self, infoset, item)
+    }
+
+    fn visit_dtd_mut(&mut self, infoset: &mut InfoSet<'_>, item: DTDInfoItem) {
+        walk_dtd_mut(self, infoset, item)
+    }
+
+    fn visit_notation_mut(&mut self, infoset: &mut InfoSet<'_>, item: NotationInfoItem) {
+        walk_notation_mut(self, infoset, item)
+    }
+
+    fn visit_unparsed_entity_mut(&mut self, infoset: &mut InfoSet<'_>, item: UEInfoItem) {
+        walk_unparsed_entity_mut(self, infoset, item)
+    }
+}
+
+/// Visits the document info item of `infoset` mutably, if it has been parsed.
+pub fn visit_infoset_mut<V: InfoSetVisitorMut + ?Sized>(
+    visitor: &mut V,
+    infoset: &mut InfoSet<'_>,
+) {
+    if let Some(doc_info_item) = infoset.doc_info_item {
+        visitor.visit_doc_mut(infoset, doc_info_item);
+    }
+}
+
+// The mutable walkers snapshot child lists before descending, so a visitor
+// is free to rewrite the list of the item it is currently visiting.
+
+pub fn walk_doc_mut<V: InfoSetVisitorMut + ?Sized>(
+    visitor: &mut V,
+    infoset: &mut InfoSet<'_>,
+    item: DocInfoItem,
+) {
+    let children = item.children(infoset).clone();
+    for child in children {
+        visitor.visit_doc_child_mut(infoset, child);
+    }
+    if let Some(notations) = item.notations(infoset).clone() {
+        for notation in notations {
+            visitor.visit_notation_mut(infoset, notation);
+        }
+    }
+    let unparsed_entities = item.unparsed_entities(infoset).clone();
+    for unparsed_entity in unparsed_entities {
+        visitor.visit_unparsed_entity_mut(infoset, unparsed_entity);
+    }
+}
+
+pub fn walk_doc_child_mut<V: InfoSetVisitorMut + ?Sized>(
+    visitor: &mut V,
+    infoset: &mut InfoSet<'_>,
+    item: DocChildInfoItem,
+) {
+    match item {
+        DocChildInfoItem::Element(element) => visitor.visit_element_mut(infoset, element),
+        DocChildInfoItem::PI(pi) => visitor.visit_pi_mut(infoset, pi),
+        DocChildInfoItem::Comment(comment) => visitor.visit_comment_mut(infoset, comment),
+        DocChildInfoItem::DTD(dtd) => visitor.visit_dtd_mut(infoset, dtd),
+    }
+}
+
+pub fn walk_element_mut<V: InfoSetVisitorMut + ?Sized>(
+    visitor: &mut V,
+    infoset: &mut InfoSet<'_>,
+    item: ElementInfoItem,
+) {
+    let namespace_attributes = item.namespace_attributes(infoset).clone();
+    for attribute in namespace_attributes {
+        visitor.visit_attribute_mut(infoset, attribute);
+    }
+    let attributes = item.attributes(infoset).clone();
+    for attribute in attributes {
+        visitor.visit_attribute_mut(infoset, attribute);
+    }
+    let children = item.children(infoset).clone();
+    for child in children {
+        visitor.visit_element_child_mut(infoset, child);
+    }
+}
+
+/// Visits the namespaces in scope for `item` mutably, including those
+/// declared by its ancestors.
+pub fn walk_in_scope_namespaces_mut<V: InfoSetVisitorMut + ?Sized>(
+    visitor: &mut V,
+    infoset: &mut InfoSet<'_>,
+    item: ElementInfoItem,
+) {
+    let in_scope_namespaces = item.in_scope_namespaces(infoset);
+    for namespace in in_scope_namespaces {
+        visitor.visit_namespace_mut(infoset, namespace);
+    }
+}
+
+pub fn walk_element_child_mut<V: InfoSetVisitorMut + ?Sized>(
+    visitor: &mut V,
+    infoset: &mut InfoSet<'_>,
+    item: ElementChildInfoItem,
+) {
+    match item {
+        ElementChildInfoItem::Element(element) => visitor.visit_element_mut(infoset, element),
+        ElementChildInfoItem::PI(pi) => visitor.visit_pi_mut(infoset, pi),
+        ElementChildInfoItem::UER(uer) => visitor.visit_uer_mut(infoset, uer),
+        ElementChildInfoItem::CharGroup(char_group) => {
+            visitor.visit_char_group_mut(infoset, char_group)
+        }
+        ElementChildInfoItem::Comment(comment) => visitor.visit_comment_mut(infoset, comment),
+    }
+}
+
+pub fn walk_attribute_mut<V: InfoSetVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &mut InfoSet<'_>,
+    _item: AttributeInfoItem,
+) {
+}
+
+pub fn walk_namespace_mut<V: InfoSetVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &mut InfoSet<'_>,
+    _item: NSInfoItem,
+) {
+}
+
+pub fn walk_char_group_mut<V: InfoSetVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &mut InfoSet<'_>,
+    _item: CharGroupInfoItem,
+) {
+}
+
+pub fn walk_comment_mut<V: InfoSetVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &mut InfoSet<'_>,
+    _item: CommentInfoItem,
+) {
+}
+
+pub fn walk_pi_mut<V: InfoSetVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &mut InfoSet<'_>,
+    _item: PIInfoItem,
+) {
+}
+
+pub fn walk_uer_mut<V: InfoSetVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &mut InfoSet<'_>,
+    _item: UERInfoItem,
+) {
+}
+
+pub fn walk_dtd_mut<V: InfoSetVisitorMut + ?Sized>(
+    visitor: &mut V,
+    infoset: &mut InfoSet<'_>,
+    item: DTDInfoItem,
+) {
+    let children = item.children(infoset).clone();
+    for pi in children {
+        visitor.visit_pi_mut(infoset, pi);
+    }
+}
+
+pub fn walk_notation_mut<V: InfoSetVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &mut InfoSet<'_>,
+    _item: NotationInfoItem,
+) {
+}
+
+pub fn walk_unparsed_entity_mut<V: InfoSetVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _infoset: &mut InfoSet<'_>,
+    _item: UEInfoItem,
+) {
+}
@@ -0,0 +1,894 @@
+//! XInclude 1.0 processing over a parsed [`InfoSet`].
+//!
+//! Included documents are parsed separately and their items copied into the
+//! including info set; their text is appended to its input so the copies can
+//! keep using spans.
+
+use super::parse::{input_str, ParseError, ParseOptions};
+use super::resolver::{EntityResolver, ResolveError};
+use super::visit::{ChildItems, InfoSetVisitor};
+use super::*;
+use core::mem;
+
+pub const XINCLUDE_NAMESPACE: &str = "http://www.w3.org/2001/XInclude";
+
+#[derive(Debug, Error)]
+pub enum XIncludeError {
+    #[error(transparent)]
+    Resolve(#[from] ResolveError),
+    #[error("included document {0:?} is not well-formed: {1}")]
+    Parse(String, #[source] ParseError),
+    #[error("included resource {0:?} is not valid UTF-8")]
+    InvalidEncoding(String),
+    #[error("unsupported encoding {0:?} for text inclusion")]
+    UnsupportedEncoding(String),
+    #[error("xpointer {0:?} does not identify an element")]
+    UnresolvedPointer(String),
+    #[error("inclusion loop through {0:?}")]
+    InclusionLoop(String),
+    #[error("include element has neither an href nor an xpointer attribute")]
+    MissingHref,
+    #[error("href attribute contains a fragment identifier")]
+    FragmentInHref,
+    #[error("invalid parse attribute value {0:?}")]
+    InvalidParseAttribute(String),
+    #[error("xpointer attribute on a text inclusion")]
+    XPointerWithTextInclusion,
+    #[error("include element has more than one fallback child")]
+    MultipleFallbacks,
+    #[error("unexpected XInclude element as a child of an include element")]
+    UnexpectedIncludeChild,
+    #[error("fallback element outside of an include element")]
+    MisplacedFallback,
+    #[error("inclusion result is not a single document element")]
+    InvalidDocumentElementInclusion,
+}
+
+impl XIncludeError {
+    /// Returns whether the error is a resource error, which is recovered
+    /// from by the fallback of the include element.
+    fn is_resource_error(&self) -> bool {
+        matches!(
+            self,
+            XIncludeError::Resolve(_)
+                | XIncludeError::Parse(..)
+                | XIncludeError::InvalidEncoding(_)
+                | XIncludeError::UnsupportedEncoding(_)
+                | XIncludeError::UnresolvedPointer(_)
+        )
+    }
+}
+
+/// Replaces every `xi:include` element of `infoset` by the items it refers
+/// to, reading the included resources through `resolver`.
+pub fn process(
+    infoset: &mut InfoSet<'_>,
+    resolver: &mut dyn EntityResolver,
+) -> Result<(), XIncludeError> {
+    let mut processor = XIncludeProcessor {
+        resolver,
+        inclusion_stack: Vec::new(),
+    };
+    processor.process_document(infoset)
+}
+
+#[derive(Clone, Copy)]
+enum Parent {
+    Doc(DocInfoItem),
+    Element(ElementInfoItem),
+}
+
+struct XIncludeProcessor<'r> {
+    resolver: &'r mut dyn EntityResolver,
+    /// The resources being included, as URI and pointer.
+    inclusion_stack: Vec<(String, Option<String>)>,
+}
+
+impl XIncludeProcessor<'_> {
+    fn process_document(&mut self, infoset: &mut InfoSet<'_>) -> Result<(), XIncludeError> {
+        let doc_info_item = match infoset.doc_info_item {
+            Some(doc_info_item) => doc_info_item,
+            None => return Ok(()),
+        };
+        let base_uri = uri_string(infoset, doc_info_item.base_uri(infoset));
+        self.inclusion_stack
+            .push((base_uri.unwrap_or_default(), None));
+        let mut appended = String::new();
+        let result = self.process_root(infoset, &mut appended, doc_info_item);
+        self.inclusion_stack.pop();
+        result?;
+        append_input(infoset, &appended);
+        validate::resolve_references(infoset);
+        Ok(())
+    }
+
+    fn process_root(
+        &mut self,
+        infoset: &mut InfoSet<'_>,
+        appended: &mut String,
+        doc_info_item: DocInfoItem,
+    ) -> Result<(), XIncludeError> {
+        let root = doc_info_item.document_element(infoset);
+        if is_xinclude_element(infoset, root, "include") {
+            self.include_document_element(infoset, appended, doc_info_item, root)
+        } else if is_xinclude_element(infoset, root, "fallback") {
+            Err(XIncludeError::MisplacedFallback)
+        } else {
+            self.process_children(infoset, appended, root)
+        }
+    }
+
+    fn include_document_element(
+        &mut self,
+        infoset: &mut InfoSet<'_>,
+        appended: &mut String,
+        doc_info_item: DocInfoItem,
+        root: ElementInfoItem,
+    ) -> Result<(), XIncludeError> {
+        let items = self.include(infoset, appended, root, Parent::Doc(doc_info_item))?;
+        let mut document_element = None;
+        let mut doc_children = Vec::with_capacity(items.len());
+        for item in items {
+            doc_children.push(match item {
+                ElementChildInfoItem::Element(element) if document_element.is_none() => {
+                    document_element = Some(element);
+                    DocChildInfoItem::Element(element)
+                }
+                ElementChildInfoItem::PI(pi) => DocChildInfoItem::PI(pi),
+                ElementChildInfoItem::Comment(comment) => DocChildInfoItem::Comment(comment),
+                _ => return Err(XIncludeError::InvalidDocumentElementInclusion),
+            });
+        }
+        let document_element =
+            document_element.ok_or(XIncludeError::InvalidDocumentElementInclusion)?;
+        let children = doc_info_item.children_mut(infoset);
+        let position = children
+            .iter()
+            .position(|child| matches!(child, DocChildInfoItem::Element(_)))
+            .unwrap();
+        children.splice(position..=position, doc_children);
+        doc_info_item.set_document_element(document_element, infoset);
+        Ok(())
+    }
+
+    fn process_children(
+        &mut self,
+        infoset: &mut InfoSet<'_>,
+        appended: &mut String,
+        element: ElementInfoItem,
+    ) -> Result<(), XIncludeError> {
+        let mut i = 0;
+        while let Some(&child) = element.children(infoset).get(i) {
+            let child = match child {
+                ElementChildInfoItem::Element(child) => child,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            if is_xinclude_element(infoset, child, "include") {
+                let items = self.include(infoset, appended, child, Parent::Element(element))?;
+                let len = items.len();
+                element.children_mut(infoset).splice(i..=i, items);
+                i += len;
+                continue;
+            }
+            if is_xinclude_element(infoset, child, "fallback") {
+                return Err(XIncludeError::MisplacedFallback);
+            }
+            self.process_children(infoset, appended, child)?;
+            i += 1;
+        }
+        Ok(())
+    }
+
+    /// Returns the items replacing the include element `include`, with
+    /// `parent` as their parent.
+    fn include(
+        &mut self,
+        infoset: &mut InfoSet<'_>,
+        appended: &mut String,
+        include: ElementInfoItem,
+        parent: Parent,
+    ) -> Result<Vec<ElementChildInfoItem>, XIncludeError> {
+        let mut fallback = None;
+        for child in ChildItems::of(infoset, include).elements {
+            if is_xinclude_element(infoset, child, "fallback") {
+                if fallback.is_some() {
+                    return Err(XIncludeError::MultipleFallbacks);
+                }
+                fallback = Some(child);
+            } else if is_xinclude_element(infoset, child, "include") {
+                return Err(XIncludeError::UnexpectedIncludeChild);
+            }
+        }
+        let result = match include.attribute_value(infoset, None, "parse").as_deref() {
+            None | Some("xml") => self.include_xml(infoset, appended, include, parent),
+            Some("text") => self.include_text(infoset, include, parent),
+            Some(parse) => return Err(XIncludeError::InvalidParseAttribute(parse.to_owned())),
+        };
+        match result {
+            Err(e) if e.is_resource_error() => {
+                let fallback = match fallback {
+                    Some(fallback) => fallback,
+                    None => return Err(e),
+                };
+                self.process_children(infoset, appended, fallback)?;
+                let items = fallback.children(infoset).clone();
+                for &item in &items {
+                    set_parent(infoset, item, parent)?;
+                }
+                Ok(items)
+            }
+            result => result,
+        }
+    }
+
+    fn include_xml(
+        &mut self,
+        infoset: &mut InfoSet<'_>,
+        appended: &mut String,
+        include: ElementInfoItem,
+        parent: Parent,
+    ) -> Result<Vec<ElementChildInfoItem>, XIncludeError> {
+        let href = include
+            .attribute_value(infoset, None, "href")
+            .unwrap_or_default();
+        let xpointer = include.attribute_value(infoset, None, "xpointer");
+        if href.contains('#') {
+            return Err(XIncludeError::FragmentInHref);
+        }
+        let base_uri = uri_string(infoset, include.base_uri(infoset));
+        let (uri, text) = if href.is_empty() {
+            // A reference to the including document itself, which is
+            // included as it was before any inclusion.
+            if xpointer.is_none() {
+                return Err(XIncludeError::MissingHref);
+            }
+            let doc_base_uri = infoset
+                .doc_info_item
+                .and_then(|doc_info_item| uri_string(infoset, doc_info_item.base_uri(infoset)));
+            (
+                doc_base_uri.unwrap_or_default(),
+                infoset.text().into_owned(),
+            )
+        } else {
+            let uri = uri::resolve_opt(base_uri.as_deref(), &href);
+            let bytes = self
+                .resolver
+                .resolve_entity(None, &href, base_uri.as_deref())?;
+            let text = String::from_utf8(bytes)
+                .map_err(|_| XIncludeError::InvalidEncoding(uri.clone()))?;
+            (uri, text)
+        };
+        let key = (uri, xpointer);
+        if self.inclusion_stack.contains(&key) {
+            return Err(XIncludeError::InclusionLoop(key.0));
+        }
+        let (uri, xpointer) = &key;
+        let options = ParseOptions {
+            base_uri: (!uri.is_empty()).then(|| uri.clone()),
+            ..ParseOptions::default()
+        };
+        let (mut included, _) = parse::parse_with_options(&text, options)
+            .map_err(|e| XIncludeError::Parse(uri.clone(), e))?;
+        self.inclusion_stack.push(key.clone());
+        let sources = self.process_included(&mut included, xpointer.as_deref());
+        self.inclusion_stack.pop();
+        let sources = sources?;
+
+        let copier = ItemCopier {
+            source: &included,
+            offset: infoset.input.len() + appended.len(),
+        };
+        appended.push_str(&included.input);
+        let parent_base_uri = match parent {
+            Parent::Doc(doc_info_item) => doc_info_item.base_uri(infoset),
+            Parent::Element(element) => element.base_uri(infoset),
+        };
+        let parent_base_uri = uri_string(infoset, parent_base_uri);
+        let items = copier.copy_children(infoset, &sources, parent)?;
+        for &item in &items {
+            if let ElementChildInfoItem::Element(element) = item {
+                let base_uri = element
+                    .base_uri(infoset)
+                    .map(|uri| copier.text(&uri.0).to_owned());
+                if let Some(base_uri) = base_uri {
+                    if Some(&base_uri) != parent_base_uri.as_ref() {
+                        copier.fix_up_base_uri(infoset, appended, element, base_uri);
+                    }
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    /// Processes the inclusions within the items to be included from another
+    /// document, and returns these items.
+    fn process_included(
+        &mut self,
+        included: &mut InfoSet<'_>,
+        xpointer: Option<&str>,
+    ) -> Result<Vec<ElementChildInfoItem>, XIncludeError> {
+        let doc_info_item = included.doc_info_item.unwrap();
+        let mut appended = String::new();
+        let items = match xpointer {
+            // Only the identified element is processed, as the rest of the
+            // document may refer back to it.
+            Some(xpointer) => {
+                let element = resolve_xpointer(included, doc_info_item, xpointer)
+                    .ok_or_else(|| XIncludeError::UnresolvedPointer(xpointer.to_owned()))?;
+                if is_xinclude_element(included, element, "include") {
+                    let parent = match element.parent(included) {
+                        ElementParentInfoItem::Doc(doc_info_item) => Parent::Doc(doc_info_item),
+                        ElementParentInfoItem::Element(parent) => Parent::Element(parent),
+                    };
+                    self.include(included, &mut appended, element, parent)?
+                } else if is_xinclude_element(included, element, "fallback") {
+                    return Err(XIncludeError::MisplacedFallback);
+                } else {
+                    self.process_children(included, &mut appended, element)?;
+                    vec![ElementChildInfoItem::Element(element)]
+                }
+            }
+            None => {
+                self.process_root(included, &mut appended, doc_info_item)?;
+                doc_info_item
+                    .children(included)
+                    .iter()
+                    .filter_map(|&child| match child {
+                        DocChildInfoItem::Element(element) => {
+                            Some(ElementChildInfoItem::Element(element))
+                        }
+                        DocChildInfoItem::PI(pi) => Some(ElementChildInfoItem::PI(pi)),
+                        DocChildInfoItem::Comment(comment) => {
+                            Some(ElementChildInfoItem::Comment(comment))
+                        }
+                        DocChildInfoItem::DTD(_) => None,
+                    })
+                    .collect()
+            }
+        };
+        append_input(included, &appended);
+        Ok(items)
+    }
+
+    fn include_text(
+        &mut self,
+        infoset: &mut InfoSet<'_>,
+        include: ElementInfoItem,
+        parent: Parent,
+    ) -> Result<Vec<ElementChildInfoItem>, XIncludeError> {
+        let href = include
+            .attribute_value(infoset, None, "href")
+            .unwrap_or_default();
+        if include.attribute_value(infoset, None, "xpointer").is_some() {
+            return Err(XIncludeError::XPointerWithTextInclusion);
+        }
+        if href.is_empty() {
+            return Err(XIncludeError::MissingHref);
+        }
+        if href.contains('#') {
+            return Err(XIncludeError::FragmentInHref);
+        }
+        if let Some(encoding) = include.attribute_value(infoset, None, "encoding") {
+            if !encoding.eq_ignore_ascii_case("UTF-8") {
+                return Err(XIncludeError::UnsupportedEncoding(encoding));
+            }
+        }
+        let base_uri = uri_string(infoset, include.base_uri(infoset));
+        let bytes = self
+            .resolver
+            .resolve_entity(None, &href, base_uri.as_deref())?;
+        let mut text = String::from_utf8(bytes).map_err(|_| {
+            XIncludeError::InvalidEncoding(uri::resolve_opt(base_uri.as_deref(), &href))
+        })?;
+        if text.starts_with('\u{FEFF}') {
+            text.drain(..'\u{FEFF}'.len_utf8());
+        }
+        if text.is_empty() {
+            return Ok(Vec::new());
+        }
+        let parent = match parent {
+            Parent::Element(parent) => parent,
+            Parent::Doc(_) => return Err(XIncludeError::InvalidDocumentElementInclusion),
+        };
+        let char_group_info_item = CharGroupInfoItem::new(
+            CowSpan::Owned(text),
+            UnknownOr::Known(None),
+            parent,
+            infoset,
+        );
+        Ok(vec![ElementChildInfoItem::CharGroup(char_group_info_item)])
+    }
+}
+
+fn is_xinclude_element(infoset: &InfoSet<'_>, element: ElementInfoItem, name: &str) -> bool {
+    element.has_name(infoset, Some(XINCLUDE_NAMESPACE), name)
+}
+
+fn append_input(infoset: &mut InfoSet<'_>, appended: &str) {
+    if !appended.is_empty() {
+        let mut input = infoset.input.to_string();
+        input.push_str(appended);
+        infoset.input = Cow::Owned(input);
+    }
+}
+
+fn uri_string(infoset: &InfoSet<'_>, uri: Option<UriSpan>) -> Option<String> {
+    uri.map(|uri| input_str(&infoset.input, &uri.0).to_owned())
+}
+
+fn set_parent(
+    infoset: &mut InfoSet<'_>,
+    item: ElementChildInfoItem,
+    parent: Parent,
+) -> Result<(), XIncludeError> {
+    match (item, parent) {
+        (ElementChildInfoItem::Element(element), Parent::Doc(doc_info_item)) => {
+            element.set_parent(ElementParentInfoItem::Doc(doc_info_item), infoset)
+        }
+        (ElementChildInfoItem::Element(element), Parent::Element(parent)) => {
+            element.set_parent(ElementParentInfoItem::Element(parent), infoset)
+        }
+        (ElementChildInfoItem::PI(pi), Parent::Doc(doc_info_item)) => {
+            pi.set_parent(PIParentInfoItem::Doc(doc_info_item), infoset)
+        }
+        (ElementChildInfoItem::PI(pi), Parent::Element(parent)) => {
+            pi.set_parent(PIParentInfoItem::Element(parent), infoset)
+        }
+        (ElementChildInfoItem::Comment(comment), Parent::Doc(doc_info_item)) => {
+            comment.set_parent(CommentParentInfoItem::Doc(doc_info_item), infoset)
+        }
+        (ElementChildInfoItem::Comment(comment), Parent::Element(parent)) => {
+            comment.set_parent(CommentParentInfoItem::Element(parent), infoset)
+        }
+        (ElementChildInfoItem::UER(uer), Parent::Element(parent)) => {
+            uer.set_parent(parent, infoset)
+        }
+        (ElementChildInfoItem::CharGroup(char_group), Parent::Element(parent)) => {
+            char_group.set_parent(parent, infoset)
+        }
+        (_, Parent::Doc(_)) => return Err(XIncludeError::InvalidDocumentElementInclusion),
+    }
+    Ok(())
+}
+
+/// Returns the element identified by an XPointer, trying each of its pointer
+/// parts in turn.
+///
+/// Shorthand pointers and the `element()` scheme are supported; parts using
+/// other schemes identify nothing.
+fn resolve_xpointer(
+    infoset: &InfoSet<'_>,
+    doc_info_item: DocInfoItem,
+    xpointer: &str,
+) -> Option<ElementInfoItem> {
+    let xpointer = xpointer.trim();
+    if !xpointer.contains('(') {
+        return infoset.element_by_id(xpointer);
+    }
+    let mut rest = xpointer;
+    while !rest.is_empty() {
+        let open = rest.find('(')?;
+        let scheme = rest[..open].trim();
+        let (data, len) = scheme_data(&rest[open + 1..])?;
+        rest = rest[open + 1 + len..].trim_start();
+        if scheme != "element" {
+            continue;
+        }
+        if let Some(element) = resolve_element_scheme(infoset, doc_info_item, &data) {
+            return Some(element);
+        }
+    }
+    None
+}
+
+/// Returns the unescaped data of a pointer part, and the length of its
+/// escaped form including the closing parenthesis.
+fn scheme_data(text: &str) -> Option<(String, usize)> {
+    let mut data = String::new();
+    let mut depth = 0;
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '^' => {
+                let (_, escaped) = chars.next()?;
+                data.push(escaped);
+            }
+            '(' => {
+                depth += 1;
+                data.push(c);
+            }
+            ')' if depth == 0 => return Some((data, i + 1)),
+            ')' => {
+                depth -= 1;
+                data.push(c);
+            }
+            _ => data.push(c),
+        }
+    }
+    None
+}
+
+// elementschemedata ::= (NCName childseq) | childseq
+// childseq ::= ('/' [1-9] [0-9]*)*
+fn resolve_element_scheme(
+    infoset: &InfoSet<'_>,
+    doc_info_item: DocInfoItem,
+    data: &str,
+) -> Option<ElementInfoItem> {
+    let mut steps = data.split('/');
+    let first = steps.next()?;
+    let mut element = if first.is_empty() {
+        // The document is the only child of its root, so the first step
+        // must be 1.
+        if steps.next()? != "1" {
+            return None;
+        }
+        doc_info_item.document_element(infoset)
+    } else {
+        infoset.element_by_id(first)?
+    };
+    for step in steps {
+        let n = step.parse::<usize>().ok().filter(|&n| n > 0)?;
+        element = *ChildItems::of(infoset, element).elements.get(n - 1)?;
+    }
+    Some(element)
+}
+
+/// Copies items of another info set, whose input starts at `offset` of the
+/// input of the info set receiving the copies.
+struct ItemCopier<'a> {
+    source: &'a InfoSet<'a>,
+    offset: usize,
+}
+
+impl<'a> ItemCopier<'a> {
+    fn span(&self, span: &Span) -> Span {
+        Span(span.0.start + self.offset..span.0.end + self.offset)
+    }
+
+    fn opt_span(&self, span: &Option<Span>) -> Option<Span> {
+        span.as_ref().map(|span| self.span(span))
+    }
+
+    fn cow_span(&self, span: &CowSpan) -> CowSpan {
+        match span {
+            CowSpan::Borrowed(span) => CowSpan::Borrowed(self.span(span)),
+            span => span.clone(),
+        }
+    }
+
+    fn uri(&self, uri: &Option<UriSpan>) -> Option<UriSpan> {
+        uri.as_ref().map(|uri| UriSpan(self.cow_span(&uri.0)))
+    }
+
+    /// Returns the text of `span`, a copy of a span of the source.
+    fn text<'s>(&'s self, span: &'s CowSpan) -> &'s str {
+        match span {
+            CowSpan::Borrowed(span) => {
+                &self.source.input[span.0.start - self.offset..span.0.end - self.offset]
+            }
+            CowSpan::Owned(text) => text,
+            CowSpan::Static(text) => text,
+        }
+    }
+
+    fn atom(&self, repo: &mut InfoSetData, atom: Atom) -> Atom {
+        repo.atoms.intern(self.source.atoms.resolve(atom))
+    }
+
+    fn expanded_name(&self, repo: &mut InfoSetData, name: ExpandedName) -> ExpandedName {
+        ExpandedName {
+            namespace_name: name
+                .namespace_name
+                .map(|namespace_name| self.atom(repo, namespace_name)),
+            local_name: self.atom(repo, name.local_name),
+        }
+    }
+
+    /// Sets the `xml:base` attribute of an included element so that its base
+    /// URI is kept in the including document.
+    fn fix_up_base_uri(
+        &self,
+        infoset: &mut InfoSet<'_>,
+        appended: &mut String,
+        element: ElementInfoItem,
+        base_uri: String,
+    ) {
+        let xml_base = infoset.atoms.expanded_name(Some(XML_NAMESPACE), "base");
+        let existing = element
+            .attributes(infoset)
+            .iter()
+            .copied()
+            .find(|&attribute| attribute.expanded_name(infoset) == xml_base);
+        if let Some(attribute) = existing {
+            attribute.set_normalized_value(CowSpan::Owned(base_uri), infoset);
+            return;
+        }
+        let input_len = infoset.input.len();
+        let mut span = |s: &str| {
+            let start = input_len + appended.len();
+            appended.push_str(s);
+            Span(start..start + s.len())
+        };
+        let prefix = span("xml");
+        let local_name = span("base");
+        let attribute = AttributeInfoItem::new(
+            Some(CowSpan::Static(XML_NAMESPACE)),
+            local_name,
+            Some(prefix),
+            xml_base,
+            CowSpan::Owned(base_uri),
+            AttrSpecified::Specified,
+            None,
+            element,
+            infoset,
+        );
+        element.attributes_mut(infoset).push(attribute);
+    }
+
+    /// Returns copies of `items`, and of their descendants, with `parent` as
+    /// their parent.
+    fn copy_children(
+        &self,
+        repo: &mut InfoSetData,
+        items: &[ElementChildInfoItem],
+        parent: Parent,
+    ) -> Result<Vec<ElementChildInfoItem>, XIncludeError> {
+        let mut copier = ChildCopier {
+            copier: self,
+            repo,
+            parent,
+            copies: Vec::with_capacity(items.len()),
+            result: Ok(()),
+        };
+        for &item in items {
+            copier.visit_element_child(self.source, item);
+        }
+        copier.result.map(|()| copier.copies)
+    }
+
+    /// Builds the scope of the in-scope namespaces of `element` in `repo`,
+    /// sharing the namespaces it has in common with `parent`.
+    fn copy_namespace_scope(
+        &self,
+        repo: &mut InfoSetData,
+        element: ElementInfoItem,
+        parent: ElementParentInfoItem,
+    ) -> NamespaceScope {
+        let source = self.source;
+        let in_scope_namespaces = element.in_scope_namespaces(source);
+        let atoms: Vec<_> = in_scope_namespaces
+            .iter()
+            .map(|&ns| {
+                let prefix = ns
+                    .prefix(source)
+                    .map(|prefix| repo.atoms.intern(input_str(&source.input, &prefix)));
+                (prefix, self.atom(repo, ns.namespace_atom(source)))
+            })
+            .collect();
+        let (parent, shared, missing) = match parent {
+            ElementParentInfoItem::Element(parent) => {
+                let parent = parent.namespace_scope(repo);
+                let (shared, missing) = repo.inherited_namespaces(parent, &atoms);
+                (Some(parent), shared, missing)
+            }
+            ElementParentInfoItem::Doc(_) => (None, 0, Vec::new()),
+        };
+        let mut bindings: Vec<_> = missing.into_iter().map(|prefix| (prefix, None)).collect();
+        for (&ns, &(prefix, namespace_atom)) in in_scope_namespaces.iter().zip(&atoms).skip(shared)
+        {
+            let copy = NSInfoItem::new(
+                ns.prefix(source)
+                    .as_ref()
+                    .map(|prefix| self.cow_span(prefix)),
+                self.cow_span(&ns.namespace_name(source)),
+                namespace_atom,
+                repo,
+            );
+            bindings.push((prefix, Some(copy)));
+        }
+        match parent {
+            Some(parent) if bindings.is_empty() => parent,
+            _ => repo.push_namespace_bindings(parent, bindings),
+        }
+    }
+
+    fn copy_element(
+        &self,
+        repo: &mut InfoSetData,
+        element: ElementInfoItem,
+        parent: ElementParentInfoItem,
+    ) -> ElementInfoItem {
+        let source = self.source;
+        let scope = self.copy_namespace_scope(repo, element, parent);
+        let expanded_name = self.expanded_name(repo, element.expanded_name(source));
+        let copy = ElementInfoItem::new(
+            self.span(&element.local_name(source)),
+            self.opt_span(&element.prefix(source)),
+            expanded_name,
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            scope,
+            self.uri(&element.base_uri(source)),
+            None,
+            parent,
+            repo,
+        );
+        let attributes = element
+            .attributes(source)
+            .iter()
+            .map(|&attribute| self.copy_attribute(repo, attribute, copy))
+            .collect();
+        copy.set_attributes(attributes, repo);
+        let namespace_attributes = element
+            .namespace_attributes(source)
+            .iter()
+            .map(|&attribute| self.copy_attribute(repo, attribute, copy))
+            .collect();
+        copy.set_namespace_attributes(namespace_attributes, repo);
+        copy
+    }
+
+    fn copy_attribute(
+        &self,
+        repo: &mut InfoSetData,
+        attribute: AttributeInfoItem,
+        owner_element: ElementInfoItem,
+    ) -> AttributeInfoItem {
+        let source = self.source;
+        let expanded_name = self.expanded_name(repo, attribute.expanded_name(source));
+        AttributeInfoItem::new(
+            attribute
+                .namespace_name(source)
+                .map(|namespace_name| self.cow_span(&namespace_name)),
+            self.span(&attribute.local_name(source)),
+            self.opt_span(&attribute.prefix(source)),
+            expanded_name,
+            self.cow_span(&attribute.normalized_value(source)),
+            attribute.specified(source),
+            // The references are resolved again once the inclusions are
+            // done.
+            attribute
+                .attribute_type_and_references(source)
+                .map(|type_and_references| match type_and_references {
+                    UnknownOr::Unknown => UnknownOr::Unknown,
+                    UnknownOr::Known(type_and_references) => {
+                        UnknownOr::Known(without_references(type_and_references))
+                    }
+                }),
+            owner_element,
+            repo,
+        )
+    }
+}
+
+/// Copies the visited items of the source of `copier` into `repo`, as
+/// children of `parent`, keeping the first error in `result`.
+struct ChildCopier<'c, 'a, 'r> {
+    copier: &'c ItemCopier<'a>,
+    repo: &'r mut InfoSetData,
+    parent: Parent,
+    copies: Vec<ElementChildInfoItem>,
+    result: Result<(), XIncludeError>,
+}
+
+impl ChildCopier<'_, '_, '_> {
+    /// The parent of a copied character group or entity reference, which
+    /// cannot be children of the document.
+    fn element_parent(&mut self) -> Option<ElementInfoItem> {
+        match self.parent {
+            Parent::Element(parent) => Some(parent),
+            Parent::Doc(_) => {
+                if self.result.is_ok() {
+                    self.result = Err(XIncludeError::InvalidDocumentElementInclusion);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<'a> InfoSetVisitor<'a> for ChildCopier<'_, 'a, '_> {
+    fn visit_element(&mut self, infoset: &'a InfoSet<'a>, item: ElementInfoItem) {
+        let parent = match self.parent {
+            Parent::Doc(doc_info_item) => ElementParentInfoItem::Doc(doc_info_item),
+            Parent::Element(parent) => ElementParentInfoItem::Element(parent),
+        };
+        let copy = self.copier.copy_element(self.repo, item, parent);
+        let parent = mem::replace(&mut self.parent, Parent::Element(copy));
+        let copies = mem::take(&mut self.copies);
+        for &child in item.children(infoset) {
+            self.visit_element_child(infoset, child);
+        }
+        self.parent = parent;
+        let children = mem::replace(&mut self.copies, copies);
+        copy.set_children(children, self.repo);
+        self.copies.push(ElementChildInfoItem::Element(copy));
+    }
+
+    fn visit_pi(&mut self, infoset: &'a InfoSet<'a>, item: PIInfoItem) {
+        let parent = match self.parent {
+            Parent::Doc(doc_info_item) => PIParentInfoItem::Doc(doc_info_item),
+            Parent::Element(parent) => PIParentInfoItem::Element(parent),
+        };
+        let copier = self.copier;
+        let copy = PIInfoItem::new(
+            copier.span(&item.target(infoset)),
+            item.content(infoset)
+                .map(|content| copier.cow_span(&content)),
+            copier.uri(&item.base_uri(infoset)),
+            // Notations are not carried over from the included document.
+            item.notation(infoset).map(|_| UnknownOr::Unknown),
+            parent,
+            self.repo,
+        );
+        self.copies.push(ElementChildInfoItem::PI(copy));
+    }
+
+    fn visit_comment(&mut self, infoset: &'a InfoSet<'a>, item: CommentInfoItem) {
+        let parent = match self.parent {
+            Parent::Doc(doc_info_item) => CommentParentInfoItem::Doc(doc_info_item),
+            Parent::Element(parent) => CommentParentInfoItem::Element(parent),
+        };
+        let copy = CommentInfoItem::new(
+            self.copier.cow_span(&item.content(infoset)),
+            parent,
+            self.repo,
+        );
+        self.copies.push(ElementChildInfoItem::Comment(copy));
+    }
+
+    fn visit_uer(&mut self, infoset: &'a InfoSet<'a>, item: UERInfoItem) {
+        let parent = match self.element_parent() {
+            Some(parent) => parent,
+            None => return,
+        };
+        let copier = self.copier;
+        let opt_span = |span: UnknownOr<Option<Span>>| match span {
+            UnknownOr::Unknown => UnknownOr::Unknown,
+            UnknownOr::Known(span) => UnknownOr::Known(copier.opt_span(&span)),
+        };
+        let copy = UERInfoItem::new(
+            copier.span(&item.name(infoset)),
+            opt_span(item.system_identifier(infoset)),
+            opt_span(item.public_identifier(infoset)),
+            match item.declaration_base_uri(infoset) {
+                UnknownOr::Unknown => UnknownOr::Unknown,
+                UnknownOr::Known(uri) => UnknownOr::Known(copier.uri(&uri)),
+            },
+            parent,
+            self.repo,
+        );
+        self.copies.push(ElementChildInfoItem::UER(copy));
+    }
+
+    fn visit_char_group(&mut self, infoset: &'a InfoSet<'a>, item: CharGroupInfoItem) {
+        let parent = match self.element_parent() {
+            Some(parent) => parent,
+            None => return,
+        };
+        let copy = CharGroupInfoItem::new(
+            self.copier.cow_span(&item.characters(infoset)),
+            item.element_content_whitespace(infoset),
+            parent,
+            self.repo,
+        );
+        self.copies.push(ElementChildInfoItem::CharGroup(copy));
+    }
+}
+
+fn without_references(type_and_references: AttrTypeAndReferences) -> AttrTypeAndReferences {
+    use AttrTypeAndReferences::*;
+    match type_and_references {
+        IDREF(_) => IDREF(None),
+        IDREFS(_) => IDREFS(None),
+        ENTITY(_) => ENTITY(None),
+        ENTITIES(_) => ENTITIES(None),
+        NOTATION(_) => NOTATION(None),
+        type_and_references => type_and_references,
+    }
+}
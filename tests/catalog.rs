@@ -0,0 +1,157 @@
+mod common;
+
+use common::{attribute, document_element};
+use std::path::{Path, PathBuf};
+use xmlinfoset::catalog::{Catalog, CatalogError};
+use xmlinfoset::parse::{self, ParseOptions};
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/catalog")
+}
+
+fn fixture(path: &str) -> String {
+    fixtures().join(path).to_string_lossy().into_owned()
+}
+
+fn catalog() -> Catalog {
+    Catalog::load(fixtures().join("catalog.xml")).unwrap()
+}
+
+#[test]
+fn public() {
+    let catalog = catalog();
+    // Public identifiers are compared with their white space normalized.
+    assert_eq!(
+        catalog.resolve_external_id(Some("-//Example//DTD Doc//EN"), None),
+        Some(fixture("dtd/doc.dtd"))
+    );
+    assert_eq!(
+        catalog.resolve_external_id(Some("-//Example//DTD Doc//EN"), Some("doc.dtd")),
+        Some(fixture("dtd/doc.dtd"))
+    );
+    assert_eq!(
+        catalog.resolve_external_id(Some("-//Unknown//EN"), None),
+        None
+    );
+}
+
+#[test]
+fn public_with_system_preferred() {
+    let catalog = catalog();
+    let public_id = "-//Example//DTD System Preferred//EN";
+    assert_eq!(
+        catalog.resolve_external_id(Some(public_id), None),
+        Some(fixture("dtd/system-preferred.dtd"))
+    );
+    assert_eq!(
+        catalog.resolve_external_id(Some(public_id), Some("http://example.org/x.dtd")),
+        None
+    );
+}
+
+#[test]
+fn system() {
+    assert_eq!(
+        catalog().resolve_external_id(None, Some("http://example.com/doc.dtd")),
+        Some(fixture("dtd/doc.dtd"))
+    );
+}
+
+#[test]
+fn rewrite_system() {
+    let catalog = catalog();
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("http://example.com/schemas/a.xsd")),
+        Some(format!("{}a.xsd", fixture("schemas/")))
+    );
+    // The longest matching start string wins.
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("http://example.com/schemas/v2/a.xsd")),
+        Some(format!("{}a.xsd", fixture("schemas-v2/")))
+    );
+}
+
+#[test]
+fn system_ids_are_normalized() {
+    let catalog = catalog();
+    // Characters not allowed in URIs are compared %-escaped.
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("http://example.com/my%20doc.dtd")),
+        Some(fixture("dtd/doc.dtd"))
+    );
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("http://example.com/my doc.dtd")),
+        Some(fixture("dtd/doc.dtd"))
+    );
+    // The rewrite prefix is resolved against the base URI of the catalog.
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("http://example.com/schémas/a.xsd")),
+        Some(format!("{}a.xsd", fixture("schemas-v2/")))
+    );
+}
+
+#[test]
+fn uri() {
+    let catalog = catalog();
+    assert_eq!(
+        catalog.resolve_uri("http://example.com/chapter.xml"),
+        Some(fixture("chapter.xml"))
+    );
+    assert_eq!(catalog.resolve_uri("http://example.com/doc.dtd"), None);
+}
+
+#[test]
+fn delegate_public() {
+    let catalog = catalog();
+    assert_eq!(
+        catalog.resolve_external_id(Some("-//Delegated//DTD A//EN"), None),
+        Some(fixture("dtd/delegated-a.dtd"))
+    );
+    // Delegation ends the lookup, so the entry in the next catalog is not
+    // consulted.
+    assert_eq!(
+        catalog.resolve_external_id(Some("-//Delegated//DTD B//EN"), None),
+        None
+    );
+}
+
+#[test]
+fn next_catalog_chain() {
+    let catalog = catalog();
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("http://example.com/next.dtd")),
+        Some(fixture("dtd/next.dtd"))
+    );
+    // `last.xml` is reached through `next.xml`, which also refers back to
+    // `catalog.xml`.
+    assert_eq!(
+        catalog.resolve_uri("http://example.com/last.xml"),
+        Some(fixture("last-chapter.xml"))
+    );
+    assert_eq!(catalog.resolve_uri("http://example.com/missing.xml"), None);
+}
+
+#[test]
+fn not_a_catalog() {
+    let result = Catalog::load(fixtures().join("not-a-catalog.xml"));
+    assert!(matches!(result, Err(CatalogError::NotACatalog(_))));
+    let result = Catalog::load(fixtures().join("missing.xml"));
+    assert!(matches!(result, Err(CatalogError::Io { .. })));
+}
+
+#[test]
+fn catalog_as_entity_resolver() {
+    let mut catalog = catalog();
+    let options = ParseOptions {
+        entity_resolver: Some(&mut catalog),
+        load_external_subset: true,
+        ..ParseOptions::default()
+    };
+    let input = r#"<!DOCTYPE doc SYSTEM "http://example.com/doc.dtd"><doc/>"#;
+    let (infoset, _) = parse::parse_with_options(input, options).unwrap();
+    let doc = document_element(&infoset);
+    assert_eq!(
+        attribute(&infoset, doc, "version"),
+        Some(("1.0".to_owned(), false))
+    );
+}
@@ -0,0 +1,69 @@
+#![allow(dead_code)]
+
+use xmlinfoset::resolver::{EntityResolver, ResolveError};
+use xmlinfoset::{AttrSpecified, ElementChildInfoItem, ElementInfoItem, InfoSet};
+
+/// Resolves system identifiers to fixed texts.
+pub struct MapResolver(pub Vec<(&'static str, &'static str)>);
+
+impl EntityResolver for MapResolver {
+    fn resolve_entity(
+        &mut self,
+        _public_id: Option<&str>,
+        system_id: &str,
+        _base_uri: Option<&str>,
+    ) -> Result<Vec<u8>, ResolveError> {
+        self.0
+            .iter()
+            .find(|&&(id, _)| id == system_id)
+            .map(|&(_, text)| text.as_bytes().to_vec())
+            .ok_or_else(|| ResolveError::NotFound(system_id.to_owned()))
+    }
+}
+
+pub fn document_element(infoset: &InfoSet<'_>) -> ElementInfoItem {
+    infoset.doc_info_item.unwrap().document_element(infoset)
+}
+
+pub fn local_name(infoset: &InfoSet<'_>, element: ElementInfoItem) -> String {
+    element.local_name(infoset).get(infoset).unwrap().to_owned()
+}
+
+pub fn child_elements(infoset: &InfoSet<'_>, element: ElementInfoItem) -> Vec<ElementInfoItem> {
+    element
+        .children(infoset)
+        .iter()
+        .filter_map(|&child| match child {
+            ElementChildInfoItem::Element(element) => Some(element),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The character data of the children of `element`.
+pub fn text(infoset: &InfoSet<'_>, element: ElementInfoItem) -> String {
+    let mut text = String::new();
+    for &child in element.children(infoset) {
+        if let ElementChildInfoItem::CharGroup(char_group) = child {
+            text.push_str(char_group.characters(infoset).get_ref(infoset).unwrap());
+        }
+    }
+    text
+}
+
+/// The normalized value of the attribute named `name`, and whether it was
+/// specified.
+pub fn attribute(
+    infoset: &InfoSet<'_>,
+    element: ElementInfoItem,
+    name: &str,
+) -> Option<(String, bool)> {
+    element.attributes(infoset).iter().find_map(|&attribute| {
+        if attribute.local_name(infoset).get(infoset).unwrap() != name {
+            return None;
+        }
+        let value = attribute.normalized_value(infoset);
+        let specified = matches!(attribute.specified(infoset), AttrSpecified::Specified);
+        Some((value.get_ref(infoset).unwrap().to_owned(), specified))
+    })
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::document_element;
+use xmlinfoset::parse;
+
+const INPUT: &str =
+    r#"<!DOCTYPE a [<!ENTITY e SYSTEM "e.xml">]><a xmlns:p="urn:p" p:b="1"><?t d?><!--c-->&e;</a>"#;
+
+#[test]
+fn dump_of_document() {
+    let infoset = parse::parse(INPUT).unwrap();
+    let expected = r#"Document
+  [version] 1.0
+  [character encoding scheme] <no value>
+  [standalone] <no value>
+  [base URI] <no value>
+  [all declarations processed] true
+  Document Type Declaration
+    [system identifier] <no value>
+    [public identifier] <no value>
+  Element
+    [namespace name] <no value>
+    [local name] a
+    [prefix] <no value>
+    [base URI] <no value>
+    Attribute
+      [namespace name] http://www.w3.org/2000/xmlns/
+      [local name] p
+      [prefix] xmlns
+      [normalized value] "urn:p"
+      [specified] true
+      [attribute type] <no value>
+    Attribute
+      [namespace name] urn:p
+      [local name] b
+      [prefix] p
+      [normalized value] "1"
+      [specified] true
+      [attribute type] <no value>
+    Namespace
+      [prefix] xml
+      [namespace name] http://www.w3.org/XML/1998/namespace
+    Namespace
+      [prefix] p
+      [namespace name] urn:p
+    Processing Instruction
+      [target] t
+      [content] "d"
+      [base URI] <no value>
+      [notation] <no value>
+    Comment
+      [content] "c"
+    Unexpanded Entity Reference
+      [name] e
+      [system identifier] e.xml
+      [public identifier] <no value>
+      [declaration base URI] <no value>
+"#;
+    assert_eq!(format!("{:?}", infoset), expected);
+}
+
+#[test]
+fn display_of_items() {
+    let infoset = parse::parse(INPUT).unwrap();
+    let doc_info_item = infoset.doc_info_item.unwrap();
+    assert_eq!(
+        infoset.debug_item(doc_info_item).to_string(),
+        "Document (version 1.0, 2 children)"
+    );
+    let a = document_element(&infoset);
+    assert_eq!(
+        infoset.debug_item(a).to_string(),
+        r#"Element <a xmlns:p="urn:p" p:b="1">"#
+    );
+    let attribute = a.attributes(&infoset)[0];
+    assert_eq!(
+        infoset.debug_item(attribute).to_string(),
+        r#"Attribute p:b="1" {urn:p}"#
+    );
+    let namespace = a.namespace_for_prefix(&infoset, Some("p")).unwrap();
+    assert_eq!(
+        infoset.debug_item(namespace).to_string(),
+        r#"Namespace xmlns:p="urn:p""#
+    );
+    let children = a
+        .children(&infoset)
+        .iter()
+        .map(|&child| infoset.debug_item(child).to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        children,
+        [
+            r#"PI t "d""#,
+            r#"Comment "c""#,
+            "Unexpanded entity reference &e;",
+        ]
+    );
+}
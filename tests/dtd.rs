@@ -0,0 +1,355 @@
+mod common;
+
+use common::{attribute, child_elements, document_element, local_name, text, MapResolver};
+use xmlinfoset::parse::{self, ParseError, ParseOptions};
+use xmlinfoset::{
+    AttrTypeAndReferences, DocChildInfoItem, ElementChildInfoItem, ElementInfoItem, InfoSet,
+    PIInfoItem, UnknownOr,
+};
+
+#[test]
+fn attribute_defaulted_from_parameter_entity() {
+    let infoset =
+        parse::parse(r#"<!DOCTYPE e [<!ENTITY % a "<!ATTLIST e x CDATA 'v'>"> %a;]><e/>"#).unwrap();
+    let e = document_element(&infoset);
+    assert_eq!(attribute(&infoset, e, "x"), Some(("v".to_owned(), false)));
+    // Every span of the info set can be read back.
+    assert!(!format!("{:?}", infoset).is_empty());
+}
+
+#[test]
+fn attribute_defaulted_from_external_subset() {
+    let mut resolver = MapResolver(vec![(
+        "e.dtd",
+        "<!ATTLIST e y CDATA 'w' z NMTOKENS ' a  b '>",
+    )]);
+    let options = ParseOptions {
+        entity_resolver: Some(&mut resolver),
+        load_external_subset: true,
+        ..ParseOptions::default()
+    };
+    let (infoset, _) =
+        parse::parse_with_options(r#"<!DOCTYPE e SYSTEM "e.dtd"><e z="c"/>"#, options).unwrap();
+    let e = document_element(&infoset);
+    assert_eq!(attribute(&infoset, e, "y"), Some(("w".to_owned(), false)));
+    assert_eq!(attribute(&infoset, e, "z"), Some(("c".to_owned(), true)));
+    assert!(!format!("{:?}", infoset).is_empty());
+}
+
+/// The declared type of the attribute named `name`, if any.
+fn attribute_type(
+    infoset: &InfoSet<'_>,
+    element: ElementInfoItem,
+    name: &str,
+) -> Option<&'static str> {
+    let attribute = element
+        .attributes(infoset)
+        .iter()
+        .find(|attribute| attribute.local_name(infoset).get(infoset).unwrap() == name)?;
+    match attribute.attribute_type_and_references(infoset) {
+        Some(UnknownOr::Known(attribute_type)) => Some(match attribute_type {
+            AttrTypeAndReferences::ID(_) => "ID",
+            AttrTypeAndReferences::IDREF(_) => "IDREF",
+            AttrTypeAndReferences::IDREFS(_) => "IDREFS",
+            AttrTypeAndReferences::ENTITY(_) => "ENTITY",
+            AttrTypeAndReferences::ENTITIES(_) => "ENTITIES",
+            AttrTypeAndReferences::NMTOKEN(_) => "NMTOKEN",
+            AttrTypeAndReferences::NMTOKENS(_) => "NMTOKENS",
+            AttrTypeAndReferences::NOTATION(_) => "NOTATION",
+            AttrTypeAndReferences::CDATA(_) => "CDATA",
+            AttrTypeAndReferences::ENUMERATION(_) => "ENUMERATION",
+        }),
+        _ => None,
+    }
+}
+
+#[test]
+fn attribute_list_declarations() {
+    let infoset = parse::parse(
+        "<!DOCTYPE e [\
+         <!ATTLIST e c CDATA #IMPLIED t NMTOKENS #IMPLIED i ID #IMPLIED r CDATA #REQUIRED>\
+         <!ATTLIST e f CDATA #FIXED ' a  b ' k (x|y) 'y' n IDREFS ' a  b '>\
+         <!ATTLIST e k CDATA 'z' u CDATA 'w'>\
+         <!ATTLIST o d CDATA 'v'>\
+         ]><e c='\ta  b ' t=' a&#9;  b ' r='v' u='x'/>",
+    )
+    .unwrap();
+    let e = document_element(&infoset);
+    // CDATA values only have their white space characters replaced.
+    assert_eq!(
+        attribute(&infoset, e, "c"),
+        Some((" a  b ".to_owned(), true))
+    );
+    // Tokenized values also lose leading, trailing and repeated spaces,
+    // but not the characters written as references.
+    assert_eq!(
+        attribute(&infoset, e, "t"),
+        Some(("a\t b".to_owned(), true))
+    );
+    assert_eq!(attribute(&infoset, e, "r"), Some(("v".to_owned(), true)));
+    assert_eq!(attribute(&infoset, e, "u"), Some(("x".to_owned(), true)));
+    // Fixed and default values are inserted, normalized by their type, and
+    // the first declaration of an attribute is the binding one.
+    assert_eq!(
+        attribute(&infoset, e, "f"),
+        Some((" a  b ".to_owned(), false))
+    );
+    assert_eq!(attribute(&infoset, e, "k"), Some(("y".to_owned(), false)));
+    assert_eq!(attribute(&infoset, e, "n"), Some(("a b".to_owned(), false)));
+    // Implied attributes and the ones of other elements are not.
+    assert_eq!(attribute(&infoset, e, "i"), None);
+    assert_eq!(attribute(&infoset, e, "d"), None);
+    assert_eq!(e.attributes(&infoset).len(), 7);
+    assert_eq!(attribute_type(&infoset, e, "t"), Some("NMTOKENS"));
+    assert_eq!(attribute_type(&infoset, e, "k"), Some("ENUMERATION"));
+    assert_eq!(attribute_type(&infoset, e, "n"), Some("IDREFS"));
+    assert_eq!(attribute_type(&infoset, e, "u"), Some("CDATA"));
+}
+
+#[test]
+fn undeclared_attributes_have_no_type() {
+    let infoset = parse::parse("<!DOCTYPE e [<!ATTLIST e a CDATA #IMPLIED>]><e b=' x '/>").unwrap();
+    let e = document_element(&infoset);
+    assert_eq!(attribute(&infoset, e, "b"), Some((" x ".to_owned(), true)));
+    assert_eq!(attribute_type(&infoset, e, "b"), None);
+}
+
+/// The name of the notation named by the target of `pi`, if any.
+fn pi_notation(infoset: &InfoSet<'_>, pi: PIInfoItem) -> Option<String> {
+    match pi.notation(infoset) {
+        Some(UnknownOr::Known(notation)) => {
+            Some(notation.name(infoset).get(infoset).unwrap().to_owned())
+        }
+        _ => None,
+    }
+}
+
+#[test]
+fn pi_targets_are_linked_to_notations() {
+    let infoset = parse::parse(
+        r#"<!DOCTYPE a [<!NOTATION n SYSTEM "n.exe"><?n in-dtd?>]><?n before?><a><?n inside?><?other?></a>"#,
+    )
+    .unwrap();
+    let doc_info_item = infoset.doc_info_item.unwrap();
+    let mut pis = Vec::new();
+    for &child in doc_info_item.children(&infoset) {
+        match child {
+            DocChildInfoItem::DTD(dtd) => pis.extend(dtd.children(&infoset)),
+            DocChildInfoItem::PI(pi) => pis.push(pi),
+            _ => {}
+        }
+    }
+    for &child in document_element(&infoset).children(&infoset) {
+        if let ElementChildInfoItem::PI(pi) = child {
+            pis.push(pi);
+        }
+    }
+    let notations: Vec<_> = pis
+        .into_iter()
+        .map(|pi| pi_notation(&infoset, pi))
+        .collect();
+    assert_eq!(
+        notations,
+        [
+            Some("n".to_owned()),
+            Some("n".to_owned()),
+            Some("n".to_owned()),
+            None
+        ]
+    );
+}
+
+#[test]
+fn internal_entity_with_markup_is_parsed_as_content() {
+    let infoset =
+        parse::parse(r#"<!DOCTYPE a [<!ENTITY e "<x>in</x>"><!ENTITY f "1&e;2">]><a>(&f;)</a>"#)
+            .unwrap();
+    let a = document_element(&infoset);
+    let children = child_elements(&infoset, a);
+    assert_eq!(children.len(), 1);
+    assert_eq!(local_name(&infoset, children[0]), "x");
+    assert_eq!(text(&infoset, children[0]), "in");
+    assert_eq!(text(&infoset, a), "(12)");
+}
+
+#[test]
+fn external_entity_with_markup_is_parsed_as_content() {
+    let mut resolver = MapResolver(vec![("e.xml", "<?xml encoding='UTF-8'?><x y='1'/>t")]);
+    let options = ParseOptions {
+        entity_resolver: Some(&mut resolver),
+        load_external_entities: true,
+        ..ParseOptions::default()
+    };
+    let (infoset, _) = parse::parse_with_options(
+        r#"<!DOCTYPE a [<!ENTITY e SYSTEM "e.xml">]><a xmlns:p="urn:p">&e;</a>"#,
+        options,
+    )
+    .unwrap();
+    let a = document_element(&infoset);
+    let children = child_elements(&infoset, a);
+    assert_eq!(children.len(), 1);
+    assert_eq!(local_name(&infoset, children[0]), "x");
+    assert_eq!(
+        attribute(&infoset, children[0], "y"),
+        Some(("1".to_owned(), true))
+    );
+    // The element is in the namespace scope of the reference.
+    assert!(children[0]
+        .namespace_for_prefix(&infoset, Some("p"))
+        .is_some());
+    assert_eq!(text(&infoset, a), "t");
+}
+
+#[test]
+fn recursive_entity_with_markup_is_rejected() {
+    let result = parse::parse(r#"<!DOCTYPE a [<!ENTITY e "<x>&e;</x>">]><a>&e;</a>"#);
+    assert!(matches!(result, Err(ParseError::RecursiveEntityReference)));
+}
+
+/// The notation property of the unparsed entities of `infoset`, by name of
+/// notation.
+fn ue_notations(infoset: &InfoSet<'_>) -> Vec<UnknownOr<Option<String>>> {
+    let doc_info_item = infoset.doc_info_item.unwrap();
+    doc_info_item
+        .unparsed_entities(infoset)
+        .iter()
+        .map(|ue| match ue.notation(infoset) {
+            UnknownOr::Known(notation) => {
+                UnknownOr::Known(notation.map(|n| n.name(infoset).get(infoset).unwrap().to_owned()))
+            }
+            UnknownOr::Unknown => UnknownOr::Unknown,
+        })
+        .collect()
+}
+
+#[test]
+fn unparsed_entities_are_linked_to_notations() {
+    let infoset = parse::parse(
+        r#"<!DOCTYPE a [
+            <!NOTATION n SYSTEM "n.exe">
+            <!NOTATION d SYSTEM "1.exe">
+            <!NOTATION d SYSTEM "2.exe">
+            <!ENTITY declared SYSTEM "u" NDATA n>
+            <!ENTITY undeclared SYSTEM "u" NDATA m>
+            <!ENTITY duplicate SYSTEM "u" NDATA d>
+        ]><a><?d x?></a>"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        &ue_notations(&infoset)[..],
+        [
+            UnknownOr::Known(Some(n)),
+            UnknownOr::Known(None),
+            UnknownOr::Known(None),
+        ] if n == "n"
+    ));
+    let doc_info_item = infoset.doc_info_item.unwrap();
+    assert!(doc_info_item.notations(&infoset).is_none());
+    let a = document_element(&infoset);
+    let ElementChildInfoItem::PI(pi) = a.children(&infoset)[0] else {
+        panic!("expected a processing instruction");
+    };
+    assert!(pi.notation(&infoset).is_none());
+}
+
+#[test]
+fn notations_with_skipped_declarations_are_unknown() {
+    let infoset = parse::parse(
+        r#"<!DOCTYPE a SYSTEM "a.dtd" [
+            <!NOTATION n SYSTEM "n.exe">
+            <!ENTITY declared SYSTEM "u" NDATA n>
+            <!ENTITY undeclared SYSTEM "u" NDATA m>
+        ]><?m before?><a><?n x?><?m y?></a>"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        &ue_notations(&infoset)[..],
+        [UnknownOr::Known(Some(n)), UnknownOr::Unknown] if n == "n"
+    ));
+    let doc_info_item = infoset.doc_info_item.unwrap();
+    let mut pis = Vec::new();
+    for &child in doc_info_item.children(&infoset) {
+        if let DocChildInfoItem::PI(pi) = child {
+            pis.push(pi);
+        }
+    }
+    for &child in document_element(&infoset).children(&infoset) {
+        if let ElementChildInfoItem::PI(pi) = child {
+            pis.push(pi);
+        }
+    }
+    let notations: Vec<_> = pis.iter().map(|pi| pi.notation(&infoset)).collect();
+    assert!(matches!(
+        notations[..],
+        [
+            Some(UnknownOr::Unknown),
+            Some(UnknownOr::Known(_)),
+            Some(UnknownOr::Unknown),
+        ]
+    ));
+}
+
+/// The element content whitespace property of the character groups of
+/// `element`.
+fn element_content_whitespace(
+    infoset: &InfoSet<'_>,
+    element: ElementInfoItem,
+) -> Vec<UnknownOr<Option<bool>>> {
+    element
+        .children(infoset)
+        .iter()
+        .filter_map(|&child| match child {
+            ElementChildInfoItem::CharGroup(char_group) => {
+                Some(char_group.element_content_whitespace(infoset))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn whitespace_in_element_content() {
+    let infoset =
+        parse::parse("<!DOCTYPE a [<!ELEMENT a (b)><!ELEMENT b (#PCDATA)>]><a> <b> x</b></a>")
+            .unwrap();
+    let a = document_element(&infoset);
+    assert!(matches!(
+        element_content_whitespace(&infoset, a)[..],
+        [UnknownOr::Known(Some(true))]
+    ));
+    let b = child_elements(&infoset, a)[0];
+    assert!(matches!(
+        element_content_whitespace(&infoset, b)[..],
+        [UnknownOr::Known(Some(false))]
+    ));
+}
+
+#[test]
+fn whitespace_in_mixed_content() {
+    let infoset = parse::parse("<!DOCTYPE a [<!ELEMENT a (#PCDATA|b)*>]><a> <b/></a>").unwrap();
+    let a = document_element(&infoset);
+    assert!(matches!(
+        element_content_whitespace(&infoset, a)[..],
+        [UnknownOr::Known(Some(false))]
+    ));
+}
+
+#[test]
+fn whitespace_in_undeclared_element() {
+    let infoset = parse::parse("<a> <b/>x</a>").unwrap();
+    let a = document_element(&infoset);
+    assert!(matches!(
+        element_content_whitespace(&infoset, a)[..],
+        [UnknownOr::Known(None), UnknownOr::Known(Some(false))]
+    ));
+}
+
+#[test]
+fn whitespace_with_skipped_external_subset() {
+    let infoset = parse::parse(r#"<!DOCTYPE a SYSTEM "a.dtd"><a> <b/>x</a>"#).unwrap();
+    let a = document_element(&infoset);
+    assert!(matches!(
+        element_content_whitespace(&infoset, a)[..],
+        [UnknownOr::Unknown, UnknownOr::Known(Some(false))]
+    ));
+}
@@ -0,0 +1,214 @@
+mod common;
+
+use common::{attribute, child_elements, document_element, local_name, text, MapResolver};
+use xmlinfoset::parse::{self, ParseError, ParseOptions};
+use xmlinfoset::ElementChildInfoItem;
+
+fn edit_range(input: &str, old: &str) -> std::ops::Range<usize> {
+    let start = input.find(old).unwrap();
+    start..start + old.len()
+}
+
+#[test]
+fn edit_text_of_nested_element() {
+    let input = r#"<a><b>hello</b><c x="1">x</c></a>"#;
+    let mut infoset = parse::parse(input).unwrap();
+    infoset
+        .apply_edit(edit_range(input, "hello"), "goodbye")
+        .unwrap();
+    let children = child_elements(&infoset, document_element(&infoset));
+    assert_eq!(text(&infoset, children[0]), "goodbye");
+    // The spans after the edit are moved.
+    assert_eq!(local_name(&infoset, children[1]), "c");
+    assert_eq!(text(&infoset, children[1]), "x");
+    assert_eq!(
+        attribute(&infoset, children[1], "x"),
+        Some(("1".to_owned(), true))
+    );
+}
+
+#[test]
+fn edit_moves_spans_of_every_item_kind() {
+    let input = r#"<a><b>hello</b><c xmlns:p="urn:p"><?t d?><!--e--></c></a>"#;
+    let mut infoset = parse::parse(input).unwrap();
+    infoset
+        .apply_edit(edit_range(input, "hello"), "hi")
+        .unwrap();
+    let c = child_elements(&infoset, document_element(&infoset))[1];
+    let p = c.namespace_for_prefix(&infoset, Some("p")).unwrap();
+    let prefix = p.prefix(&infoset).unwrap();
+    assert_eq!(prefix.get_ref(&infoset).unwrap(), "p");
+    let namespace_name = p.namespace_name(&infoset);
+    assert_eq!(namespace_name.get_ref(&infoset).unwrap(), "urn:p");
+    let children = c.children(&infoset);
+    match children[0] {
+        ElementChildInfoItem::PI(pi) => {
+            assert_eq!(pi.target(&infoset).get(&infoset).unwrap(), "t");
+            let content = pi.content(&infoset).unwrap();
+            assert_eq!(content.get_ref(&infoset).unwrap(), "d");
+        }
+        _ => panic!("expected a processing instruction"),
+    }
+    match children[1] {
+        ElementChildInfoItem::Comment(comment) => {
+            let content = comment.content(&infoset);
+            assert_eq!(content.get_ref(&infoset).unwrap(), "e");
+        }
+        _ => panic!("expected a comment"),
+    }
+}
+
+#[test]
+fn insert_element_with_inherited_namespace() {
+    let input = r#"<a xmlns:p="urn:p"><b>text</b></a>"#;
+    let mut infoset = parse::parse(input).unwrap();
+    let end = input.find("</b>").unwrap();
+    infoset.apply_edit(end..end, "<p:d/>").unwrap();
+    let b = child_elements(&infoset, document_element(&infoset))[0];
+    let d = child_elements(&infoset, b)[0];
+    assert_eq!(
+        infoset.atoms().lookup(Some("urn:p"), "d"),
+        Some(d.expanded_name(&infoset))
+    );
+    assert_eq!(text(&infoset, b), "text");
+}
+
+#[test]
+fn edit_of_start_tag_reparses_parent() {
+    let input = "<a><b>text</b></a>";
+    let mut infoset = parse::parse(input).unwrap();
+    // The start tag of `b` changes, so `a` is the smallest element left
+    // intact.
+    infoset
+        .apply_edit(edit_range(input, "<b>"), r#"<b y="2">"#)
+        .unwrap();
+    let b = child_elements(&infoset, document_element(&infoset))[0];
+    assert_eq!(attribute(&infoset, b, "y"), Some(("2".to_owned(), true)));
+}
+
+#[test]
+fn edit_of_document_element_reparses_document() {
+    let input = "<a>text</a>";
+    let mut infoset = parse::parse(input).unwrap();
+    infoset.apply_edit(0..input.len(), "<z>new</z>").unwrap();
+    let z = document_element(&infoset);
+    assert_eq!(local_name(&infoset, z), "z");
+    assert_eq!(text(&infoset, z), "new");
+}
+
+#[test]
+fn failed_edit_leaves_info_set_unchanged() {
+    let input = "<a><b>text</b></a>";
+    let mut infoset = parse::parse(input).unwrap();
+    assert!(infoset
+        .apply_edit(edit_range(input, "text"), "<oops")
+        .is_err());
+    let b = child_elements(&infoset, document_element(&infoset))[0];
+    assert_eq!(text(&infoset, b), "text");
+}
+
+#[test]
+fn edit_keeps_defaults_from_external_subset() {
+    let input = r#"<!DOCTYPE a SYSTEM "a.dtd"><a><b>hello</b><c/></a>"#;
+    let mut resolver = MapResolver(vec![(
+        "a.dtd",
+        "<!ATTLIST b d CDATA 'bd'><!ATTLIST c d CDATA 'cd'>",
+    )]);
+    let options = ParseOptions {
+        entity_resolver: Some(&mut resolver),
+        load_external_subset: true,
+        ..ParseOptions::default()
+    };
+    let (mut infoset, _) = parse::parse_with_options(input, options).unwrap();
+    infoset
+        .apply_edit(edit_range(input, "hello"), "<c/>")
+        .unwrap();
+    let children = child_elements(&infoset, document_element(&infoset));
+    assert_eq!(
+        attribute(&infoset, children[0], "d"),
+        Some(("bd".to_owned(), false))
+    );
+    let c = child_elements(&infoset, children[0])[0];
+    assert_eq!(attribute(&infoset, c, "d"), Some(("cd".to_owned(), false)));
+    assert_eq!(
+        attribute(&infoset, children[1], "d"),
+        Some(("cd".to_owned(), false))
+    );
+}
+
+#[test]
+fn reparse_of_document_keeps_options() {
+    let input = r#"<!DOCTYPE a SYSTEM "a.dtd"><a><b/></a>"#;
+    let mut resolver = MapResolver(vec![("a.dtd", "<!ATTLIST b d CDATA 'bd'>")]);
+    let options = ParseOptions {
+        entity_resolver: Some(&mut resolver),
+        load_external_subset: true,
+        ..ParseOptions::default()
+    };
+    let (mut infoset, _) = parse::parse_with_options(input, options).unwrap();
+    // The document element changes, so the whole document is parsed again,
+    // with the external subset read before.
+    infoset
+        .apply_edit(edit_range(input, "<a>"), r#"<a x="1">"#)
+        .unwrap();
+    assert_eq!(
+        infoset.text(),
+        r#"<!DOCTYPE a SYSTEM "a.dtd"><a x="1"><b/></a>"#
+    );
+    let a = document_element(&infoset);
+    assert_eq!(attribute(&infoset, a, "x"), Some(("1".to_owned(), true)));
+    let b = child_elements(&infoset, a)[0];
+    assert_eq!(attribute(&infoset, b, "d"), Some(("bd".to_owned(), false)));
+}
+
+#[test]
+fn successive_edits() {
+    let input = "<a><b>one</b><c>two</c></a>";
+    let mut infoset = parse::parse(input).unwrap();
+    infoset.apply_edit(edit_range(input, "one"), "1").unwrap();
+    let text_after = "<a><b>1</b><c>two</c></a>";
+    assert_eq!(infoset.text(), text_after);
+    infoset
+        .apply_edit(edit_range(text_after, "two"), "2")
+        .unwrap();
+    assert_eq!(infoset.text(), "<a><b>1</b><c>2</c></a>");
+    let children = child_elements(&infoset, document_element(&infoset));
+    assert_eq!(text(&infoset, children[0]), "1");
+    assert_eq!(text(&infoset, children[1]), "2");
+    let a = document_element(&infoset);
+    let span = a.source_span(&infoset).unwrap();
+    assert_eq!(infoset.document_range(&span), Some(0..23));
+    let span = children[1].source_span(&infoset).unwrap();
+    assert_eq!(infoset.document_range(&span), Some(11..19));
+}
+
+#[test]
+fn many_edits_of_one_element() {
+    let input = "<a><b>0</b><c/></a>";
+    let mut infoset = parse::parse(input).unwrap();
+    for i in 1..100 {
+        let text = infoset.text().into_owned();
+        let start = text.find("<b>").unwrap() + 3;
+        let end = text.find("</b>").unwrap();
+        infoset.apply_edit(start..end, &i.to_string()).unwrap();
+    }
+    assert_eq!(infoset.text(), "<a><b>99</b><c/></a>");
+    let children = child_elements(&infoset, document_element(&infoset));
+    assert_eq!(text(&infoset, children[0]), "99");
+    assert_eq!(local_name(&infoset, children[1]), "c");
+}
+
+#[test]
+fn edit_out_of_range_is_an_error() {
+    let input = "<a>é</a>";
+    let mut infoset = parse::parse(input).unwrap();
+    assert!(matches!(
+        infoset.apply_edit(0..input.len() + 1, ""),
+        Err(ParseError::InvalidEditRange)
+    ));
+    assert!(matches!(
+        infoset.apply_edit(4..5, ""),
+        Err(ParseError::InvalidEditRange)
+    ));
+    assert_eq!(infoset.text(), input);
+}
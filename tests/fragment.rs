@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use xmlinfoset::parse::{parse_fragment, FragmentContext, ParseError};
+use xmlinfoset::{ElementChildInfoItem, InfoSet};
+
+/// Describes the items as `element:<local name>`, `text:<characters>`,
+/// `comment` or `pi`.
+fn describe(infoset: &InfoSet<'_>, items: &[ElementChildInfoItem]) -> Vec<String> {
+    items
+        .iter()
+        .map(|&item| match item {
+            ElementChildInfoItem::Element(element) => {
+                format!(
+                    "element:{}",
+                    element.local_name(infoset).get(infoset).unwrap()
+                )
+            }
+            ElementChildInfoItem::CharGroup(char_group) => {
+                let characters = char_group.characters(infoset);
+                format!("text:{}", characters.get_ref(infoset).unwrap())
+            }
+            ElementChildInfoItem::Comment(_) => "comment".to_owned(),
+            ElementChildInfoItem::PI(_) => "pi".to_owned(),
+            ElementChildInfoItem::UER(_) => "uer".to_owned(),
+        })
+        .collect()
+}
+
+#[test]
+fn mixed_content() {
+    let context = FragmentContext {
+        entities: BTreeMap::from([("e".to_owned(), "entity".to_owned())]),
+        ..FragmentContext::default()
+    };
+    let input = "text <a/>&e;&amp;<!--c--><?pi?><b>x</b>";
+    let (infoset, items) = parse_fragment(input, &context).unwrap();
+    assert_eq!(
+        describe(&infoset, &items),
+        [
+            "text:text ",
+            "element:a",
+            "text:entity&",
+            "comment",
+            "pi",
+            "element:b"
+        ]
+    );
+}
+
+#[test]
+fn text_declaration_is_skipped() {
+    let input = "\u{FEFF}<?xml encoding=\"UTF-8\"?><a/>";
+    let (infoset, items) = parse_fragment(input, &FragmentContext::default()).unwrap();
+    assert_eq!(describe(&infoset, &items), ["element:a"]);
+}
+
+#[test]
+fn namespaces_from_context() {
+    let context = FragmentContext {
+        namespaces: vec![
+            (None, "urn:default".to_owned()),
+            (Some("p".to_owned()), "urn:p".to_owned()),
+        ],
+        ..FragmentContext::default()
+    };
+    let (infoset, items) = parse_fragment("<x/><p:y/>", &context).unwrap();
+    let names: Vec<_> = items
+        .iter()
+        .map(|&item| match item {
+            ElementChildInfoItem::Element(element) => element.expanded_name(&infoset),
+            _ => panic!("expected an element"),
+        })
+        .collect();
+    assert_eq!(
+        names,
+        [
+            infoset.atoms().lookup(Some("urn:default"), "x").unwrap(),
+            infoset.atoms().lookup(Some("urn:p"), "y").unwrap(),
+        ]
+    );
+    assert!(parse_fragment("<q:z/>", &context).is_err());
+}
+
+#[test]
+fn base_uri_from_context() {
+    let context = FragmentContext {
+        base_uri: Some("http://example.com/a/doc.xml".to_owned()),
+        ..FragmentContext::default()
+    };
+    let (infoset, items) = parse_fragment(r#"<x xml:base="b/"/>"#, &context).unwrap();
+    let element = match items[0] {
+        ElementChildInfoItem::Element(element) => element,
+        _ => panic!("expected an element"),
+    };
+    let base_uri = element.base_uri(&infoset).unwrap();
+    assert_eq!(
+        base_uri.get_ref(&infoset).unwrap(),
+        "http://example.com/a/b/"
+    );
+}
+
+#[test]
+fn malformed_fragment() {
+    let context = FragmentContext::default();
+    assert!(parse_fragment("<a>", &context).is_err());
+    assert!(parse_fragment("</a>", &context).is_err());
+    assert!(parse_fragment("&undefined;", &context).is_err());
+}
+
+#[test]
+fn token_error_in_fragment() {
+    let result = parse_fragment("a < b", &FragmentContext::default());
+    assert!(matches!(result, Err(ParseError::TokenError(_))));
+}
+
+#[test]
+fn fragment_items_can_be_displayed() {
+    let (infoset, items) = parse_fragment("<b/>t", &FragmentContext::default()).unwrap();
+    assert_eq!(infoset.debug_item(items[0]).to_string(), "Element <b>");
+    let doc_info_item = infoset.doc_info_item.unwrap();
+    assert_eq!(
+        infoset.debug_item(doc_info_item).to_string(),
+        "Document (version 1.0, 1 children)"
+    );
+    let dump = format!("{:?}", infoset);
+    assert!(dump.starts_with("Document\n  [version] 1.0\n"));
+    assert!(dump.contains("  [local name] b\n"));
+    assert!(dump.contains("  [character codes] \"t\"\n"));
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "json")]
+
+mod common;
+
+use common::{child_elements, document_element};
+use serde_json::json;
+use xmlinfoset::json::JsonForm;
+use xmlinfoset::{parse, InfoSet};
+
+#[test]
+fn jsonml() {
+    let input = r#"<a xmlns:p="urn:p" x="1"><p:b>t&amp;u</p:b><!--c-->v<![CDATA[w]]><?pi?></a>"#;
+    let infoset = parse::parse(input).unwrap();
+    assert_eq!(
+        infoset.to_json(JsonForm::JsonML).unwrap(),
+        json!(["a", {"xmlns:p": "urn:p", "x": "1"}, ["p:b", "t&u"], "vw"])
+    );
+}
+
+#[test]
+fn lossless_round_trip() {
+    let input = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<!DOCTYPE doc [
+  <!NOTATION gif SYSTEM "image/gif">
+  <!ENTITY logo SYSTEM "logo.gif" NDATA gif>
+  <!ATTLIST doc id ID #IMPLIED img ENTITY #IMPLIED>
+  <!ATTLIST ref to IDREF #IMPLIED>
+]>
+<?style href="s.css"?>
+<doc id="d" img="logo" xmlns="urn:default" xmlns:x="urn:x" xml:base="http://example.com/">
+  <!-- comment -->
+  <x:item x:a="1">text</x:item>
+  <ref to="d"/>
+</doc>"#;
+    let infoset = parse::parse(input).unwrap();
+    let json = infoset.to_json(JsonForm::Lossless).unwrap();
+    let read_back = InfoSet::from_json(&json).unwrap();
+    assert_eq!(read_back.to_json(JsonForm::Lossless).unwrap(), json);
+    // References are recomputed when reading the JSON back.
+    assert!(read_back.element_by_id("d").is_some());
+}
+
+#[test]
+fn lossless_in_scope_namespaces() {
+    let input = r#"<a xmlns:x="urn:x"><b xmlns:y="urn:y"><c/></b></a>"#;
+    let infoset = parse::parse(input).unwrap();
+    let json = infoset.to_json(JsonForm::Lossless).unwrap();
+    let read_back = InfoSet::from_json(&json).unwrap();
+    let b = child_elements(&read_back, document_element(&read_back))[0];
+    let c = child_elements(&read_back, b)[0];
+    let mut prefixes: Vec<_> = c
+        .in_scope_namespaces(&read_back)
+        .into_iter()
+        .map(|ns| {
+            let prefix = ns.prefix(&read_back).unwrap();
+            prefix.get_ref(&read_back).unwrap().to_owned()
+        })
+        .collect();
+    prefixes.sort();
+    assert_eq!(prefixes, ["x", "xml", "y"]);
+}
+
+#[test]
+fn lossless_redeclared_namespaces() {
+    let input = r#"<a xmlns="urn:d" xmlns:x="urn:x"><b xmlns="" xmlns:x="urn:y"><c/></b></a>"#;
+    let infoset = parse::parse(input).unwrap();
+    let json = infoset.to_json(JsonForm::Lossless).unwrap();
+    let read_back = InfoSet::from_json(&json).unwrap();
+    assert_eq!(read_back.to_json(JsonForm::Lossless).unwrap(), json);
+    let b = child_elements(&read_back, document_element(&read_back))[0];
+    let c = child_elements(&read_back, b)[0];
+    assert!(c.namespace_for_prefix(&read_back, None).is_none());
+    let x = c.namespace_for_prefix(&read_back, Some("x")).unwrap();
+    let namespace_name = x.namespace_name(&read_back);
+    assert_eq!(namespace_name.get_ref(&read_back).unwrap(), "urn:y");
+}
+
+#[test]
+fn from_json_rejects_malformed_input() {
+    assert!(InfoSet::from_json(&json!({"version": "1.0"})).is_err());
+    assert!(InfoSet::from_json(&json!([])).is_err());
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::{child_elements, document_element};
+use xmlinfoset::{parse, ElementInfoItem, InfoSet};
+
+fn namespace(
+    infoset: &InfoSet<'_>,
+    element: ElementInfoItem,
+    prefix: Option<&str>,
+) -> Option<String> {
+    let ns = element.namespace_for_prefix(infoset, prefix)?;
+    Some(
+        ns.namespace_name(infoset)
+            .get_ref(infoset)
+            .unwrap()
+            .to_owned(),
+    )
+}
+
+#[test]
+fn namespace_for_prefix_looks_through_ancestors() {
+    let infoset =
+        parse::parse(r#"<a xmlns="urn:d" xmlns:x="urn:x"><b xmlns:x="urn:y"><c/></b></a>"#)
+            .unwrap();
+    let a = document_element(&infoset);
+    let b = child_elements(&infoset, a)[0];
+    let c = child_elements(&infoset, b)[0];
+    assert_eq!(namespace(&infoset, c, None).as_deref(), Some("urn:d"));
+    assert_eq!(namespace(&infoset, a, Some("x")).as_deref(), Some("urn:x"));
+    assert_eq!(namespace(&infoset, c, Some("x")).as_deref(), Some("urn:y"));
+    assert_eq!(
+        namespace(&infoset, c, Some("xml")).as_deref(),
+        Some("http://www.w3.org/XML/1998/namespace")
+    );
+    assert_eq!(namespace(&infoset, c, Some("unknown")), None);
+    // The redeclared prefix is listed once.
+    assert_eq!(c.in_scope_namespaces(&infoset).len(), 3);
+}
+
+#[test]
+fn undeclared_default_namespace_is_out_of_scope() {
+    let infoset = parse::parse(r#"<a xmlns="urn:d"><b xmlns=""/></a>"#).unwrap();
+    let a = document_element(&infoset);
+    let b = child_elements(&infoset, a)[0];
+    assert_eq!(namespace(&infoset, a, None).as_deref(), Some("urn:d"));
+    assert_eq!(namespace(&infoset, b, None), None);
+    assert_eq!(b.in_scope_namespaces(&infoset).len(), 1);
+}
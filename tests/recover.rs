@@ -0,0 +1,72 @@
+mod common;
+
+use common::{child_elements, document_element, local_name, text};
+use xmlinfoset::parse::{self, ParseError};
+
+#[test]
+fn mismatched_end_tag_closes_inner_elements() {
+    let input = "<a><b>text</a>";
+    assert!(parse::parse(input).is_err());
+    let (infoset, errors) = parse::parse_recovering(input).unwrap();
+    assert_eq!(errors.len(), 1);
+    let a = document_element(&infoset);
+    let children = child_elements(&infoset, a);
+    assert_eq!(children.len(), 1);
+    assert_eq!(local_name(&infoset, children[0]), "b");
+    assert_eq!(text(&infoset, children[0]), "text");
+    assert_eq!(infoset.recovered_elements().len(), 1);
+}
+
+#[test]
+fn stray_end_tag_is_skipped() {
+    let (infoset, errors) = parse::parse_recovering("<a>x</b>y</a>").unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(text(&infoset, document_element(&infoset)), "xy");
+}
+
+#[test]
+fn unclosed_elements_are_closed_at_end() {
+    let (infoset, errors) = parse::parse_recovering("<a><b>").unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(infoset.recovered_elements().len(), 2);
+}
+
+#[test]
+fn additional_root_elements_are_kept() {
+    let input = "<a/><b/>";
+    assert!(parse::parse(input).is_err());
+    let (infoset, errors) = parse::parse_recovering(input).unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(local_name(&infoset, document_element(&infoset)), "a");
+    let recovered = infoset.recovered_elements();
+    assert_eq!(recovered.len(), 1);
+    assert_eq!(local_name(&infoset, recovered[0]), "b");
+}
+
+#[test]
+fn misplaced_doctype_is_reported() {
+    let input = "<a/><!DOCTYPE a>";
+    assert!(parse::parse(input).is_err());
+    let (infoset, errors) = parse::parse_recovering(input).unwrap();
+    assert!(!errors.is_empty());
+    assert_eq!(local_name(&infoset, document_element(&infoset)), "a");
+}
+
+#[test]
+fn token_errors() {
+    // Before the XML declaration.
+    assert!(matches!(
+        parse::parse("< a/>"),
+        Err(ParseError::TokenError(_))
+    ));
+    // After the document element.
+    let input = "<a/>< b";
+    assert!(matches!(
+        parse::parse(input),
+        Err(ParseError::TokenError(_))
+    ));
+    let (infoset, errors) = parse::parse_recovering(input).unwrap();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].error, ParseError::TokenError(_)));
+    assert_eq!(local_name(&infoset, document_element(&infoset)), "a");
+}
@@ -0,0 +1,165 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use common::{attribute, child_elements, document_element, local_name, text};
+use serde::{Deserialize, Serialize};
+use xmlinfoset::de::from_infoset;
+use xmlinfoset::parse;
+use xmlinfoset::ser::{to_infoset, SerializerConfig};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Format {
+    Hardcover,
+    Paperback,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "book")]
+struct Book {
+    #[serde(rename = "@id")]
+    id: u32,
+    #[serde(rename = "@tags", default)]
+    tags: Vec<String>,
+    title: String,
+    #[serde(rename = "author", default)]
+    authors: Vec<String>,
+    subtitle: Option<String>,
+    format: Format,
+}
+
+fn book() -> Book {
+    Book {
+        id: 7,
+        tags: vec!["fiction".to_owned(), "classic".to_owned()],
+        title: "Moby-Dick".to_owned(),
+        authors: vec!["Herman Melville".to_owned()],
+        subtitle: Some("The Whale".to_owned()),
+        format: Format::Paperback,
+    }
+}
+
+#[test]
+fn deserialize_parsed_document() {
+    let input = r#"<book id=" 7 " tags="fiction  classic">
+        <title>Moby-Dick</title>
+        <author>Herman Melville</author>
+        <subtitle>The Whale</subtitle>
+        <format>Paperback</format>
+    </book>"#;
+    let infoset = parse::parse(input).unwrap();
+    assert_eq!(from_infoset::<Book>(&infoset).unwrap(), book());
+}
+
+#[test]
+fn serialize_to_elements_and_attributes() {
+    let infoset = to_infoset(&book(), &SerializerConfig::default()).unwrap();
+    let root = document_element(&infoset);
+    assert_eq!(local_name(&infoset, root), "book");
+    assert_eq!(
+        attribute(&infoset, root, "tags"),
+        Some(("fiction classic".to_owned(), true))
+    );
+    let children = child_elements(&infoset, root);
+    let names: Vec<_> = children
+        .iter()
+        .map(|&child| local_name(&infoset, child))
+        .collect();
+    assert_eq!(names, ["title", "author", "subtitle", "format"]);
+    assert_eq!(text(&infoset, children[3]), "Paperback");
+}
+
+#[test]
+fn round_trip() {
+    let mut value = book();
+    value.authors.push("Anonymous".to_owned());
+    value.subtitle = None;
+    value.tags.clear();
+    let infoset = to_infoset(&value, &SerializerConfig::default()).unwrap();
+    assert_eq!(from_infoset::<Book>(&infoset).unwrap(), value);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Block {
+    #[serde(rename = "p")]
+    Paragraph(String),
+    #[serde(rename = "note")]
+    Note(String),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "section")]
+struct Section {
+    #[serde(rename = "$value")]
+    blocks: Vec<Block>,
+}
+
+fn section() -> Section {
+    Section {
+        blocks: vec![
+            Block::Paragraph("one".to_owned()),
+            Block::Note("two".to_owned()),
+            Block::Paragraph("three".to_owned()),
+        ],
+    }
+}
+
+#[test]
+fn deserialize_mixed_children() {
+    let infoset =
+        parse::parse("<section><p>one</p><note>two</note><p>three</p></section>").unwrap();
+    assert_eq!(from_infoset::<Section>(&infoset).unwrap(), section());
+}
+
+#[test]
+fn round_trip_mixed_children() {
+    let value = section();
+    let infoset = to_infoset(&value, &SerializerConfig::default()).unwrap();
+    let names: Vec<_> = child_elements(&infoset, document_element(&infoset))
+        .into_iter()
+        .map(|child| local_name(&infoset, child))
+        .collect();
+    assert_eq!(names, ["p", "note", "p"]);
+    assert_eq!(from_infoset::<Section>(&infoset).unwrap(), value);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "entry")]
+struct Entry {
+    #[serde(rename = "{urn:example}item")]
+    item: String,
+    #[serde(rename = "{}item", default)]
+    plain: Option<String>,
+}
+
+#[test]
+fn round_trip_namespaced_fields() {
+    let config = SerializerConfig {
+        namespaces: vec![("x".to_owned(), "urn:example".to_owned())],
+        ..SerializerConfig::default()
+    };
+    let value = Entry {
+        item: "namespaced".to_owned(),
+        plain: Some("plain".to_owned()),
+    };
+    let infoset = to_infoset(&value, &config).unwrap();
+    let entry = document_element(&infoset);
+    let names: Vec<_> = child_elements(&infoset, entry)
+        .into_iter()
+        .map(|child| match child.prefix(&infoset) {
+            Some(prefix) => format!(
+                "{}:{}",
+                prefix.get(&infoset).unwrap(),
+                local_name(&infoset, child)
+            ),
+            None => local_name(&infoset, child),
+        })
+        .collect();
+    assert_eq!(names, ["x:item", "item"]);
+    assert_eq!(from_infoset::<Entry>(&infoset).unwrap(), value);
+
+    let input =
+        r#"<entry xmlns:x="urn:example"><item>plain</item><x:item>namespaced</x:item></entry>"#;
+    let infoset = parse::parse(input).unwrap();
+    assert_eq!(from_infoset::<Entry>(&infoset).unwrap(), value);
+}
@@ -0,0 +1,66 @@
+use xmlinfoset::parse::{self, ParseOptions};
+
+#[test]
+fn items_are_counted() {
+    let input = concat!(
+        "<?xml version='1.0'?><!--c-->",
+        r#"<p:a xmlns:p="urn:p" xmlns="urn:d" x="1" p:y="2">"#,
+        "<b><![CDATA[c]]></b><?pi d?><c><d/></c>u</p:a>",
+    );
+    let (_, statistics) = parse::parse_with_statistics(input).unwrap();
+    assert_eq!(statistics.element_count, 4);
+    // Namespace declarations are not attributes.
+    assert_eq!(statistics.attribute_count, 2);
+    assert_eq!(statistics.text_count, 2);
+    assert_eq!(statistics.text_bytes, 2);
+    assert_eq!(statistics.comment_count, 1);
+    assert_eq!(statistics.pi_count, 1);
+    assert_eq!(statistics.max_depth, 3);
+    assert_eq!(statistics.namespace_count, 2);
+    assert_eq!(
+        statistics.prefixes.into_iter().collect::<Vec<_>>(),
+        vec!["p".to_owned()]
+    );
+    assert!(statistics.has_cdata);
+    assert!(!statistics.has_dtd);
+    assert!(!statistics.has_nonstandard_entity_reference);
+}
+
+#[test]
+fn content_and_attribute_expansions_are_counted_alike() {
+    let dtd = r#"<!DOCTYPE a [<!ENTITY e "&f;"><!ENTITY f "y">]>"#;
+    let (_, content) = parse::parse_with_statistics(&format!("{dtd}<a>&e;</a>")).unwrap();
+    let (_, attribute) = parse::parse_with_statistics(&format!(r#"{dtd}<a b="&e;"/>"#)).unwrap();
+    assert_eq!(content.entity_expansions, 2);
+    assert_eq!(attribute.entity_expansions, 2);
+    assert!(content.has_nonstandard_entity_reference);
+    assert!(attribute.has_nonstandard_entity_reference);
+}
+
+#[test]
+fn attribute_entity_expansions_are_counted() {
+    let input = r#"<!DOCTYPE a [<!ENTITY e "&f;"><!ENTITY f "y">]><a b="&e;&amp;&#33;"/>"#;
+    let (_, statistics) = parse::parse_with_statistics(input).unwrap();
+    // `&e;` and the `&f;` in its replacement text.
+    assert_eq!(statistics.entity_expansions, 2);
+    assert!(statistics.has_nonstandard_entity_reference);
+}
+
+#[test]
+fn predefined_references_are_not_expansions() {
+    let (_, statistics) = parse::parse_with_statistics(r#"<a b="&lt;&#33;"/>"#).unwrap();
+    assert_eq!(statistics.entity_expansions, 0);
+    assert!(!statistics.has_nonstandard_entity_reference);
+}
+
+#[test]
+fn undefined_references_are_not_expansions() {
+    let options = ParseOptions {
+        recover: true,
+        ..ParseOptions::default()
+    };
+    let (_, statistics) = parse::parse_with_options(r#"<a b="&u;&u;">&u;</a>"#, options).unwrap();
+    assert_eq!(statistics.recovered_errors.len(), 2);
+    assert_eq!(statistics.entity_expansions, 0);
+    assert!(statistics.has_nonstandard_entity_reference);
+}
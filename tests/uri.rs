@@ -0,0 +1,102 @@
+mod common;
+
+use common::{child_elements, document_element};
+use xmlinfoset::parse::{self, ParseOptions};
+use xmlinfoset::uri::resolve;
+use xmlinfoset::ElementInfoItem;
+
+const BASE: &str = "http://a/b/c/d;p?q";
+
+/// RFC 3986 §5.4.1.
+const NORMAL_EXAMPLES: &[(&str, &str)] = &[
+    ("g:h", "g:h"),
+    ("g", "http://a/b/c/g"),
+    ("./g", "http://a/b/c/g"),
+    ("g/", "http://a/b/c/g/"),
+    ("/g", "http://a/g"),
+    ("//g", "http://g"),
+    ("?y", "http://a/b/c/d;p?y"),
+    ("g?y", "http://a/b/c/g?y"),
+    ("#s", "http://a/b/c/d;p?q#s"),
+    ("g#s", "http://a/b/c/g#s"),
+    ("g?y#s", "http://a/b/c/g?y#s"),
+    (";x", "http://a/b/c/;x"),
+    ("g;x", "http://a/b/c/g;x"),
+    ("g;x?y#s", "http://a/b/c/g;x?y#s"),
+    ("", "http://a/b/c/d;p?q"),
+    (".", "http://a/b/c/"),
+    ("./", "http://a/b/c/"),
+    ("..", "http://a/b/"),
+    ("../", "http://a/b/"),
+    ("../g", "http://a/b/g"),
+    ("../..", "http://a/"),
+    ("../../", "http://a/"),
+    ("../../g", "http://a/g"),
+];
+
+/// RFC 3986 §5.4.2.
+const ABNORMAL_EXAMPLES: &[(&str, &str)] = &[
+    ("../../../g", "http://a/g"),
+    ("../../../../g", "http://a/g"),
+    ("/./g", "http://a/g"),
+    ("/../g", "http://a/g"),
+    ("g.", "http://a/b/c/g."),
+    (".g", "http://a/b/c/.g"),
+    ("g..", "http://a/b/c/g.."),
+    ("..g", "http://a/b/c/..g"),
+    ("./../g", "http://a/b/g"),
+    ("./g/.", "http://a/b/c/g/"),
+    ("g/./h", "http://a/b/c/g/h"),
+    ("g/../h", "http://a/b/c/h"),
+    ("g;x=1/./y", "http://a/b/c/g;x=1/y"),
+    ("g;x=1/../y", "http://a/b/c/y"),
+    ("g?y/./x", "http://a/b/c/g?y/./x"),
+    ("g?y/../x", "http://a/b/c/g?y/../x"),
+    ("g#s/./x", "http://a/b/c/g#s/./x"),
+    ("g#s/../x", "http://a/b/c/g#s/../x"),
+    ("http:g", "http:g"),
+];
+
+#[test]
+fn rfc3986_normal_examples() {
+    for &(reference, expected) in NORMAL_EXAMPLES {
+        assert_eq!(
+            resolve(BASE, reference),
+            expected,
+            "reference {:?}",
+            reference
+        );
+    }
+}
+
+#[test]
+fn rfc3986_abnormal_examples() {
+    for &(reference, expected) in ABNORMAL_EXAMPLES {
+        assert_eq!(
+            resolve(BASE, reference),
+            expected,
+            "reference {:?}",
+            reference
+        );
+    }
+}
+
+#[test]
+fn xml_base_is_resolved_against_the_parent() {
+    let input = r#"<a xml:base="http://example.com/docs/"><b xml:base="../img/"><c/></b></a>"#;
+    let options = ParseOptions {
+        base_uri: Some("http://example.com/index.xml".to_owned()),
+        ..ParseOptions::default()
+    };
+    let (infoset, _) = parse::parse_with_options(input, options).unwrap();
+    let a = document_element(&infoset);
+    let b = child_elements(&infoset, a)[0];
+    let c = child_elements(&infoset, b)[0];
+    let base_uri = |element: ElementInfoItem| {
+        let base_uri = element.base_uri(&infoset).unwrap();
+        base_uri.get_ref(&infoset).unwrap().to_owned()
+    };
+    assert_eq!(base_uri(a), "http://example.com/docs/");
+    assert_eq!(base_uri(b), "http://example.com/img/");
+    assert_eq!(base_uri(c), "http://example.com/img/");
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::{child_elements, document_element, local_name};
+use xmlinfoset::validate::validate_dtd;
+use xmlinfoset::{parse, AttrReferenceInfoItem, AttrTypeAndReferences, InfoSet, UnknownOr};
+
+fn kinds(errors: &[xmlinfoset::validate::ValidityError]) -> Vec<String> {
+    errors
+        .iter()
+        .map(|error| format!("{:?}", error.kind))
+        .collect()
+}
+
+const DTD: &str = r#"<!DOCTYPE doc [
+  <!ELEMENT doc (item+, note?)>
+  <!ELEMENT item (#PCDATA)>
+  <!ELEMENT note EMPTY>
+  <!ATTLIST doc version CDATA #FIXED "1">
+  <!ATTLIST item id ID #REQUIRED kind (a|b) "a">
+  <!ATTLIST note refs IDREFS #IMPLIED>
+]>"#;
+
+fn validate(body: &str) -> Vec<String> {
+    let input = format!("{}{}", DTD, body);
+    let infoset = parse::parse(&input).unwrap();
+    kinds(&validate_dtd(&infoset))
+}
+
+#[test]
+fn valid_document() {
+    let errors = validate(r#"<doc><item id="x">1</item><item id="y"/><note refs="x y"/></doc>"#);
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn content_and_attribute_constraints() {
+    assert_eq!(
+        validate(r#"<doc version="2"><note/><item id="x" kind="c"/><other/></doc>"#),
+        [
+            r#"InvalidContent("doc")"#,
+            r#"FixedAttributeMismatch("version")"#,
+            r#"InvalidAttributeValue("kind")"#,
+            r#"UndeclaredElement("other")"#,
+        ]
+    );
+    assert_eq!(
+        validate(r#"<doc><item>1</item></doc>"#),
+        [r#"MissingRequiredAttribute("id")"#]
+    );
+    assert_eq!(
+        validate(r#"<doc><item id="x"><note/></item></doc>"#),
+        [r#"InvalidContent("item")"#]
+    );
+}
+
+#[test]
+fn root_element_must_match_doctype() {
+    let infoset = parse::parse(r#"<!DOCTYPE doc [<!ELEMENT other EMPTY>]><other/>"#).unwrap();
+    assert_eq!(
+        kinds(&validate_dtd(&infoset)),
+        [r#"RootElementMismatch("other")"#]
+    );
+}
+
+#[test]
+fn ids_and_idrefs_are_checked_once() {
+    let input = format!(
+        "{}{}",
+        DTD, r#"<doc><item id="x"/><item id="x"/><note refs="x missing"/></doc>"#
+    );
+    let (infoset, statistics) = parse::parse_with_statistics(&input).unwrap();
+    let expected = [r#"DuplicateId("x")"#, r#"DanglingIdRef("missing")"#];
+    assert_eq!(kinds(&statistics.validity_errors), expected);
+    assert_eq!(kinds(&validate_dtd(&infoset)), expected);
+}
+
+#[test]
+fn empty_idrefs_refer_to_nothing() {
+    let input = format!("{}{}", DTD, r#"<doc><item id="x"/><note refs="  "/></doc>"#);
+    let (infoset, statistics) = parse::parse_with_statistics(&input).unwrap();
+    assert!(statistics.validity_errors.is_empty());
+    assert_eq!(
+        kinds(&validate_dtd(&infoset)),
+        [r#"InvalidAttributeValue("refs")"#]
+    );
+}
+
+fn references(infoset: &InfoSet<'_>, body_child: usize) -> Option<Vec<String>> {
+    let element = child_elements(infoset, document_element(infoset))[body_child];
+    let attribute = element.attributes(infoset)[0];
+    let references = match attribute.attribute_type_and_references(infoset) {
+        Some(UnknownOr::Known(AttrTypeAndReferences::IDREFS(references))) => references?,
+        _ => panic!("expected an IDREFS attribute"),
+    };
+    Some(
+        references
+            .into_iter()
+            .map(|reference| match reference {
+                AttrReferenceInfoItem::Element(element) => {
+                    let id = element.attributes(infoset)[0].normalized_value(infoset);
+                    id.get_ref(infoset).unwrap().to_owned()
+                }
+                _ => panic!("expected an element"),
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn idrefs_are_resolved() {
+    let input = format!(
+        "{}{}",
+        DTD, r#"<doc><item id="x"/><item id="y"/><note refs=" y  x "/><note refs="x z"/></doc>"#
+    );
+    let infoset = parse::parse(&input).unwrap();
+    let y = infoset.element_by_id("y").unwrap();
+    assert_eq!(local_name(&infoset, y), "item");
+    assert_eq!(
+        references(&infoset, 2),
+        Some(vec!["y".to_owned(), "x".to_owned()])
+    );
+    assert_eq!(references(&infoset, 3), None);
+}
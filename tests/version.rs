@@ -0,0 +1,60 @@
+mod common;
+
+use common::{attribute, child_elements, document_element, local_name, text};
+use xmlinfoset::parse::{self, ParseError, ParseOptions, UnknownVersionPolicy};
+
+#[test]
+fn names_with_xml_1_1_name_characters() {
+    // U+2C00 and U+10000 are name characters since XML 1.1, but were not in
+    // the tables of XML 1.0 before its fifth edition.
+    let input = "<?xml version=\"1.1\"?><\u{2C00}a \u{10000}=\"v\"><b\u{2C00}/></\u{2C00}a>";
+    let infoset = parse::parse(input).unwrap();
+    let root = document_element(&infoset);
+    assert_eq!(local_name(&infoset, root), "\u{2C00}a");
+    assert_eq!(
+        attribute(&infoset, root, "\u{10000}"),
+        Some(("v".to_owned(), true))
+    );
+    assert_eq!(
+        local_name(&infoset, child_elements(&infoset, root)[0]),
+        "b\u{2C00}"
+    );
+}
+
+#[test]
+fn xml_1_1_line_ends_and_control_characters() {
+    let input = "<?xml version=\"1.1\"?><a b=\"x\u{85}y\">1\u{85}2\u{2028}3&#x1;</a>";
+    let infoset = parse::parse(input).unwrap();
+    let root = document_element(&infoset);
+    assert_eq!(text(&infoset, root), "1\n2\n3\u{1}");
+    assert_eq!(
+        attribute(&infoset, root, "b"),
+        Some(("x y".to_owned(), true))
+    );
+
+    // The same document is processed with the XML 1.0 rules as version 1.0.
+    assert!(parse::parse("<a>&#x1;</a>").is_err());
+    let infoset = parse::parse("<a>1\u{85}2</a>").unwrap();
+    assert_eq!(text(&infoset, document_element(&infoset)), "1\u{85}2");
+}
+
+#[test]
+fn xml_1_1_prefix_undeclaration() {
+    let input = r#"<?xml version="1.1"?><a xmlns:p="urn:p"><b xmlns:p=""/></a>"#;
+    assert!(parse::parse(input).is_ok());
+    assert!(parse::parse(&input[21..]).is_err());
+}
+
+#[test]
+fn unknown_version_policy() {
+    let input = r#"<?xml version="1.5"?><a/>"#;
+    assert!(parse::parse(input).is_ok());
+    let options = ParseOptions {
+        unknown_version_policy: UnknownVersionPolicy::Reject,
+        ..ParseOptions::default()
+    };
+    assert!(matches!(
+        parse::parse_with_options(input, options),
+        Err(ParseError::UnsupportedVersion(version)) if version == "1.5"
+    ));
+}
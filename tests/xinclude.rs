@@ -0,0 +1,171 @@
+mod common;
+
+use common::{attribute, child_elements, document_element, local_name, text, MapResolver};
+use xmlinfoset::parse::{self, ParseOptions};
+use xmlinfoset::xinclude::{self, XIncludeError};
+use xmlinfoset::ElementInfoItem;
+
+fn process(
+    input: &str,
+    resources: &[(&'static str, &'static str)],
+) -> Result<String, XIncludeError> {
+    let mut infoset = parse::parse(input).unwrap();
+    xinclude::process(&mut infoset, &mut MapResolver(resources.to_vec()))?;
+    // Lists the children of the document element as `name(text)`, or the
+    // text alone when there are no child elements.
+    let doc = document_element(&infoset);
+    let children = child_elements(&infoset, doc);
+    if children.is_empty() {
+        return Ok(text(&infoset, doc));
+    }
+    Ok(children
+        .into_iter()
+        .map(|child| format!("{}({})", local_name(&infoset, child), text(&infoset, child)))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+fn document(include: &str) -> String {
+    format!(
+        r#"<doc xmlns:xi="http://www.w3.org/2001/XInclude">{}</doc>"#,
+        include
+    )
+}
+
+#[test]
+fn xml_inclusion() {
+    let input = document(r#"<xi:include href="part.xml"/>"#);
+    let result = process(&input, &[("part.xml", "<part>p</part>")]);
+    assert_eq!(result.unwrap(), "part(p)");
+}
+
+#[test]
+fn text_inclusion() {
+    let input = document(r#"[<xi:include href="t.txt" parse="text"/>]"#);
+    let result = process(&input, &[("t.txt", "a < b & c")]);
+    assert_eq!(result.unwrap(), "[a < b & c]");
+}
+
+#[test]
+fn element_scheme_pointer() {
+    let input = document(r#"<xi:include href="part.xml" xpointer="element(/1/2)"/>"#);
+    let result = process(&input, &[("part.xml", "<r><a>1</a><b>2</b></r>")]);
+    assert_eq!(result.unwrap(), "b(2)");
+}
+
+#[test]
+fn fallback_on_resource_error() {
+    let input = document(
+        r#"<xi:include href="missing.xml"><xi:fallback><f>fallback</f></xi:fallback></xi:include>"#,
+    );
+    assert_eq!(process(&input, &[]).unwrap(), "f(fallback)");
+}
+
+#[test]
+fn resource_error_without_fallback() {
+    let input = document(r#"<xi:include href="missing.xml"/>"#);
+    assert!(matches!(
+        process(&input, &[]),
+        Err(XIncludeError::Resolve(_))
+    ));
+}
+
+#[test]
+fn nested_inclusion() {
+    let input = document(r#"<xi:include href="outer.xml"/>"#);
+    let resources = [
+        (
+            "outer.xml",
+            r#"<outer xmlns:xi="http://www.w3.org/2001/XInclude"><xi:include href="inner.xml"/></outer>"#,
+        ),
+        ("inner.xml", "<inner>i</inner>"),
+    ];
+    let mut infoset = parse::parse(&input).unwrap();
+    xinclude::process(&mut infoset, &mut MapResolver(resources.to_vec())).unwrap();
+    let outer = child_elements(&infoset, document_element(&infoset))[0];
+    let inner = child_elements(&infoset, outer)[0];
+    assert_eq!(local_name(&infoset, inner), "inner");
+    assert_eq!(text(&infoset, inner), "i");
+}
+
+#[test]
+fn inclusion_from_nested_directories() {
+    let input = document(r#"<xi:include href="sub/outer.xml"/>"#);
+    let resources = [
+        (
+            "sub/outer.xml",
+            r#"<outer xmlns:xi="http://www.w3.org/2001/XInclude"><xi:include href="deeper/inner.xml"/></outer>"#,
+        ),
+        ("deeper/inner.xml", "<inner>i</inner>"),
+    ];
+    let options = ParseOptions {
+        base_uri: Some("http://example.org/doc.xml".to_owned()),
+        ..ParseOptions::default()
+    };
+    let (mut infoset, _) = parse::parse_with_options(&input, options).unwrap();
+    xinclude::process(&mut infoset, &mut MapResolver(resources.to_vec())).unwrap();
+    let base_uri = |element: ElementInfoItem| {
+        let base_uri = element.base_uri(&infoset).unwrap();
+        base_uri.0.get_ref(&infoset).unwrap().to_owned()
+    };
+    let outer = child_elements(&infoset, document_element(&infoset))[0];
+    assert_eq!(
+        attribute(&infoset, outer, "base"),
+        Some(("http://example.org/sub/outer.xml".to_owned(), true))
+    );
+    assert_eq!(base_uri(outer), "http://example.org/sub/outer.xml");
+    let inner = child_elements(&infoset, outer)[0];
+    assert_eq!(
+        attribute(&infoset, inner, "base"),
+        Some(("http://example.org/sub/deeper/inner.xml".to_owned(), true))
+    );
+    assert_eq!(base_uri(inner), "http://example.org/sub/deeper/inner.xml");
+    assert_eq!(text(&infoset, inner), "i");
+}
+
+#[test]
+fn inclusion_from_edited_document() {
+    let input = document(r#"<p>x</p><xi:include xpointer="element(/1/1)"/>"#);
+    let mut infoset = parse::parse(&input).unwrap();
+    let start = input.find("x<").unwrap();
+    infoset.apply_edit(start..start + 1, "y").unwrap();
+    xinclude::process(&mut infoset, &mut MapResolver(Vec::new())).unwrap();
+    let doc = document_element(&infoset);
+    let children: Vec<_> = child_elements(&infoset, doc)
+        .into_iter()
+        .map(|child| format!("{}({})", local_name(&infoset, child), text(&infoset, child)))
+        .collect();
+    assert_eq!(children, ["p(y)", "p(y)"]);
+}
+
+#[test]
+fn inclusion_loop() {
+    let input = document(r#"<xi:include href="a.xml"/>"#);
+    let resources = [(
+        "a.xml",
+        r#"<a xmlns:xi="http://www.w3.org/2001/XInclude"><xi:include href="a.xml"/></a>"#,
+    )];
+    assert!(matches!(
+        process(&input, &resources),
+        Err(XIncludeError::InclusionLoop(_))
+    ));
+}
+
+#[test]
+fn invalid_include_elements() {
+    let input = document(r#"<xi:include href="part.xml" parse="html"/>"#);
+    assert!(matches!(
+        process(&input, &[]),
+        Err(XIncludeError::InvalidParseAttribute(_))
+    ));
+    let input = document(r#"<xi:include href="part.xml#frag"/>"#);
+    assert!(matches!(
+        process(&input, &[]),
+        Err(XIncludeError::FragmentInHref)
+    ));
+    let input = document("<xi:fallback/>");
+    assert!(matches!(
+        process(&input, &[]),
+        Err(XIncludeError::MisplacedFallback)
+    ));
+}